@@ -0,0 +1,124 @@
+use geodiff_rs::{
+    shapes::{Circle, Ellipse, Polyline},
+    transform::Transformable,
+    NodeCollection, Point3, Rectangle,
+};
+use uuid::Uuid;
+
+/// Relative weights of the node types in a generated collection.
+#[derive(Clone, Copy, Debug)]
+pub struct NodeMix {
+    pub points: u32,
+    pub rectangles: u32,
+    pub circles: u32,
+    pub ellipses: u32,
+    pub polylines: u32,
+}
+
+impl Default for NodeMix {
+    fn default() -> Self {
+        Self {
+            points: 4,
+            rectangles: 3,
+            circles: 1,
+            ellipses: 1,
+            polylines: 1,
+        }
+    }
+}
+
+/// A collection of `size` nodes, cycling through the types of `mix` by their weights.
+/// Positions depend on the index only, so two fixtures differ in their uuids alone.
+pub fn collection(size: usize, mix: NodeMix) -> NodeCollection {
+    let kinds: Vec<u32> = [
+        mix.points,
+        mix.rectangles,
+        mix.circles,
+        mix.ellipses,
+        mix.polylines,
+    ]
+    .iter()
+    .enumerate()
+    .flat_map(|(kind, weight)| std::iter::repeat_n(kind as u32, *weight as usize))
+    .collect();
+    assert!(!kinds.is_empty(), "the node mix needs a positive weight");
+
+    let mut collection = NodeCollection::with_capacity(size);
+    for i in 0..size {
+        let (x, y) = ((i % 100) as f64 * 10.0, (i / 100) as f64 * 10.0);
+        let size = 1.0 + (i % 7) as f64;
+        match kinds[i % kinds.len()] {
+            0 => {
+                let mut point = Point3::new();
+                point.x = x;
+                point.y = y;
+                collection
+                    .push(Box::new(point))
+                    .expect("fresh collections hold no locks");
+            }
+            1 => {
+                let mut rectangle = Rectangle::new();
+                rectangle.anchor_mut().translate(x, y, 0.0);
+                *rectangle.width_mut() = size;
+                *rectangle.height_mut() = size * 2.0;
+                collection
+                    .push(Box::new(rectangle))
+                    .expect("fresh collections hold no locks");
+            }
+            2 => {
+                let mut circle = Circle::new();
+                circle.translate(x, y, 0.0);
+                *circle.radius_mut() = size;
+                collection
+                    .push(Box::new(circle))
+                    .expect("fresh collections hold no locks");
+            }
+            3 => {
+                let mut ellipse = Ellipse::new();
+                ellipse.translate(x, y, 0.0);
+                *ellipse.radius_x_mut() = size;
+                *ellipse.radius_y_mut() = size / 2.0;
+                collection
+                    .push(Box::new(ellipse))
+                    .expect("fresh collections hold no locks");
+            }
+            _ => {
+                let mut polyline = Polyline::new();
+                for j in 0..8 {
+                    polyline.push(x + j as f64, y + (j % 2) as f64, 0.0);
+                }
+                collection
+                    .push(Box::new(polyline))
+                    .expect("fresh collections hold no locks");
+            }
+        }
+    }
+    collection
+}
+
+/// A copy of `collection` with every `stride`th node moved.
+pub fn edited(collection: &NodeCollection, stride: usize) -> NodeCollection {
+    let mut edited = snapshot_serde(collection);
+    let mut ids: Vec<Uuid> = edited.iter().map(|n| n.uuid()).collect();
+    ids.sort();
+    let moved: Vec<Uuid> = ids.into_iter().step_by(stride).collect();
+    // Fixtures hold no locks, so no node is skipped.
+    edited.translate_nodes(&moved, 0.5, 0.0, 0.0);
+    edited
+}
+
+/// Copies the collection node by node.
+pub fn snapshot_clone(collection: &NodeCollection) -> NodeCollection {
+    let mut copy = NodeCollection::with_capacity(collection.len());
+    for node in collection.iter() {
+        copy.push(node.clone_box())
+            .expect("fresh collections hold no locks");
+    }
+    copy
+}
+
+/// Copies the collection through its json form.
+pub fn snapshot_serde(collection: &NodeCollection) -> NodeCollection {
+    NodeCollection::from_value(collection.to_value().expect("fixtures serialize"))
+        .expect("fixtures deserialize")
+}
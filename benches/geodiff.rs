@@ -0,0 +1,73 @@
+mod fixture;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use geodiff_rs::NodeCollection;
+use uuid::Uuid;
+
+use fixture::NodeMix;
+
+const SIZE: usize = 10_000;
+/// Every hundredth node differs between the diffed collections.
+const EDIT_STRIDE: usize = 100;
+/// Every thousandth node differs, 10 changes in all.
+const FEW_EDITS_STRIDE: usize = 1_000;
+
+fn serialization(c: &mut Criterion) {
+    let collection = fixture::collection(SIZE, NodeMix::default());
+    let json = collection.to_json_string(false).unwrap();
+
+    let mut group = c.benchmark_group("serialization");
+    group.bench_function("serialize", |b| {
+        b.iter(|| collection.to_json_string(false).unwrap())
+    });
+    group.bench_function("deserialize", |b| {
+        b.iter(|| NodeCollection::from_json_string(&json).unwrap())
+    });
+    group.finish();
+}
+
+fn diff(c: &mut Criterion) {
+    let before = fixture::collection(SIZE, NodeMix::default());
+    let after = fixture::edited(&before, EDIT_STRIDE);
+
+    let mut group = c.benchmark_group("diff");
+    group.bench_function("json_tree", |b| b.iter(|| before.diff(&after)));
+    group.bench_function("streaming", |b| b.iter(|| before.diff_streaming(&after)));
+    group.finish();
+}
+
+/// Diffs node by node like `diff_streaming`, but without skipping the nodes
+/// with equal content hashes. Leaves out the bookkeeping `diff_streaming` diffs
+/// as well, so the comparison favors this side.
+fn diff_every_node(before: &NodeCollection, after: &NodeCollection) -> usize {
+    let uuids: Vec<Uuid> = before.iter().map(|n| n.uuid()).collect();
+    uuids
+        .iter()
+        .filter_map(|uuid| before.diff_node(after, uuid).unwrap())
+        .count()
+}
+
+fn diff_few_changes(c: &mut Criterion) {
+    let before = fixture::collection(SIZE, NodeMix::default());
+    let after = fixture::edited(&before, FEW_EDITS_STRIDE);
+    assert_eq!(diff_every_node(&before, &after), 10);
+
+    let mut group = c.benchmark_group("diff_10_changes");
+    group.bench_function("every_node", |b| {
+        b.iter(|| diff_every_node(&before, &after))
+    });
+    group.bench_function("hash_skip", |b| b.iter(|| before.diff_streaming(&after)));
+    group.finish();
+}
+
+fn snapshot(c: &mut Criterion) {
+    let collection = fixture::collection(SIZE, NodeMix::default());
+
+    let mut group = c.benchmark_group("snapshot");
+    group.bench_function("clone", |b| b.iter(|| fixture::snapshot_clone(&collection)));
+    group.bench_function("serde", |b| b.iter(|| fixture::snapshot_serde(&collection)));
+    group.finish();
+}
+
+criterion_group!(benches, serialization, diff, diff_few_changes, snapshot);
+criterion_main!(benches);
@@ -0,0 +1,336 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    diff::{compare_paths, Change, ChangePath, Diff},
+    error::GeodiffError,
+    events::NodeEvent,
+    lock::LockInfo,
+    minimize::moved_to,
+    NodeCollection,
+};
+
+/// Why a change of a diff can not be applied to a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApplyError {
+    /// The change targets a node the collection does not hold.
+    MissingNode { path: ChangePath },
+    /// The change targets a value the collection does not hold.
+    MissingValue { path: ChangePath },
+    /// The change adds a value the collection already holds.
+    AlreadyPresent { path: ChangePath },
+    /// The change was recorded against a node of a different type.
+    TypeMismatch {
+        path: ChangePath,
+        expected: String,
+        found: String,
+    },
+    /// A float of the node is NaN or infinite, which the diff can not match against.
+    NonFiniteValue { uuid: Uuid, field: String },
+    /// The change targets a locked node, see [`NodeCollection::lock`].
+    Locked { path: ChangePath, info: LockInfo },
+    /// The patched collection failed to deserialize.
+    Invalid(String),
+}
+
+impl Display for ApplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApplyError::MissingNode { path } => write!(f, "no node at {}", path),
+            ApplyError::MissingValue { path } => write!(f, "no value at {}", path),
+            ApplyError::AlreadyPresent { path } => write!(f, "value already present at {}", path),
+            ApplyError::TypeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(f, "expected a {} at {}, found a {}", expected, path, found),
+            ApplyError::NonFiniteValue { uuid, field } => {
+                write!(f, "non finite value in {} of node {}", field, uuid)
+            }
+            ApplyError::Locked { path, info } => {
+                write!(f, "node at {} is locked: {}", path, info.reason)
+            }
+            ApplyError::Invalid(reason) => write!(f, "patched collection is invalid: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+pub(crate) fn value_at<'a>(value: &'a Value, segments: &[String]) -> Option<&'a Value> {
+    segments.iter().try_fold(value, |v, segment| match v {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+pub(crate) fn value_at_mut<'a>(value: &'a mut Value, segments: &[String]) -> Option<&'a mut Value> {
+    segments.iter().try_fold(value, |v, segment| match v {
+        Value::Object(map) => map.get_mut(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+        _ => None,
+    })
+}
+
+/// Writes `change` into `value`, assuming [`NodeCollection::can_apply`] accepted it.
+pub(crate) fn apply_change(value: &mut Value, change: &Change) {
+    let segments = change.path().segments();
+    let Some((last, parent)) = segments.split_last() else {
+        if let Some(after) = change.after() {
+            *value = after.clone();
+        }
+        return;
+    };
+    let Some(parent) = value_at_mut(value, parent) else {
+        return;
+    };
+    if let (Value::Object(map), Some(uuid), Some(after)) =
+        (&mut *parent, moved_to(change), change.after())
+    {
+        map.remove(last);
+        map.insert(uuid.to_string(), after.clone());
+        return;
+    }
+
+    match (parent, change) {
+        (Value::Object(map), Change::Removed { .. }) => {
+            map.remove(last);
+        }
+        (
+            Value::Object(map),
+            Change::Added { value, .. }
+            | Change::Modified { after: value, .. }
+            | Change::Operation { after: value, .. },
+        ) => {
+            map.insert(last.clone(), value.clone());
+        }
+        (Value::Array(items), change) => {
+            let Ok(index) = last.parse::<usize>() else {
+                return;
+            };
+            match change {
+                Change::Removed { .. } if index < items.len() => {
+                    items.remove(index);
+                }
+                Change::Added { value, .. } if index <= items.len() => {
+                    items.insert(index, value.clone())
+                }
+                Change::Modified { after, .. } | Change::Operation { after, .. }
+                    if index < items.len() =>
+                {
+                    items[index] = after.clone()
+                }
+                _ => (),
+            }
+        }
+        _ => (),
+    }
+}
+
+/// The changes in the order to apply them in: additions and modifications
+/// as recorded, then removals back to front so array indices stay valid.
+pub(crate) fn apply_order(changes: &[Change]) -> Vec<&Change> {
+    let (mut removals, mut ordered): (Vec<&Change>, Vec<&Change>) = changes
+        .iter()
+        .partition(|c| matches!(c, Change::Removed { .. }));
+    removals.sort_by(|a, b| compare_paths(b.path(), a.path()));
+    ordered.extend(removals);
+    ordered
+}
+
+impl NodeCollection {
+    /// Checks whether `diff` applies cleanly to this collection, without modifying it.
+    ///
+    /// Every change is checked: the nodes it targets must exist unlocked with the type
+    /// recorded in the diff, removed and modified values must exist and added ones must not.
+    /// All problems found are returned. A collection holding NaN or infinite floats
+    /// fails with the first of them instead, see [`NodeCollection::check_finite`].
+    pub fn can_apply(&self, diff: &Diff) -> Result<(), Vec<ApplyError>> {
+        if let Err(GeodiffError::NonFiniteValue { uuid, field }) = self.check_finite() {
+            return Err(vec![ApplyError::NonFiniteValue { uuid, field }]);
+        }
+        let value = serde_json::to_value(self).expect("collections serialize to json");
+        let errors: Vec<ApplyError> = diff
+            .changes()
+            .iter()
+            .filter_map(|change| self.check_change(&value, diff, change))
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_change(&self, value: &Value, diff: &Diff, change: &Change) -> Option<ApplyError> {
+        let path = change.path();
+        let adds_node = matches!(change, Change::Added { .. }) && path.field().is_empty();
+
+        if let (Some("nodes"), Some(uuid), false) = (path.section(), path.node(), adds_node) {
+            if let Some(info) = self.lock_info(&uuid) {
+                return Some(ApplyError::Locked {
+                    path: path.clone(),
+                    info: info.clone(),
+                });
+            }
+            let Some(node) = self.nodes.get(&uuid) else {
+                return Some(ApplyError::MissingNode { path: path.clone() });
+            };
+            match diff.node_type(&uuid) {
+                Some(expected) if expected != node.type_tag() => {
+                    return Some(ApplyError::TypeMismatch {
+                        path: path.clone(),
+                        expected: expected.to_string(),
+                        found: node.type_tag().to_string(),
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(uuid) = moved_to(change) {
+            let to = ChangePath::node_root(&uuid);
+            if value_at(value, to.segments()).is_some() {
+                return Some(ApplyError::AlreadyPresent { path: to });
+            }
+        }
+
+        let segments = path.segments();
+        let exists = value_at(value, segments).is_some();
+        match change {
+            Change::Added { .. } => {
+                let parent = segments.split_last().map(|(_, parent)| parent);
+                match parent.and_then(|p| value_at(value, p)) {
+                    None => Some(ApplyError::MissingValue { path: path.clone() }),
+                    // array elements shift to make room
+                    Some(Value::Array(_)) => None,
+                    Some(_) if exists => Some(ApplyError::AlreadyPresent { path: path.clone() }),
+                    Some(_) => None,
+                }
+            }
+            Change::Removed { .. } | Change::Modified { .. } | Change::Operation { .. }
+                if !exists =>
+            {
+                Some(ApplyError::MissingValue { path: path.clone() })
+            }
+            _ => None,
+        }
+    }
+
+    /// Applies `diff` to this collection, after checking it with [`NodeCollection::can_apply`].
+    /// On error the collection is left unchanged.
+    pub fn apply(&mut self, diff: &Diff) -> Result<(), Vec<ApplyError>> {
+        self.can_apply(diff)?;
+
+        let mut value = serde_json::to_value(&*self).expect("collections serialize to json");
+        for change in apply_order(diff.changes()) {
+            apply_change(&mut value, change);
+        }
+        let applied: NodeCollection =
+            serde_json::from_value(value).map_err(|e| vec![ApplyError::Invalid(e.to_string())])?;
+
+        let touched: BTreeSet<Uuid> = diff
+            .changes()
+            .iter()
+            .filter(|c| c.path().section() == Some("nodes"))
+            .flat_map(|c| c.path().node().into_iter().chain(moved_to(c)))
+            .collect();
+        self.replace_with(applied, touched);
+        Ok(())
+    }
+
+    /// Takes over the nodes and bookkeeping of `applied`, emitting an event
+    /// for each of the `touched` nodes. Nodes `applied` holds a tombstone for are
+    /// dropped, unless `applied` adds them back, and indices the json left
+    /// inconsistent are repaired, see [`NodeCollection::settle_indices`].
+    pub(crate) fn replace_with(&mut self, mut applied: NodeCollection, touched: BTreeSet<Uuid>) {
+        let readded: BTreeSet<Uuid> = (touched.iter())
+            .filter(|id| !self.nodes.contains_key(id) && applied.nodes.contains_key(id))
+            .copied()
+            .collect();
+        applied.bury_tombstoned(&readded);
+        applied.settle_indices(self);
+        let events: Vec<NodeEvent> = touched
+            .into_iter()
+            .filter_map(|uuid| {
+                match (
+                    self.nodes.contains_key(&uuid),
+                    applied.nodes.contains_key(&uuid),
+                ) {
+                    (false, false) => None,
+                    (false, true) => Some(NodeEvent::Added(uuid)),
+                    (true, false) => Some(NodeEvent::Removed(uuid)),
+                    (true, true) => Some(NodeEvent::Modified(uuid)),
+                }
+            })
+            .collect();
+
+        self.nodes = applied.nodes;
+        self.bookkeeping = applied.bookkeeping;
+        self.invalidate_bounds();
+        self.invalidate_spatial();
+        for event in events {
+            self.emit(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3, Rectangle};
+
+    /// A collection holding one rectangle, and its uuid.
+    fn with_rectangle() -> (NodeCollection, Uuid) {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        (collection, id)
+    }
+
+    #[test]
+    fn can_apply_reports_a_missing_node_without_modifying_anything() {
+        let (before, id) = with_rectangle();
+        let mut after = before.snapshot();
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 3.0)
+            .unwrap();
+        let diff = before.diff(&after).unwrap();
+
+        let empty = NodeCollection::new();
+        let errors = empty.can_apply(&diff).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![ApplyError::MissingNode {
+                path: ChangePath::node_root(&id).join("width"),
+            }]
+        );
+        assert!(empty.is_empty());
+        assert_eq!(before.can_apply(&diff), Ok(()));
+    }
+
+    #[test]
+    fn an_add_diff_places_the_node_at_its_original_index() {
+        let mut full = NodeCollection::new();
+        let points = [Point3::new(), Point3::new(), Point3::new()];
+        let ids = points.each_ref().map(|p| p.uuid());
+        for point in points {
+            full.push(Box::new(point)).unwrap();
+        }
+        let mut without = full.snapshot();
+        without.remove(&ids[1]).unwrap();
+        let add = without.diff(&full).unwrap();
+
+        without.apply(&add).unwrap();
+
+        assert_eq!(without.index_of(&ids[1]), Some(1));
+        assert_eq!(without.ordered_for_draw(), ids);
+        let order: Vec<Option<Uuid>> = (0..3).map(|i| without.uuid_of(i)).collect();
+        assert_eq!(order, ids.map(Some));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    diff::{Change, ChangePath},
+    events::NodeEvent,
+    fields::node_fields,
+    NodeCollection,
+};
+
+/// Who edited which fields of the nodes, see [`NodeCollection::blame`].
+#[derive(Default)]
+pub(crate) struct Authorship {
+    author: Option<String>,
+    /// The nodes as of the last edit, kept once an author was first set.
+    seen: Option<HashMap<Uuid, Value>>,
+    blame: HashMap<Uuid, HashMap<String, String>>,
+}
+
+/// The `/` separated paths of all values in `value` that are neither objects nor arrays.
+fn leaf_fields(value: &Value, path: &ChangePath, fields: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                leaf_fields(value, &path.join(key.clone()), fields);
+            }
+        }
+        Value::Array(items) => {
+            for (i, value) in items.iter().enumerate() {
+                leaf_fields(value, &path.join(i.to_string()), fields);
+            }
+        }
+        _ => fields.push(path.to_string()),
+    }
+}
+
+impl NodeCollection {
+    /// Attributes all following edits to `author`, until it is changed or
+    /// reset to `None`.
+    ///
+    /// From the first author on the collection compares every edited node
+    /// against its previous state to find the fields that changed. Edits
+    /// through [`NodeCollection::try_get_typed_mut`] emit no event, so
+    /// they are attributed to the author of the next edit of the node.
+    pub fn set_current_author(&mut self, author: Option<String>) {
+        if self.authorship.seen.is_none() {
+            let seen = self
+                .nodes
+                .iter()
+                .map(|(id, n)| (*id, Value::Object(node_fields(n.as_ref()))))
+                .collect();
+            self.authorship.seen = Some(seen);
+        }
+        self.authorship.author = author;
+    }
+
+    pub fn current_author(&self) -> Option<&str> {
+        self.authorship.author.as_deref()
+    }
+
+    /// The author who last changed each field of the node, keyed by the
+    /// `/` separated field path. Fields changed without an author are left out.
+    pub fn blame(&self, key: &Uuid) -> HashMap<String, String> {
+        self.authorship.blame.get(key).cloned().unwrap_or_default()
+    }
+
+    /// Updates the blame for the nodes `event` is about.
+    pub(crate) fn attribute(&mut self, event: &NodeEvent) {
+        let Some(seen) = &mut self.authorship.seen else {
+            return;
+        };
+
+        for uuid in event.uuids() {
+            let before = seen.remove(uuid);
+            let Some(node) = self.nodes.get(uuid) else {
+                self.authorship.blame.remove(uuid);
+                continue;
+            };
+            let after = Value::Object(node_fields(node.as_ref()));
+
+            let mut fields = Vec::new();
+            match &before {
+                Some(before) => fields.extend(
+                    Change::record(&ChangePath::default(), before, &after)
+                        .iter()
+                        .map(|c| c.path().to_string()),
+                ),
+                None => leaf_fields(&after, &ChangePath::default(), &mut fields),
+            }
+            let blame = self.authorship.blame.entry(*uuid).or_default();
+            for field in fields {
+                match &self.authorship.author {
+                    Some(author) => blame.insert(field, author.clone()),
+                    None => blame.remove(&field),
+                };
+            }
+            seen.insert(*uuid, after);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Rectangle};
+
+    /// A collection holding a rectangle added without an author, and its uuid.
+    fn with_rectangle() -> (NodeCollection, Uuid) {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        (collection, id)
+    }
+
+    fn blame_of(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(field, author)| (field.to_string(), author.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn edits_by_two_authors_blame_each_for_their_fields() {
+        let (mut collection, id) = with_rectangle();
+
+        collection.set_current_author(Some("ada".to_string()));
+        collection
+            .update(&id, |r: &mut Rectangle| {
+                *r.width_mut() = 2.0;
+                *r.height_mut() = 1.0;
+            })
+            .unwrap();
+        collection.set_current_author(Some("bob".to_string()));
+        collection
+            .update(&id, |r: &mut Rectangle| {
+                *r.width_mut() = 3.0;
+                r.anchor_mut().x = 1.0;
+            })
+            .unwrap();
+
+        assert_eq!(
+            collection.blame(&id),
+            blame_of(&[("width", "bob"), ("height", "ada"), ("anchor/x", "bob")])
+        );
+    }
+
+    #[test]
+    fn node_changes_carry_the_current_author() {
+        let (before, id) = with_rectangle();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after.set_current_author(Some("ada".to_string()));
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 2.0)
+            .unwrap();
+
+        let change = before.diff_node(&after, &id).unwrap().unwrap();
+
+        assert_eq!(change.author.as_deref(), Some("ada"));
+    }
+}
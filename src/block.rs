@@ -0,0 +1,402 @@
+use std::{any::Any, fmt::Display};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    concrete_node, concrete_node_mut,
+    extra::ExtraFields,
+    factory::{NodeFactory, V4Factory},
+    fields::{set_field_by_json, FieldError},
+    lock::EditError,
+    schema::register_schema,
+    transform::Transform,
+    GeometryNode, NodeCollection,
+};
+
+/// A group of other nodes of the collection, placed by a common transform.
+///
+/// The members keep their own coordinates; the block's transform applies on top
+/// of them when the block is rendered or exploded.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Block {
+    pub(crate) members: Vec<Uuid>,
+    #[serde(default)]
+    pub(crate) transform: Transform,
+    pub(crate) uuid: Uuid,
+    #[serde(flatten)]
+    pub(crate) extra: ExtraFields,
+}
+
+impl Block {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            members: Vec::new(),
+            transform: Transform::identity(),
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    pub fn members(&self) -> &[Uuid] {
+        &self.members
+    }
+
+    pub fn members_mut(&mut self) -> &mut Vec<Uuid> {
+        &mut self.members
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    /// Copies of the members found in `collection`, with the block transform baked in.
+    /// Nested blocks get the transform composed into their own.
+    pub fn world_members(&self, collection: &NodeCollection) -> Vec<Box<dyn GeometryNode>> {
+        self.members
+            .iter()
+            .filter_map(|id| collection.nodes.get(id))
+            .map(|node| {
+                let mut member = node.clone_box();
+                if let Some(block) = concrete_node_mut::<Block>(member.as_mut()) {
+                    block.transform = block.transform.then(&self.transform);
+                } else if let Some(t) = member.as_transformable_mut() {
+                    self.transform.apply(t);
+                }
+                member
+            })
+            .collect()
+    }
+}
+
+impl Default for Block {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Block);
+
+/// Why the members of a block hierarchy could not be collected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockError {
+    /// The collection holds no node with the uuid.
+    MissingNode(Uuid),
+    /// The node is not a block.
+    NotABlock(Uuid),
+    /// The blocks hold each other in a cycle, listed from the first block
+    /// of the cycle to the one holding it again.
+    Cycle(Vec<Uuid>),
+}
+
+impl Display for BlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockError::MissingNode(uuid) => write!(f, "no node {}", uuid),
+            BlockError::NotABlock(uuid) => write!(f, "node {} is not a block", uuid),
+            BlockError::Cycle(blocks) => {
+                write!(f, "blocks hold each other in a cycle:")?;
+                for block in blocks {
+                    write!(f, " {}", block)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+#[typetag::serde]
+impl GeometryNode for Block {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn dependencies(&self) -> Vec<Uuid> {
+        self.members.clone()
+    }
+    fn drop_dependency(&mut self, uuid: &Uuid) -> bool {
+        let len = self.members.len();
+        self.members.retain(|id| id != uuid);
+        self.members.len() != len
+    }
+    /// Members are compared by uuid, as the block holds nothing else of them.
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Block>(other).is_some_and(|o| {
+            self.members == o.members && self.transform == o.transform && self.extra == o.extra
+        })
+    }
+}
+
+impl NodeCollection {
+    /// Replaces the block by its members, baking its transform into them.
+    ///
+    /// Fails if the node is missing or not a block, or if it or one of its members is locked.
+    pub fn explode_block(&mut self, key: &Uuid) -> Result<(), EditError> {
+        let node = self.check_editable(key)?;
+        let block = concrete_node::<Block>(node).ok_or(EditError::WrongType {
+            uuid: *key,
+            found: node.type_tag(),
+        })?;
+        for member in &block.members {
+            self.check_unlocked(member)?;
+        }
+
+        for member in block.world_members(self) {
+            self.insert_node(member);
+        }
+        self.remove(key)?;
+        Ok(())
+    }
+
+    /// Bakes the transform of every block into its members and resets it to the identity,
+    /// so all nodes sit at their world coordinates, e.g. for export to formats
+    /// without nested transforms. Outer blocks are baked before the blocks they hold,
+    /// which first get the outer transform composed into their own.
+    ///
+    /// A block keeps its transform if it or one of its members is frozen,
+    /// or if a member is neither a block nor transformable.
+    pub fn bake_transforms(&mut self) {
+        for id in self.blocks_outside_in() {
+            let Some(block) = self
+                .nodes
+                .get(&id)
+                .and_then(|n| concrete_node::<Block>(n.as_ref()))
+            else {
+                continue;
+            };
+            let (transform, members) = (block.transform, block.members.clone());
+            if transform.is_identity() || !self.can_bake(&id, &members) {
+                continue;
+            }
+
+            for member in &members {
+                let composed = self.update(member, |b: &mut Block| {
+                    b.transform = b.transform.then(&transform);
+                });
+                // `can_bake` made sure members that are no blocks are transformable.
+                if composed.is_err() {
+                    let _ = self.transform_node(member, |t| transform.apply(t));
+                }
+            }
+            let _ = self.update(&id, |b: &mut Block| b.transform = Transform::identity());
+        }
+    }
+
+    /// All nodes the block holds, directly or through nested blocks, that are not blocks
+    /// themselves. Each member is listed once, in the order it is first reached.
+    /// Members the collection does not hold are skipped.
+    pub fn block_members_recursive(&self, block_uuid: &Uuid) -> Result<Vec<Uuid>, BlockError> {
+        fn visit(
+            collection: &NodeCollection,
+            block: &Block,
+            path: &mut Vec<Uuid>,
+            leaves: &mut Vec<Uuid>,
+        ) -> Result<(), BlockError> {
+            path.push(block.uuid);
+            for member in &block.members {
+                let Some(node) = collection.nodes.get(member) else {
+                    continue;
+                };
+                match concrete_node::<Block>(node.as_ref()) {
+                    Some(nested) => {
+                        if let Some(start) = path.iter().position(|id| id == member) {
+                            return Err(BlockError::Cycle(path[start..].to_vec()));
+                        }
+                        visit(collection, nested, path, leaves)?;
+                    }
+                    None if !leaves.contains(member) => leaves.push(*member),
+                    None => (),
+                }
+            }
+            path.pop();
+            Ok(())
+        }
+
+        let node = self
+            .nodes
+            .get(block_uuid)
+            .ok_or(BlockError::MissingNode(*block_uuid))?;
+        let block =
+            concrete_node::<Block>(node.as_ref()).ok_or(BlockError::NotABlock(*block_uuid))?;
+
+        let mut leaves = Vec::new();
+        visit(self, block, &mut Vec::new(), &mut leaves)?;
+        Ok(leaves)
+    }
+
+    /// All blocks, each one after every block holding it, for blocks that do not hold themselves.
+    fn blocks_outside_in(&self) -> Vec<Uuid> {
+        fn visit(
+            collection: &NodeCollection,
+            id: Uuid,
+            seen: &mut Vec<Uuid>,
+            order: &mut Vec<Uuid>,
+        ) {
+            if seen.contains(&id) {
+                return;
+            }
+            seen.push(id);
+            let Some(block) = collection
+                .nodes
+                .get(&id)
+                .and_then(|n| concrete_node::<Block>(n.as_ref()))
+            else {
+                return;
+            };
+            for member in &block.members {
+                visit(collection, *member, seen, order);
+            }
+            order.push(id);
+        }
+
+        let mut blocks: Vec<Uuid> = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| concrete_node::<Block>(n.as_ref()).is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        blocks.sort();
+
+        let (mut seen, mut order) = (Vec::new(), Vec::new());
+        for id in blocks {
+            visit(self, id, &mut seen, &mut order);
+        }
+        order.reverse();
+        order
+    }
+
+    fn can_bake(&mut self, id: &Uuid, members: &[Uuid]) -> bool {
+        !self.is_frozen(id)
+            && members.iter().all(|member| {
+                !self.is_frozen(member)
+                    && self.nodes.get_mut(member).is_none_or(|n| {
+                        concrete_node::<Block>(n.as_ref()).is_some()
+                            || n.as_transformable_mut().is_some()
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        let mut point = Point3::new();
+        (point.x, point.y, point.z) = (x, y, z);
+        point
+    }
+
+    fn position(collection: &NodeCollection, id: &Uuid) -> [f64; 3] {
+        let point = collection.try_get_typed::<Point3>(id).unwrap();
+        [point.x, point.y, point.z]
+    }
+
+    /// A collection of a point at `[1, 2, 0]` in a block translated by `[10, 0, 5]`,
+    /// and the uuids of the point and the block.
+    fn translated_block() -> (NodeCollection, Uuid, Uuid) {
+        let mut collection = NodeCollection::new();
+        let point = point(1.0, 2.0, 0.0);
+        let point_id = point.uuid();
+        let mut block = Block::new();
+        block.members_mut().push(point_id);
+        *block.transform_mut() = Transform::translation(10.0, 0.0, 5.0);
+        let block_id = block.uuid();
+        collection.push(Box::new(point)).unwrap();
+        collection.push(Box::new(block)).unwrap();
+        (collection, point_id, block_id)
+    }
+
+    #[test]
+    fn a_translated_block_explodes_to_translated_members() {
+        let (mut collection, point_id, block_id) = translated_block();
+
+        collection.explode_block(&block_id).unwrap();
+
+        assert!(collection.get(&block_id).is_none());
+        assert_eq!(position(&collection, &point_id), [11.0, 2.0, 5.0]);
+    }
+
+    #[test]
+    fn baking_a_translated_block_moves_its_members_to_world_coordinates() {
+        let (mut collection, point_id, block_id) = translated_block();
+
+        collection.bake_transforms();
+
+        assert_eq!(position(&collection, &point_id), [11.0, 2.0, 5.0]);
+        let block = collection.try_get_typed::<Block>(&block_id).unwrap();
+        assert!(block.transform().is_identity());
+        assert_eq!(block.members(), [point_id]);
+    }
+
+    #[test]
+    fn members_of_nested_blocks_are_listed_once() {
+        let mut collection = NodeCollection::new();
+        let (first, second) = (point(0.0, 0.0, 0.0), point(1.0, 0.0, 0.0));
+        let (first_id, second_id) = (first.uuid(), second.uuid());
+        let mut inner = Block::new();
+        inner.members_mut().extend([second_id, first_id]);
+        let mut outer = Block::new();
+        outer.members_mut().extend([first_id, inner.uuid()]);
+        let outer_id = outer.uuid();
+        collection.push(Box::new(first)).unwrap();
+        collection.push(Box::new(second)).unwrap();
+        collection.push(Box::new(inner)).unwrap();
+        collection.push(Box::new(outer)).unwrap();
+
+        assert_eq!(
+            collection.block_members_recursive(&outer_id),
+            Ok(vec![first_id, second_id])
+        );
+        assert_eq!(
+            collection.block_members_recursive(&first_id),
+            Err(BlockError::NotABlock(first_id))
+        );
+    }
+
+    #[test]
+    fn blocks_holding_each_other_are_a_cycle() {
+        let mut collection = NodeCollection::new();
+        let (mut a, mut b) = (Block::new(), Block::new());
+        let (a_id, b_id) = (a.uuid(), b.uuid());
+        a.members_mut().push(b_id);
+        b.members_mut().push(a_id);
+        collection.push(Box::new(a)).unwrap();
+        collection.push(Box::new(b)).unwrap();
+
+        assert_eq!(
+            collection.block_members_recursive(&a_id),
+            Err(BlockError::Cycle(vec![a_id, b_id]))
+        );
+    }
+}
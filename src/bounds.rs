@@ -0,0 +1,340 @@
+use std::{
+    f64::consts::{FRAC_PI_2, TAU},
+    sync::PoisonError,
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    shapes::{Arc, Circle, Ellipse, Line, PointCloud, Polyline},
+    GeometryNode, NodeCollection, Point3, Rectangle,
+};
+
+/// An axis aligned box spanning from `min` to `max`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl BoundingBox {
+    pub fn new(a: [f64; 3], b: [f64; 3]) -> Self {
+        Self {
+            min: [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])],
+            max: [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])],
+        }
+    }
+
+    pub fn from_point(p: [f64; 3]) -> Self {
+        Self { min: p, max: p }
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> Self {
+        Self::new(
+            [
+                self.min[0].min(other.min[0]),
+                self.min[1].min(other.min[1]),
+                self.min[2].min(other.min[2]),
+            ],
+            [
+                self.max[0].max(other.max[0]),
+                self.max[1].max(other.max[1]),
+                self.max[2].max(other.max[2]),
+            ],
+        )
+    }
+
+    pub fn center(&self) -> [f64; 3] {
+        [
+            (self.min[0] + self.max[0]) / 2.0,
+            (self.min[1] + self.max[1]) / 2.0,
+            (self.min[2] + self.max[2]) / 2.0,
+        ]
+    }
+
+    /// The point inside of this box closest to `p`.
+    pub fn clamp_point(&self, p: [f64; 3]) -> [f64; 3] {
+        [
+            p[0].clamp(self.min[0], self.max[0]),
+            p[1].clamp(self.min[1], self.max[1]),
+            p[2].clamp(self.min[2], self.max[2]),
+        ]
+    }
+
+    /// True if `other` lies inside of this box, touching faces included.
+    pub fn contains(&self, other: &BoundingBox) -> bool {
+        (0..3).all(|i| self.min[i] <= other.min[i] && other.max[i] <= self.max[i])
+    }
+
+    /// True if `other` lies inside of this box without touching any of its faces.
+    pub fn contains_strictly(&self, other: &BoundingBox) -> bool {
+        (0..3).all(|i| self.min[i] < other.min[i] && other.max[i] < self.max[i])
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+}
+
+/// Geometry that occupies a finite region of space.
+pub trait Bounded {
+    fn bounding_box(&self) -> BoundingBox;
+}
+
+impl Bounded for Point3 {
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::from_point([self.x, self.y, self.z])
+    }
+}
+
+impl Bounded for Rectangle {
+    fn bounding_box(&self) -> BoundingBox {
+        let c = self.corner();
+        BoundingBox::new([c.x, c.y, c.z], [c.x + self.width, c.y + self.height, c.z])
+    }
+}
+
+impl Bounded for Circle {
+    fn bounding_box(&self) -> BoundingBox {
+        let (c, r) = (self.center, self.radius.abs());
+        BoundingBox::new([c.x - r, c.y - r, c.z], [c.x + r, c.y + r, c.z])
+    }
+}
+
+/// Bounded by the end points and the extreme points of the circle the arc passes.
+impl Bounded for Arc {
+    fn bounding_box(&self) -> BoundingBox {
+        let sweep = self.sweep_angle.clamp(-TAU, TAU);
+        let (start, end) = if sweep < 0.0 {
+            (self.start_angle + sweep, self.start_angle)
+        } else {
+            (self.start_angle, self.start_angle + sweep)
+        };
+
+        let first_quadrant = (start / FRAC_PI_2).ceil() as i64;
+        let last_quadrant = (end / FRAC_PI_2).floor() as i64;
+        (first_quadrant..=last_quadrant)
+            .map(|quadrant| quadrant as f64 * FRAC_PI_2)
+            .chain([start, end])
+            .map(|angle| BoundingBox::from_point(self.point_at(angle)))
+            .reduce(|acc, b| acc.union(&b))
+            .expect("an arc has end points")
+    }
+}
+
+impl Bounded for Line {
+    fn bounding_box(&self) -> BoundingBox {
+        let (a, b) = (self.start, self.end);
+        BoundingBox::new([a.x, a.y, a.z], [b.x, b.y, b.z])
+    }
+}
+
+impl Bounded for Ellipse {
+    fn bounding_box(&self) -> BoundingBox {
+        let (c, rx, ry) = (self.center, self.radius_x.abs(), self.radius_y.abs());
+        BoundingBox::new([c.x - rx, c.y - ry, c.z], [c.x + rx, c.y + ry, c.z])
+    }
+}
+
+/// An empty cloud is bounded by the origin. The collection never asks
+/// for it, as [`GeometryNode::as_bounded`] skips empty clouds.
+impl Bounded for PointCloud {
+    fn bounding_box(&self) -> BoundingBox {
+        self.points
+            .iter()
+            .map(|p| BoundingBox::from_point(*p))
+            .reduce(|acc, b| acc.union(&b))
+            .unwrap_or(BoundingBox::from_point([0.0; 3]))
+    }
+}
+
+/// An empty polyline is bounded by the origin, like an empty [`PointCloud`].
+impl Bounded for Polyline {
+    fn bounding_box(&self) -> BoundingBox {
+        self.points
+            .iter()
+            .map(|p| BoundingBox::from_point(*p))
+            .reduce(|acc, b| acc.union(&b))
+            .unwrap_or(BoundingBox::from_point([0.0; 3]))
+    }
+}
+
+pub(crate) fn node_bounds(node: &dyn GeometryNode) -> Option<BoundingBox> {
+    node.as_bounded().map(|b| b.bounding_box())
+}
+
+/// State of the collection wide bounding box.
+/// `Clean(None)` means the bounds are known and the collection holds nothing bounded.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) enum BoundsCache {
+    #[default]
+    Dirty,
+    Clean(Option<BoundingBox>),
+}
+
+impl NodeCollection {
+    /// The bounding box of all bounded nodes in the collection.
+    ///
+    /// The result is cached and only recomputed after an edit invalidated it.
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        if let BoundsCache::Clean(bounds) = self.bounds_cache() {
+            return bounds;
+        }
+
+        let bounds = self.compute_bounds();
+        self.set_bounds_cache(BoundsCache::Clean(bounds));
+        bounds
+    }
+
+    /// All bounded nodes whose bounding box intersects `region`.
+    pub fn query_region(&self, region: &BoundingBox) -> Vec<Uuid> {
+        self.nodes
+            .iter()
+            .filter(|(_, n)| {
+                n.as_bounded()
+                    .is_some_and(|b| b.bounding_box().intersects(region))
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Computes the bounding box from scratch, ignoring the cache.
+    pub fn compute_bounds(&self) -> Option<BoundingBox> {
+        self.nodes
+            .values()
+            .filter_map(|n| n.as_bounded())
+            .map(|b| b.bounding_box())
+            .reduce(|acc, b| acc.union(&b))
+    }
+
+    /// Grows a clean cache to include `added`.
+    pub(crate) fn expand_bounds(&self, added: Option<BoundingBox>) {
+        if let (BoundsCache::Clean(bounds), Some(added)) = (self.bounds_cache(), added) {
+            let bounds = bounds.map_or(added, |b| b.union(&added));
+            self.set_bounds_cache(BoundsCache::Clean(Some(bounds)));
+        }
+    }
+
+    /// Drops the cache, unless `removed` sat strictly inside of it and
+    /// so could not have defined any of its faces.
+    pub(crate) fn shrink_bounds(&self, removed: Option<BoundingBox>) {
+        let Some(removed) = removed else {
+            return;
+        };
+
+        match self.bounds_cache() {
+            BoundsCache::Clean(Some(bounds)) if bounds.contains_strictly(&removed) => (),
+            _ => self.invalidate_bounds(),
+        }
+    }
+
+    pub(crate) fn invalidate_bounds(&self) {
+        self.set_bounds_cache(BoundsCache::Dirty);
+    }
+
+    fn bounds_cache(&self) -> BoundsCache {
+        *self
+            .bounds_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_bounds_cache(&self, cache: BoundsCache) {
+        *self
+            .bounds_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = cache;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnchorKind;
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        let mut point = Point3::new();
+        (point.x, point.y, point.z) = (x, y, z);
+        point
+    }
+
+    #[test]
+    fn cached_bounds_match_computed_bounds_after_edits() {
+        let mut collection = NodeCollection::new();
+        assert_eq!(collection.bounds(), None);
+
+        let inner = point(1.0, 1.0, 0.0);
+        let outer = point(10.0, -5.0, 2.0);
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 4.0;
+        *rectangle.height_mut() = 3.0;
+        let (inner_id, outer_id, rectangle_id) = (inner.uuid(), outer.uuid(), rectangle.uuid());
+
+        collection.push(Box::new(inner)).unwrap();
+        assert_eq!(collection.bounds(), collection.compute_bounds());
+        collection.push(Box::new(outer)).unwrap();
+        collection.push(Box::new(rectangle)).unwrap();
+        assert_eq!(collection.bounds(), collection.compute_bounds());
+
+        collection
+            .update(&rectangle_id, |r: &mut Rectangle| *r.width_mut() = 20.0)
+            .unwrap();
+        assert_eq!(collection.bounds(), collection.compute_bounds());
+
+        collection.remove(&outer_id).unwrap();
+        assert_eq!(collection.bounds(), collection.compute_bounds());
+
+        collection.remove(&inner_id).unwrap();
+        collection.translate_nodes(&[rectangle_id], -3.0, 0.0, 1.0);
+        assert_eq!(collection.bounds(), collection.compute_bounds());
+
+        collection
+            .try_get_typed_mut::<Rectangle>(&rectangle_id)
+            .unwrap()
+            .anchor_mut()
+            .y = 7.0;
+        assert_eq!(collection.bounds(), collection.compute_bounds());
+        assert_eq!(
+            collection.bounds(),
+            Some(BoundingBox::new([-3.0, 7.0, 1.0], [17.0, 10.0, 1.0]))
+        );
+
+        collection.remove(&rectangle_id).unwrap();
+        assert_eq!(collection.bounds(), None);
+    }
+
+    #[test]
+    fn rectangle_bounds_under_each_anchor_kind() {
+        let bounds = |kind: AnchorKind| {
+            let mut rectangle = Rectangle::new();
+            *rectangle.width_mut() = 4.0;
+            *rectangle.height_mut() = 2.0;
+            (rectangle.anchor_mut().x, rectangle.anchor_mut().y) = (1.0, 1.0);
+            rectangle.anchor_kind = kind;
+            rectangle.bounding_box()
+        };
+
+        assert_eq!(
+            bounds(AnchorKind::BottomLeft),
+            BoundingBox::new([1.0, 1.0, 0.0], [5.0, 3.0, 0.0])
+        );
+        assert_eq!(
+            bounds(AnchorKind::Center),
+            BoundingBox::new([-1.0, 0.0, 0.0], [3.0, 2.0, 0.0])
+        );
+        assert_eq!(
+            bounds(AnchorKind::TopLeft),
+            BoundingBox::new([1.0, -1.0, 0.0], [5.0, 1.0, 0.0])
+        );
+    }
+
+    #[test]
+    fn rectangles_without_an_anchor_kind_default_to_bottom_left() {
+        let mut value = serde_json::to_value(Rectangle::new()).unwrap();
+        value.as_object_mut().unwrap().remove("anchor_kind");
+
+        let rectangle: Rectangle = serde_json::from_value(value).unwrap();
+        assert_eq!(rectangle.anchor_kind, AnchorKind::BottomLeft);
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    apply::apply_change,
+    diff::{Change, ChangeCategory, ChangePath, Diff},
+};
+
+/// True if `ancestor` leads to a value holding the one `path` leads to.
+fn is_strict_prefix(ancestor: &ChangePath, path: &ChangePath) -> bool {
+    let (a, p) = (ancestor.segments(), path.segments());
+    a.len() < p.len() && p.starts_with(a)
+}
+
+/// `change` with its path made relative to `root`, an ancestor of it.
+fn relative_to(change: &Change, root: &ChangePath) -> Change {
+    let path = ChangePath::new(change.path().segments()[root.segments().len()..].to_vec());
+    match change.clone() {
+        Change::Added { value, .. } => Change::Added { path, value },
+        Change::Removed { value, .. } => Change::Removed { path, value },
+        Change::Modified { before, after, .. } => Change::Modified {
+            path,
+            before,
+            after,
+        },
+        Change::Operation {
+            name,
+            before,
+            after,
+            ..
+        } => Change::Operation {
+            path,
+            name,
+            before,
+            after,
+        },
+    }
+}
+
+/// The change undoing `change`.
+fn inverse(change: Change) -> Change {
+    match change {
+        Change::Added { path, value } => Change::Removed { path, value },
+        Change::Removed { path, value } => Change::Added { path, value },
+        Change::Modified {
+            path,
+            before,
+            after,
+        }
+        | Change::Operation {
+            path,
+            before,
+            after,
+            ..
+        } => Change::Modified {
+            path,
+            before: after,
+            after: before,
+        },
+    }
+}
+
+fn after_mut(change: &mut Change) -> Option<&mut Value> {
+    match change {
+        Change::Added { value, .. } => Some(value),
+        Change::Removed { .. } => None,
+        Change::Modified { after, .. } | Change::Operation { after, .. } => Some(after),
+    }
+}
+
+/// A modification from `before` to `after`, `None` if the value ends where it started.
+fn modification(path: ChangePath, before: Value, after: Value) -> Option<Change> {
+    (before != after).then_some(Change::Modified {
+        path,
+        before,
+        after,
+    })
+}
+
+/// Folds `change`, made after all of `changes`, into them, so that applying the result
+/// to the state before `changes` gives the state after `change`.
+///
+/// A change inside of a value an earlier change added or modified is written into
+/// that change's value. A change replacing or removing a value earlier changes
+/// reached into takes over the value from before them. Two changes of the same
+/// path merge into one, or cancel out.
+fn fold_change(changes: &mut Vec<Change>, change: Change) {
+    let path = change.path().clone();
+
+    if let Some(index) = changes
+        .iter()
+        .rposition(|c| is_strict_prefix(c.path(), &path) && c.after().is_some())
+    {
+        let relative = relative_to(&change, changes[index].path());
+        if let Some(value) = after_mut(&mut changes[index]) {
+            apply_change(value, &relative);
+        }
+        if changes[index].before().is_some() && changes[index].before() == changes[index].after() {
+            changes.remove(index);
+        }
+        return;
+    }
+
+    let mut change = change;
+    let (inner, rest): (Vec<Change>, Vec<Change>) = std::mem::take(changes)
+        .into_iter()
+        .partition(|c| is_strict_prefix(&path, c.path()));
+    *changes = rest;
+    if let Some(before) = match &mut change {
+        Change::Removed { value, .. } => Some(value),
+        Change::Modified { before, .. } | Change::Operation { before, .. } => Some(before),
+        Change::Added { .. } => None,
+    } {
+        for undone in inner.into_iter().rev() {
+            apply_change(before, &inverse(relative_to(&undone, &path)));
+        }
+    }
+
+    let Some(index) = changes.iter().rposition(|c| *c.path() == path) else {
+        changes.push(change);
+        return;
+    };
+    let earlier = changes.remove(index);
+    let merged = match (earlier, change) {
+        (Change::Added { .. }, Change::Removed { .. }) => None,
+        (Change::Added { .. }, later) => later.after().map(|value| Change::Added {
+            path,
+            value: value.clone(),
+        }),
+        (Change::Removed { value: before, .. }, Change::Added { value, .. }) => {
+            modification(path, before, value)
+        }
+        (earlier @ Change::Removed { .. }, later) => {
+            // nothing to change after a removal, keep both as recorded
+            changes.insert(index, earlier);
+            Some(later)
+        }
+        (earlier, Change::Removed { .. }) => Some(Change::Removed {
+            path,
+            value: earlier.before().cloned().unwrap_or_default(),
+        }),
+        (earlier, later) => modification(
+            path,
+            earlier.before().cloned().unwrap_or_default(),
+            later.after().cloned().unwrap_or_default(),
+        ),
+    };
+    changes.extend(merged);
+}
+
+impl Diff {
+    /// The diff of applying `self`, then `next`, as one diff that applies to
+    /// the state `self` applies to.
+    ///
+    /// A change of `next` inside of a value `self` added or modified is written into
+    /// that value, one replacing or removing a value `self` changed parts of takes over
+    /// the value from before them, and changes of the same path merge or cancel out.
+    ///
+    /// Type tags and categories are taken from `self` first, as they need to match that state.
+    pub fn then(&self, next: &Diff) -> Diff {
+        let mut changes = self.changes().to_vec();
+        for change in next.changes() {
+            fold_change(&mut changes, change.clone());
+        }
+        let mut composed = Diff::new(changes);
+        composed.adopt_node_types(self);
+        composed.adopt_node_types(next);
+        composed
+    }
+}
+
+/// The changes of many successive diffs in the order they were recorded,
+/// for collecting edits until they are taken as one diff.
+#[derive(Clone, Debug, Default)]
+pub struct ChangeLog {
+    entries: Vec<(Instant, Change)>,
+    node_types: BTreeMap<Uuid, String>,
+    categories: BTreeMap<String, ChangeCategory>,
+    extra_keys: BTreeMap<Uuid, BTreeSet<String>>,
+}
+
+impl ChangeLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the changes of `diff`, as made now.
+    pub fn record(&mut self, diff: &Diff) {
+        self.record_at(diff, Instant::now());
+    }
+
+    /// Appends the changes of `diff`, as made at `at`.
+    pub fn record_at(&mut self, diff: &Diff, at: Instant) {
+        self.note_node_types(diff);
+        self.entries
+            .extend(diff.changes().iter().map(|c| (at, c.clone())));
+    }
+
+    /// Keeps the type tag a node had when it was first recorded,
+    /// which is the one the state before all recorded changes holds,
+    /// and the categories its type gave to the changes, and which of
+    /// its fields are extra fields.
+    fn note_node_types(&mut self, diff: &Diff) {
+        for uuid in diff.changes().iter().filter_map(|c| c.path().node()) {
+            if let Some(tag) = diff.node_type(&uuid) {
+                self.node_types
+                    .entry(uuid)
+                    .or_insert_with(|| tag.to_string());
+            }
+        }
+        for (path, category) in diff.overridden_categories() {
+            self.categories.entry(path.clone()).or_insert(*category);
+        }
+        for (uuid, keys) in diff.extra_keys() {
+            self.extra_keys
+                .entry(*uuid)
+                .or_default()
+                .extend(keys.iter().cloned());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All changes recorded so far as one diff, leaving the log empty.
+    ///
+    /// The changes are folded into each other in the order they were recorded,
+    /// see [`Diff::then`], so the diff applies to the state before the first of them.
+    pub fn take(&mut self) -> Diff {
+        let mut changes = Vec::new();
+        for (_, change) in self.entries.drain(..) {
+            fold_change(&mut changes, change);
+        }
+        let mut diff = Diff::new(changes);
+        for (uuid, tag) in std::mem::take(&mut self.node_types) {
+            diff.set_node_type(uuid, tag);
+        }
+        let mut categories = std::mem::take(&mut self.categories);
+        let paths: Vec<String> = diff
+            .changes()
+            .iter()
+            .map(|c| c.path().to_string())
+            .collect();
+        for path in paths {
+            if let Some(category) = categories.remove(&path) {
+                diff.set_category(path, category);
+            }
+        }
+        for (uuid, keys) in std::mem::take(&mut self.extra_keys) {
+            for key in keys {
+                diff.add_extra_key(uuid, key);
+            }
+        }
+        diff
+    }
+}
+
+/// A [`ChangeLog`] that merges rapid modifications of the same value, e.g.
+/// the many tiny edits of a slider, into one change from the first value
+/// before to the last value after.
+///
+/// A modification merges into the last change recorded for its path if that
+/// one is a modification as well, made at most `window` earlier. Additions,
+/// removals and operations are kept as recorded.
+#[derive(Clone, Debug)]
+pub struct CoalescingRecorder {
+    log: ChangeLog,
+    window: Duration,
+}
+
+impl CoalescingRecorder {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            log: ChangeLog::new(),
+            window,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// The changes recorded so far, after merging.
+    pub fn log(&self) -> &ChangeLog {
+        &self.log
+    }
+
+    /// Records the changes of `diff`, as made now.
+    pub fn record(&mut self, diff: &Diff) {
+        self.record_at(diff, Instant::now());
+    }
+
+    /// Records the changes of `diff`, as made at `at`.
+    pub fn record_at(&mut self, diff: &Diff, at: Instant) {
+        self.log.note_node_types(diff);
+        for change in diff.changes() {
+            self.coalesce(change, at);
+        }
+    }
+
+    fn coalesce(&mut self, change: &Change, at: Instant) {
+        let entries = &mut self.log.entries;
+        let last = entries.iter().rposition(|(_, c)| c.path() == change.path());
+        if let (Change::Modified { after, .. }, Some(index)) = (change, last) {
+            if let (
+                recorded_at,
+                Change::Modified {
+                    before,
+                    after: net_after,
+                    ..
+                },
+            ) = &mut entries[index]
+            {
+                if at.saturating_duration_since(*recorded_at) <= self.window {
+                    if before == after {
+                        // the value is back where it started
+                        entries.remove(index);
+                    } else {
+                        *net_after = after.clone();
+                        *recorded_at = at;
+                    }
+                    return;
+                }
+            }
+        }
+        entries.push((at, change.clone()));
+    }
+
+    /// The merged changes as one diff, leaving the recorder empty.
+    pub fn take(&mut self) -> Diff {
+        self.log.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, NodeCollection, Rectangle};
+
+    #[test]
+    fn composing_an_add_and_an_edit_applies_to_the_state_before_both() {
+        let start = NodeCollection::new();
+        let mut added = start.snapshot();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        added.push(Box::new(rectangle)).unwrap();
+        let mut edited = added.snapshot();
+        edited
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 4.0)
+            .unwrap();
+
+        let composed = start
+            .diff(&added)
+            .unwrap()
+            .then(&added.diff(&edited).unwrap());
+
+        let mut applied = start.snapshot();
+        applied.apply(&composed).unwrap();
+        assert_eq!(applied.diff(&edited).unwrap().changes(), []);
+    }
+
+    #[test]
+    fn composing_an_add_and_a_removal_cancels_out() {
+        let start = NodeCollection::new();
+        let mut added = start.snapshot();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        added.push(Box::new(rectangle)).unwrap();
+        let mut removed = added.snapshot();
+        removed.remove(&id).unwrap();
+
+        let composed = start
+            .diff(&added)
+            .unwrap()
+            .then(&added.diff(&removed).unwrap());
+
+        assert!(composed
+            .changes()
+            .iter()
+            .all(|c| c.path().node() != Some(id)));
+    }
+
+    #[test]
+    fn ten_width_changes_coalesce_into_one_modification() {
+        let mut state = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        state.push(Box::new(rectangle)).unwrap();
+        let mut recorder = CoalescingRecorder::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        for i in 1..=10 {
+            let before = state.snapshot();
+            state
+                .update(&id, |r: &mut Rectangle| *r.width_mut() = i as f64)
+                .unwrap();
+            let at = start + Duration::from_millis(10 * i);
+            recorder.record_at(&before.diff(&state).unwrap(), at);
+        }
+
+        assert_eq!(recorder.log().len(), 1);
+        assert_eq!(
+            recorder.take().changes(),
+            [Change::Modified {
+                path: ChangePath::node_root(&id).join("width"),
+                before: Value::from(0.0),
+                after: Value::from(10.0),
+            }]
+        );
+        assert!(recorder.log().is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{apply::ApplyError, diff::Diff, NodeCollection};
+
+/// A diff together with where it came from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Commit {
+    pub id: Uuid,
+    /// The commit this one applies on top of, `None` for a root commit,
+    /// which applies to an empty collection.
+    pub parent: Option<Uuid>,
+    /// Serializes as milliseconds since the unix epoch, which other tools
+    /// read more easily than the seconds and nanoseconds `SystemTime` writes.
+    #[serde(with = "epoch_millis")]
+    pub timestamp: SystemTime,
+    pub author: String,
+    pub message: String,
+    pub diff: Diff,
+}
+
+impl Commit {
+    /// A commit made now, with a time ordered id.
+    pub fn new(
+        parent: Option<Uuid>,
+        author: impl Into<String>,
+        message: impl Into<String>,
+        diff: Diff,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            parent,
+            timestamp: epoch_millis::now(),
+            author: author.into(),
+            message: message.into(),
+            diff,
+        }
+    }
+}
+
+/// Commit timestamps as milliseconds since the unix epoch. Also reads the
+/// seconds and nanoseconds struct logs were written with before.
+mod epoch_millis {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    /// Now, truncated to what serializes.
+    pub(super) fn now() -> SystemTime {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    pub(super) fn serialize<S: Serializer>(
+        time: &SystemTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(serde::ser::Error::custom)?
+            .as_millis();
+        serializer.serialize_u64(millis as u64)
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Millis(u64),
+        Legacy(SystemTime),
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SystemTime, D::Error> {
+        Ok(match Timestamp::deserialize(deserializer)? {
+            Timestamp::Millis(millis) => UNIX_EPOCH + Duration::from_millis(millis),
+            Timestamp::Legacy(time) => time,
+        })
+    }
+}
+
+/// Why a collection could not be reconstructed from a [`CommitLog`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckoutError {
+    /// The log holds no commit with the id.
+    UnknownCommit(Uuid),
+    /// The diff of a commit did not apply to the state of its parent.
+    Apply {
+        commit: Uuid,
+        errors: Vec<ApplyError>,
+    },
+    /// The base snapshot of a compacted log does not deserialize.
+    InvalidSnapshot(String),
+    /// The collection to snapshot when compacting does not serialize,
+    /// e.g. because it holds NaN.
+    Unserializable(String),
+}
+
+impl Display for CheckoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckoutError::UnknownCommit(id) => write!(f, "no commit {}", id),
+            CheckoutError::Apply { commit, errors } => {
+                write!(f, "commit {} does not apply:", commit)?;
+                for error in errors {
+                    write!(f, " {};", error)?;
+                }
+                Ok(())
+            }
+            CheckoutError::InvalidSnapshot(e) => write!(f, "invalid base snapshot: {}", e),
+            CheckoutError::Unserializable(e) => write!(f, "can not snapshot the head: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckoutError {}
+
+/// The number of commits after the base snapshot above which
+/// [`CommitLog::compact`] takes a new snapshot.
+pub const DEFAULT_COMPACT_THRESHOLD: usize = 64;
+
+fn default_compact_threshold() -> usize {
+    DEFAULT_COMPACT_THRESHOLD
+}
+
+/// The serialized state of a collection as of a commit dropped by [`CommitLog::compact`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Snapshot {
+    commit: Uuid,
+    collection: Value,
+}
+
+/// Commits chained by their parents, in the order they were added.
+///
+/// Only the diffs are stored, states are rebuilt from them on checkout.
+/// Once compacted the log starts from a snapshot of the collection instead
+/// of the empty one, and the commits leading up to it are gone.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CommitLog {
+    commits: Vec<Commit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<Snapshot>,
+    #[serde(default = "default_compact_threshold")]
+    compact_threshold: usize,
+}
+
+impl Default for CommitLog {
+    fn default() -> Self {
+        Self {
+            commits: Vec::new(),
+            base: None,
+            compact_threshold: DEFAULT_COMPACT_THRESHOLD,
+        }
+    }
+}
+
+impl CommitLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a commit. Its parent has to be in the log already,
+    /// or be the commit the base snapshot was taken at.
+    pub fn push(&mut self, commit: Commit) -> Result<(), CheckoutError> {
+        match commit.parent {
+            Some(parent) if self.get(&parent).is_none() && !self.is_base(&parent) => {
+                Err(CheckoutError::UnknownCommit(parent))
+            }
+            _ => {
+                self.commits.push(commit);
+                Ok(())
+            }
+        }
+    }
+
+    /// Commits `diff` on top of the latest commit, returning the new commit's id.
+    pub fn commit(
+        &mut self,
+        author: impl Into<String>,
+        message: impl Into<String>,
+        diff: Diff,
+    ) -> Uuid {
+        let commit = Commit::new(self.head_id(), author, message, diff);
+        let id = commit.id;
+        self.commits.push(commit);
+        id
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&Commit> {
+        self.commits.iter().find(|c| c.id == *id)
+    }
+
+    /// The latest commit.
+    pub fn head(&self) -> Option<&Commit> {
+        self.commits.last()
+    }
+
+    /// The id of the latest commit, which may already be compacted into the base snapshot.
+    pub fn head_id(&self) -> Option<Uuid> {
+        self.head()
+            .map(|c| c.id)
+            .or_else(|| self.base.as_ref().map(|b| b.commit))
+    }
+
+    /// The commit the base snapshot was taken at, `None` if the log was never compacted.
+    pub fn base_commit(&self) -> Option<Uuid> {
+        self.base.as_ref().map(|b| b.commit)
+    }
+
+    fn is_base(&self, id: &Uuid) -> bool {
+        self.base_commit() == Some(*id)
+    }
+
+    pub fn compact_threshold(&self) -> usize {
+        self.compact_threshold
+    }
+
+    pub fn set_compact_threshold(&mut self, threshold: usize) {
+        self.compact_threshold = threshold;
+    }
+
+    pub fn commits(&self) -> &[Commit] {
+        &self.commits
+    }
+
+    pub fn len(&self) -> usize {
+        self.commits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty()
+    }
+
+    /// The commits from the root, or from the base snapshot, up to and including `id`.
+    pub fn ancestry(&self, id: &Uuid) -> Result<Vec<&Commit>, CheckoutError> {
+        let mut chain = Vec::new();
+        let mut next = Some(*id).filter(|id| !self.is_base(id));
+        while let Some(id) = next {
+            let commit = self.get(&id).ok_or(CheckoutError::UnknownCommit(id))?;
+            chain.push(commit);
+            next = commit.parent.filter(|id| !self.is_base(id));
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// The collection as of commit `id`, built by applying the diffs of
+    /// its ancestry to the base snapshot, or to an empty collection if
+    /// the ancestry starts from a root commit.
+    pub fn checkout(&self, id: &Uuid) -> Result<NodeCollection, CheckoutError> {
+        let ancestry = self.ancestry(id)?;
+        let from_base = match ancestry.first() {
+            Some(first) => first.parent.is_some(),
+            None => true,
+        };
+        let mut collection = match &self.base {
+            Some(base) if from_base => NodeCollection::from_value(base.collection.clone())
+                .map_err(|e| CheckoutError::InvalidSnapshot(e.to_string()))?,
+            _ => NodeCollection::new(),
+        };
+        for commit in ancestry {
+            collection
+                .apply(&commit.diff)
+                .map_err(|errors| CheckoutError::Apply {
+                    commit: commit.id,
+                    errors,
+                })?;
+        }
+        Ok(collection)
+    }
+
+    /// Replaces the history up to the head with a snapshot of the head's
+    /// collection, if more than [`CommitLog::compact_threshold`] commits
+    /// lead up to it. Returns whether the log was compacted.
+    ///
+    /// Commits branching off the replaced history are dropped with it, as
+    /// they can not be rebuilt anymore. Histories starting from other root
+    /// commits are kept.
+    pub fn compact(&mut self) -> Result<bool, CheckoutError> {
+        let Some(head) = self.head().map(|c| c.id) else {
+            return Ok(false);
+        };
+        let ancestry: HashSet<Uuid> = self.ancestry(&head)?.iter().map(|c| c.id).collect();
+        if ancestry.len() <= self.compact_threshold {
+            return Ok(false);
+        }
+
+        let collection = self
+            .checkout(&head)?
+            .to_value()
+            .map_err(|e| CheckoutError::Unserializable(e.to_string()))?;
+        let mut dropped = ancestry;
+        if let Some(base) = self.base_commit() {
+            dropped.insert(base);
+        }
+        self.commits.retain(|commit| {
+            let drop =
+                dropped.contains(&commit.id) || commit.parent.is_some_and(|p| dropped.contains(&p));
+            if drop {
+                dropped.insert(commit.id);
+            }
+            !drop
+        });
+        self.base = Some(Snapshot {
+            commit: head,
+            collection,
+        });
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3, Rectangle};
+
+    /// Four successive states: empty, with a rectangle, with it widened,
+    /// and with a point added.
+    fn states() -> Vec<NodeCollection> {
+        let mut states = vec![NodeCollection::new()];
+        let mut state = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        state.push(Box::new(rectangle)).unwrap();
+        states.push(state.snapshot());
+        state
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 2.0)
+            .unwrap();
+        states.push(state.snapshot());
+        state.push(Box::new(Point3::new())).unwrap();
+        states.push(state);
+        states
+    }
+
+    /// A log committing the step to each state after the first, and the commit ids.
+    fn log_of(states: &[NodeCollection]) -> (CommitLog, Vec<Uuid>) {
+        let mut log = CommitLog::new();
+        let ids = states
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                let diff = pair[0].diff(&pair[1]).unwrap();
+                log.commit("ada", format!("step {}", i + 1), diff)
+            })
+            .collect();
+        (log, ids)
+    }
+
+    fn assert_same(a: &NodeCollection, b: &NodeCollection) {
+        assert_eq!(a.diff(b).unwrap().changes(), []);
+    }
+
+    #[test]
+    fn checkout_reconstructs_a_mid_chain_state() {
+        let states = states();
+        let (log, ids) = log_of(&states);
+
+        assert_eq!(log.ancestry(&ids[1]).unwrap().len(), 2);
+        assert_same(&log.checkout(&ids[1]).unwrap(), &states[2]);
+        assert_same(&log.checkout(&ids[0]).unwrap(), &states[1]);
+        assert_same(&log.checkout(&ids[2]).unwrap(), &states[3]);
+    }
+
+    #[test]
+    fn checkout_of_an_unknown_commit_fails() {
+        let (log, _) = log_of(&states());
+        let unknown = Uuid::new_v4();
+
+        assert!(matches!(
+            log.checkout(&unknown),
+            Err(CheckoutError::UnknownCommit(id)) if id == unknown
+        ));
+    }
+
+    #[test]
+    fn a_compacted_log_still_reconstructs_the_latest_state() {
+        let states = states();
+        let (mut log, ids) = log_of(&states);
+        log.set_compact_threshold(2);
+
+        assert!(log.compact().unwrap());
+
+        assert!(log.is_empty());
+        assert_eq!(log.base_commit(), Some(ids[2]));
+        assert_same(&log.checkout(&ids[2]).unwrap(), &states[3]);
+        assert!(matches!(
+            log.checkout(&ids[1]),
+            Err(CheckoutError::UnknownCommit(id)) if id == ids[1]
+        ));
+
+        let read: CommitLog = serde_json::from_str(&serde_json::to_string(&log).unwrap()).unwrap();
+        assert_same(&read.checkout(&ids[2]).unwrap(), &states[3]);
+    }
+
+    #[test]
+    fn commits_continue_on_top_of_the_base_snapshot() {
+        let states = states();
+        let (mut log, _) = log_of(&states);
+        log.set_compact_threshold(0);
+        log.compact().unwrap();
+
+        let mut next = states[3].snapshot();
+        next.push(Box::new(Point3::new())).unwrap();
+        let id = log.commit("ada", "step 4", states[3].diff(&next).unwrap());
+
+        assert_same(&log.checkout(&id).unwrap(), &next);
+        assert!(log.compact().unwrap());
+        assert_eq!(log.base_commit(), Some(id));
+        assert_same(&log.checkout(&id).unwrap(), &next);
+    }
+
+    #[test]
+    fn timestamps_serialize_as_epoch_millis() {
+        let (log, ids) = log_of(&states());
+        let commit = log.get(&ids[0]).unwrap();
+        let value = serde_json::to_value(commit).unwrap();
+        let millis = commit
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        assert_eq!(value["timestamp"], millis as u64);
+        let read: Commit = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(&read, commit);
+
+        let mut legacy = value;
+        legacy["timestamp"] = serde_json::json!({
+            "secs_since_epoch": 1_700_000_000,
+            "nanos_since_epoch": 5,
+        });
+        let read: Commit = serde_json::from_value(legacy).unwrap();
+        assert_eq!(read.timestamp, UNIX_EPOCH + Duration::new(1_700_000_000, 5));
+    }
+
+    #[test]
+    fn short_logs_are_not_compacted() {
+        let (mut log, _) = log_of(&states());
+
+        assert!(!log.compact().unwrap());
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.base_commit(), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use uuid::Uuid;
+
+use crate::{diff::Diff, error::GeodiffError, lock::EditError, GeometryNode, NodeCollection};
+
+/// A collection shared between threads, behind a single reader writer lock.
+///
+/// Cloning the handle shares the collection, it does not copy it.
+///
+/// # Ordering
+///
+/// Writes are totally ordered: each [`SharedCollection::write`] guard, and so
+/// each [`SharedCollection::update_node`], sees every write that finished before
+/// it took the lock. A read sees the state after some prefix of that order, never
+/// a write in progress. [`SharedCollection::diff_against`] holds the read lock for
+/// the whole diff, so it compares against one consistent snapshot, though that
+/// snapshot may already be stale once the diff is returned.
+///
+/// A thread that panics while writing does not poison the collection for others,
+/// the guards hand out whatever state the write left behind.
+#[derive(Clone, Default)]
+pub struct SharedCollection {
+    inner: Arc<RwLock<NodeCollection>>,
+}
+
+impl SharedCollection {
+    pub fn new(collection: NodeCollection) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(collection)),
+        }
+    }
+
+    /// Locks the collection for reading, blocking while a write is in progress.
+    pub fn read(&self) -> RwLockReadGuard<'_, NodeCollection> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the collection for writing, blocking until all other guards are dropped.
+    pub fn write(&self) -> RwLockWriteGuard<'_, NodeCollection> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs `f` on the node under the read lock, `None` if the node is missing.
+    pub fn read_node<R>(&self, key: &Uuid, f: impl FnOnce(&dyn GeometryNode) -> R) -> Option<R> {
+        self.read().get(key).map(f)
+    }
+
+    /// Edits the node under the write lock, see [`NodeCollection::update`].
+    ///
+    /// Fails if the node is missing, locked or not of type `T`.
+    pub fn update_node<T>(&self, key: &Uuid, f: impl FnOnce(&mut T)) -> Result<(), EditError>
+    where
+        T: GeometryNode + 'static,
+    {
+        self.write().update(key, f)
+    }
+
+    /// Diffs the shared collection, as `before`, against `other`, see [`NodeCollection::diff`].
+    pub fn diff_against(&self, other: &NodeCollection) -> Result<Diff, GeodiffError> {
+        self.read().diff(other)
+    }
+}
+
+impl From<NodeCollection> for SharedCollection {
+    fn from(collection: NodeCollection) -> Self {
+        Self::new(collection)
+    }
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<NodeCollection>();
+};
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::Point3;
+
+    #[test]
+    fn reads_during_writes_see_consistent_snapshots() {
+        let mut collection = NodeCollection::new();
+        let (a, b) = (Point3::new(), Point3::new());
+        let (a_id, b_id) = (a.uuid(), b.uuid());
+        collection.push(Box::new(a)).unwrap();
+        collection.push(Box::new(b)).unwrap();
+        let start = NodeCollection::from_value(collection.to_value().unwrap()).unwrap();
+        let shared = SharedCollection::new(collection);
+        let x = |c: &NodeCollection, id| c.try_get_typed::<Point3>(id).unwrap().x;
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 1..=200 {
+                    // both points move under one write lock
+                    let mut collection = shared.write();
+                    collection
+                        .update(&a_id, |p: &mut Point3| p.x = i as f64)
+                        .unwrap();
+                    collection
+                        .update(&b_id, |p: &mut Point3| p.x = i as f64)
+                        .unwrap();
+                }
+            });
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..200 {
+                        let collection = shared.read();
+                        assert_eq!(x(&collection, &a_id), x(&collection, &b_id));
+                        drop(collection);
+                        let changes = shared.diff_against(&start).unwrap().len();
+                        assert!(changes == 0 || changes == 2);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(shared.read_node(&a_id, |n| n.uuid()), Some(a_id));
+        assert_eq!(x(&shared.read(), &b_id), 200.0);
+    }
+
+    #[test]
+    fn update_node_refuses_missing_nodes() {
+        let shared = SharedCollection::from(NodeCollection::new());
+        let missing = Uuid::new_v4();
+
+        assert!(shared
+            .update_node(&missing, |p: &mut Point3| p.x = 1.0)
+            .is_err());
+        assert!(shared.read_node(&missing, |n| n.uuid()).is_none());
+    }
+}
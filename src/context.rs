@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    diff::{node_value, Change, Diff},
+    GeometryNode, NodeCollection,
+};
+
+/// A modified node with all of its current fields, for rendering
+/// the whole node with its changed fields highlighted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeContext<'a> {
+    pub uuid: Uuid,
+    /// The node as currently serialized in the collection.
+    pub fields: Value,
+    /// The changes to the node's fields.
+    pub changes: Vec<&'a Change>,
+}
+
+impl Diff {
+    /// The context of every node this diff modifies field by field, ordered by uuid.
+    ///
+    /// Fields are read from `collection`, usually the state the diff leads to.
+    /// Nodes the collection does not hold are left out.
+    pub fn with_context<'a>(&'a self, collection: &NodeCollection) -> Vec<NodeContext<'a>> {
+        let mut changes: BTreeMap<Uuid, Vec<&Change>> = BTreeMap::new();
+        for change in self.changes() {
+            let path = change.path();
+            if let (Some("nodes"), Some(uuid), false) =
+                (path.section(), path.node(), path.field().is_empty())
+            {
+                changes.entry(uuid).or_default().push(change);
+            }
+        }
+
+        changes
+            .into_iter()
+            .filter_map(|(uuid, changes)| {
+                let node = collection.nodes.get(&uuid)?;
+                Some(NodeContext {
+                    uuid,
+                    fields: node_value(node.as_ref()),
+                    changes,
+                })
+            })
+            .collect()
+    }
+
+    /// Pairs every change with the node in `collection` it refers to.
+    ///
+    /// With `collection` in the state the diff leads to, removed nodes resolve
+    /// to `None`, as do changes to collection wide bookkeeping that belong to no node.
+    pub fn resolve<'a>(
+        &'a self,
+        collection: &'a NodeCollection,
+    ) -> impl Iterator<Item = (&'a Change, Option<&'a dyn GeometryNode>)> {
+        self.changes().iter().map(move |change| {
+            let node = change.path().node().and_then(|uuid| collection.get(&uuid));
+            (change, node)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rectangle;
+
+    /// A rectangle of height 4, before and after its width changed, and its uuid.
+    fn widened_rectangle() -> (NodeCollection, NodeCollection, Uuid) {
+        let mut before = NodeCollection::new();
+        let mut rectangle = Rectangle::new();
+        *rectangle.height_mut() = 4.0;
+        rectangle.anchor_mut().x = 1.0;
+        let id = rectangle.uuid();
+        before.push(Box::new(rectangle)).unwrap();
+
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 3.0)
+            .unwrap();
+        (before, after, id)
+    }
+
+    #[test]
+    fn context_of_a_widened_rectangle_includes_its_unchanged_fields() {
+        let (before, after, id) = widened_rectangle();
+        let diff = before.diff(&after).unwrap();
+
+        let context = diff.with_context(&after);
+
+        assert_eq!(context.len(), 1);
+        assert_eq!(context[0].uuid, id);
+        assert_eq!(context[0].fields["width"], 3.0);
+        assert_eq!(context[0].fields["height"], 4.0);
+        assert_eq!(context[0].fields["anchor"]["x"], 1.0);
+        assert_eq!(
+            context[0].changes,
+            diff.changes().iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn a_modification_resolves_to_the_rectangle() {
+        let (before, after, id) = widened_rectangle();
+        let diff = before.diff(&after).unwrap();
+
+        let resolved: Vec<_> = diff.resolve(&after).collect();
+
+        assert_eq!(resolved.len(), 1);
+        let (change, node) = resolved[0];
+        assert_eq!(change, &diff.changes()[0]);
+        let rectangle = node.and_then(|n| n.as_any().downcast_ref::<Rectangle>());
+        assert_eq!(rectangle.map(|r| (r.uuid(), r.width)), Some((id, 3.0)));
+    }
+
+    #[test]
+    fn a_removal_resolves_to_no_node() {
+        let (before, _, id) = widened_rectangle();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after.remove(&id).unwrap();
+        let diff = before.diff(&after).unwrap();
+
+        assert!(diff.resolve(&after).all(|(_, node)| node.is_none()));
+    }
+}
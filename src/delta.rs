@@ -0,0 +1,151 @@
+use std::sync::mpsc::Receiver;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    diff::node_value, error::GeodiffError, hash::content_hash, GeometryNode, NodeCollection,
+};
+
+/// Whole node updates taking one collection to another, e.g. to replicate
+/// edits to a peer. Names, layers and other bookkeeping are not included.
+///
+/// The nodes are held in their serialized form, so deltas can be sent between threads.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CollectionDelta {
+    /// Nodes that were added or changed, replacing any node with their uuid.
+    pub upserts: Vec<Value>,
+    /// Uuids of the nodes that were removed.
+    pub removals: Vec<Uuid>,
+}
+
+impl CollectionDelta {
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.removals.is_empty()
+    }
+}
+
+impl NodeCollection {
+    /// The delta taking `self` to `other`, with changed nodes found by their
+    /// [`content_hash`]. Both lists are sorted by uuid.
+    ///
+    /// Fails like [`NodeCollection::diff`] on NaN or infinite floats.
+    pub fn delta(&self, other: &NodeCollection) -> Result<CollectionDelta, GeodiffError> {
+        self.check_finite()?;
+        other.check_finite()?;
+        let mut upserts: Vec<(&Uuid, &dyn GeometryNode)> = Vec::new();
+        for (id, after) in &other.nodes {
+            let changed = match self.nodes.get(id) {
+                Some(before) => content_hash(before.as_ref())? != content_hash(after.as_ref())?,
+                None => true,
+            };
+            if changed {
+                upserts.push((id, after.as_ref()));
+            }
+        }
+        upserts.sort_by_key(|(id, _)| **id);
+        let mut removals = self.difference(other);
+        removals.sort();
+
+        Ok(CollectionDelta {
+            upserts: upserts.into_iter().map(|(_, n)| node_value(n)).collect(),
+            removals,
+        })
+    }
+
+    /// Applies a delta as one [`NodeCollection::batch`], so listeners hear
+    /// about it in a single event. Removals come before upserts.
+    ///
+    /// Fails without changing anything if one of the nodes does not deserialize,
+    /// or if the delta removes or replaces a locked node.
+    pub fn apply_delta(&mut self, delta: &CollectionDelta) -> Result<(), GeodiffError> {
+        let upserts = delta
+            .upserts
+            .iter()
+            .map(|value| serde_json::from_value::<Box<dyn GeometryNode>>(value.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let touched = (delta.removals.iter().copied()).chain(upserts.iter().map(|n| n.uuid()));
+        for key in touched {
+            self.check_unlocked(&key)?;
+        }
+
+        self.batch(|collection| {
+            for key in &delta.removals {
+                // Removals of nodes the collection does not hold are no-ops.
+                let _ = collection.remove(key);
+            }
+            for node in upserts {
+                collection.insert_node(node);
+            }
+        });
+        Ok(())
+    }
+
+    /// Applies the deltas coming off `rx` in the order they arrive, until all
+    /// senders are gone, e.g. as the receiving end of a replication loop.
+    ///
+    /// Stops at the first delta that fails to apply, leaving the ones before applied.
+    pub fn apply_delta_stream(
+        &mut self,
+        rx: Receiver<CollectionDelta>,
+    ) -> Result<(), GeodiffError> {
+        for delta in rx {
+            self.apply_delta(&delta)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+    use crate::{Point3, Rectangle};
+
+    /// Four states of a collection: empty, with a rectangle and a point added,
+    /// with the rectangle widened, and with the point removed.
+    fn states() -> Vec<NodeCollection> {
+        let mut states = vec![NodeCollection::new()];
+        let mut current = NodeCollection::new();
+        let (rectangle, point) = (Rectangle::new(), Point3::new());
+        let (rectangle_id, point_id) = (rectangle.uuid(), point.uuid());
+        current.push(Box::new(rectangle)).unwrap();
+        current.push(Box::new(point)).unwrap();
+        states.push(current.snapshot());
+        current
+            .update(&rectangle_id, |r: &mut Rectangle| *r.width_mut() = 2.0)
+            .unwrap();
+        states.push(current.snapshot());
+        current.remove(&point_id).unwrap();
+        states.push(current);
+        states
+    }
+
+    #[test]
+    fn streamed_deltas_apply_like_deltas_applied_in_order() {
+        let states = states();
+        let deltas: Vec<CollectionDelta> = states
+            .windows(2)
+            .map(|pair| pair[0].delta(&pair[1]).unwrap())
+            .collect();
+        let mut direct = NodeCollection::new();
+        for delta in &deltas {
+            direct.apply_delta(delta).unwrap();
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let sender = thread::spawn(move || {
+            for delta in deltas {
+                tx.send(delta).unwrap();
+            }
+        });
+        let mut streamed = NodeCollection::new();
+        streamed.apply_delta_stream(rx).unwrap();
+        sender.join().unwrap();
+
+        assert_eq!(streamed.diff(&direct).unwrap().changes(), []);
+        assert!(streamed.delta(&states[3]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,950 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use treediff::{
+    tools::{ChangeType, Recorder},
+    value::Key,
+    Delegate,
+};
+use uuid::Uuid;
+
+use crate::{
+    domain::diff_nodes, error::GeodiffError, finite::check_node_finite, GeometryNode,
+    NodeCollection,
+};
+
+/// The keys leading to a changed value in the serialized collection,
+/// e.g. `nodes/<uuid>/anchor/x`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChangePath(Vec<String>);
+
+impl ChangePath {
+    pub fn new(segments: Vec<String>) -> Self {
+        Self(segments)
+    }
+
+    /// The path of a node inside the `nodes` map.
+    pub fn node_root(uuid: &Uuid) -> Self {
+        Self(vec!["nodes".to_string(), uuid.to_string()])
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.0
+    }
+
+    /// The top level entry of the collection the path points into.
+    pub fn section(&self) -> Option<&str> {
+        self.0.first().map(String::as_str)
+    }
+
+    /// The node the path points into, if any.
+    pub fn node(&self) -> Option<Uuid> {
+        self.0.get(1).and_then(|s| Uuid::parse_str(s).ok())
+    }
+
+    /// The keys below the node, empty if the path points at the node itself.
+    pub fn field(&self) -> &[String] {
+        self.0.get(2..).unwrap_or_default()
+    }
+
+    pub fn join(&self, segment: impl Into<String>) -> Self {
+        let mut segments = self.0.clone();
+        segments.push(segment.into());
+        Self(segments)
+    }
+}
+
+impl Display for ChangePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join("/"))
+    }
+}
+
+/// Orders paths segment by segment, comparing array indices numerically.
+pub(crate) fn compare_paths(a: &ChangePath, b: &ChangePath) -> Ordering {
+    let compare = |a: &String, b: &String| match (a.parse::<usize>(), b.parse::<usize>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    };
+    a.segments()
+        .iter()
+        .zip(b.segments())
+        .map(|(a, b)| compare(a, b))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.segments().len().cmp(&b.segments().len()))
+}
+
+/// A single value that differs between two serialized states.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Change {
+    Added {
+        path: ChangePath,
+        value: Value,
+    },
+    Removed {
+        path: ChangePath,
+        value: Value,
+    },
+    Modified {
+        path: ChangePath,
+        before: Value,
+        after: Value,
+    },
+    /// A domain specific edit found by a [`DiffAware`](crate::domain::DiffAware) node type, e.g. a reversed line.
+    /// Applies like a modification of the value at `path`, except for a
+    /// [`MOVED`](crate::minimize::MOVED) node, which also takes the uuid of `after`.
+    Operation {
+        path: ChangePath,
+        name: String,
+        before: Value,
+        after: Value,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &ChangePath {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Modified { path, .. }
+            | Change::Operation { path, .. } => path,
+        }
+    }
+
+    /// The value before the change, `None` if it was added.
+    pub fn before(&self) -> Option<&Value> {
+        match self {
+            Change::Added { .. } => None,
+            Change::Removed { value, .. } => Some(value),
+            Change::Modified { before, .. } | Change::Operation { before, .. } => Some(before),
+        }
+    }
+
+    /// The value after the change, `None` if it was removed.
+    pub fn after(&self) -> Option<&Value> {
+        match self {
+            Change::Added { value, .. } => Some(value),
+            Change::Removed { .. } => None,
+            Change::Modified { after, .. } | Change::Operation { after, .. } => Some(after),
+        }
+    }
+
+    /// A lowercase name for the kind of change.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::Added { .. } => "added",
+            Change::Removed { .. } => "removed",
+            Change::Modified { .. } => "modified",
+            Change::Operation { .. } => "operation",
+        }
+    }
+
+    /// The category of this change, judged by its path alone.
+    pub fn category(&self) -> ChangeCategory {
+        ChangeCategory::of(self)
+    }
+
+    /// The category of this change, letting `node` override the classification
+    /// through [`GeometryNode::change_category`].
+    pub fn category_for(&self, node: &dyn GeometryNode) -> ChangeCategory {
+        node.change_category(self.path().field())
+            .unwrap_or_else(|| self.category())
+    }
+
+    /// How much this change matters for review, higher is more severe,
+    /// judged by its path alone.
+    ///
+    /// Structural changes score [`STRUCTURAL_SEVERITY`], dimensional ones
+    /// [`DIMENSIONAL_SEVERITY`]. Positional changes score below that, growing with
+    /// the distance moved, so a tiny nudge scores close to zero.
+    pub fn severity(&self) -> f64 {
+        self.severity_as(self.category())
+    }
+
+    /// Like [`Change::severity`], but categorized by [`Change::category_for`] the `node`.
+    pub fn severity_for(&self, node: &dyn GeometryNode) -> f64 {
+        self.severity_as(self.category_for(node))
+    }
+
+    fn severity_as(&self, category: ChangeCategory) -> f64 {
+        match category {
+            ChangeCategory::Structural => STRUCTURAL_SEVERITY,
+            ChangeCategory::Dimensional => DIMENSIONAL_SEVERITY,
+            ChangeCategory::Positional => {
+                let moved = self
+                    .before()
+                    .and_then(Value::as_f64)
+                    .zip(self.after().and_then(Value::as_f64))
+                    .map(|(before, after)| (after - before).abs());
+                match moved {
+                    Some(moved) => DIMENSIONAL_SEVERITY * moved / (1.0 + moved),
+                    None => DIMENSIONAL_SEVERITY,
+                }
+            }
+        }
+    }
+
+    /// Diffs two values, prefixing every recorded path with `root`.
+    pub(crate) fn record(root: &ChangePath, before: &Value, after: &Value) -> Vec<Change> {
+        let mut recorder = Recorder::default();
+        treediff::diff(before, after, &mut recorder);
+
+        let path = |keys: &[Key]| {
+            let mut path = root.clone();
+            for key in keys {
+                path = path.join(key.to_string());
+            }
+            path
+        };
+
+        recorder
+            .calls
+            .into_iter()
+            .filter_map(|call| match call {
+                ChangeType::Unchanged(..) => None,
+                ChangeType::Added(keys, value) => Some(Change::Added {
+                    path: path(&keys),
+                    value: value.clone(),
+                }),
+                ChangeType::Removed(keys, value) => Some(Change::Removed {
+                    path: path(&keys),
+                    value: value.clone(),
+                }),
+                ChangeType::Modified(keys, before, after) => Some(Change::Modified {
+                    path: path(&keys),
+                    before: before.clone(),
+                    after: after.clone(),
+                }),
+            })
+            .collect()
+    }
+
+    /// Like [`Change::record`], but keeps no more than the first `max` changes.
+    /// The flag is true if further changes were dropped.
+    pub(crate) fn record_capped(
+        root: &ChangePath,
+        before: &Value,
+        after: &Value,
+        max: usize,
+    ) -> (Vec<Change>, bool) {
+        let mut recorder = CappedRecorder {
+            path: root.clone(),
+            max,
+            changes: Vec::new(),
+            overflowed: false,
+        };
+        treediff::diff(before, after, &mut recorder);
+        (recorder.changes, recorder.overflowed)
+    }
+}
+
+/// A treediff delegate recording at most `max` changes, see [`Change::record_capped`].
+struct CappedRecorder {
+    path: ChangePath,
+    max: usize,
+    changes: Vec<Change>,
+    overflowed: bool,
+}
+
+impl CappedRecorder {
+    fn record(&mut self, change: impl FnOnce(&ChangePath) -> Change) {
+        if self.changes.len() < self.max {
+            self.changes.push(change(&self.path));
+        } else {
+            self.overflowed = true;
+        }
+    }
+}
+
+impl<'a> Delegate<'a, Key, Value> for CappedRecorder {
+    fn push(&mut self, k: &Key) {
+        self.path = self.path.join(k.to_string());
+    }
+
+    fn pop(&mut self) {
+        self.path.0.pop();
+    }
+
+    fn removed<'b>(&mut self, k: &'b Key, v: &'a Value) {
+        self.record(|path| Change::Removed {
+            path: path.join(k.to_string()),
+            value: v.clone(),
+        });
+    }
+
+    fn added<'b>(&mut self, k: &'b Key, v: &'a Value) {
+        self.record(|path| Change::Added {
+            path: path.join(k.to_string()),
+            value: v.clone(),
+        });
+    }
+
+    fn modified(&mut self, old: &'a Value, new: &'a Value) {
+        self.record(|path| Change::Modified {
+            path: path.clone(),
+            before: old.clone(),
+            after: new.clone(),
+        });
+    }
+}
+
+/// The severity of a structural change, see [`Change::severity`].
+pub const STRUCTURAL_SEVERITY: f64 = 10.0;
+/// The severity of a dimensional change, and the bound of positional ones.
+pub const DIMENSIONAL_SEVERITY: f64 = 5.0;
+
+/// The kind of edit a change represents, for filtering diffs.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeCategory {
+    /// The geometry moved, e.g. a coordinate or an anchor changed.
+    Positional,
+    /// The geometry changed size, e.g. a width or a radius changed.
+    Dimensional,
+    /// Nodes, array elements or references were added, removed or rewired.
+    Structural,
+}
+
+impl ChangeCategory {
+    /// Classifies a change by the field it touches.
+    fn of(change: &Change) -> Self {
+        let field = change.path().field();
+        let Some(last) = field.last() else {
+            return ChangeCategory::Structural;
+        };
+        if !matches!(change, Change::Modified { .. }) || last == "uuid" {
+            return ChangeCategory::Structural;
+        }
+
+        match last.as_str() {
+            "width" | "height" | "radius" => ChangeCategory::Dimensional,
+            _ if field
+                .iter()
+                .any(|f| matches!(f.as_str(), "x" | "y" | "z" | "anchor")) =>
+            {
+                ChangeCategory::Positional
+            }
+            _ => ChangeCategory::Structural,
+        }
+    }
+}
+
+/// How a single node differs between two collections, and who changed it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NodeChange {
+    pub kind: NodeChangeKind,
+    /// The author the newer collection attributed its edits to when the change
+    /// was taken, see [`NodeCollection::set_current_author`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+/// What changed about a node, see [`NodeChange`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum NodeChangeKind {
+    /// The node only exists in the newer collection.
+    Added(Value),
+    /// The node only exists in the older collection.
+    Removed(Value),
+    /// The node exists in both, with the given field changes.
+    Modified(Vec<Change>),
+    /// The uuid belongs to nodes of different types, so its fields are not compared.
+    TypeChanged { from: String, to: String },
+}
+
+/// Formats a changed value for display, leaving strings unquoted.
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Quotes a csv field if it needs to be, doubling the quotes inside of it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A single change flattened for tabular display.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DiffRow {
+    pub node: Uuid,
+    pub node_type: String,
+    /// The changed field, `/` separated. Changes to side maps like
+    /// the layers are prefixed with the name of the map.
+    pub field: String,
+    /// The [`Change::kind`], or the name of an [`Change::Operation`].
+    pub change: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// All changes between two collections.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    changes: Vec<Change>,
+    /// Type tags of the nodes the changes touch.
+    #[serde(default)]
+    node_types: BTreeMap<Uuid, String>,
+    /// The categories the types of the touched nodes give to changes, by path,
+    /// where they override [`Change::category`].
+    #[serde(default)]
+    categories: BTreeMap<String, ChangeCategory>,
+    /// The extra field keys of the touched nodes, which [`Diff::partition`]
+    /// counts as metadata.
+    #[serde(default)]
+    extra_keys: BTreeMap<Uuid, BTreeSet<String>>,
+}
+
+impl Diff {
+    pub fn new(changes: Vec<Change>) -> Self {
+        Self {
+            changes,
+            node_types: BTreeMap::new(),
+            categories: BTreeMap::new(),
+            extra_keys: BTreeMap::new(),
+        }
+    }
+
+    /// The type tag of a node touched by this diff, if known.
+    pub fn node_type(&self, uuid: &Uuid) -> Option<&str> {
+        self.node_types.get(uuid).map(String::as_str)
+    }
+
+    /// One row per change that touches a node.
+    /// Changes to collection wide bookkeeping, which belong to no node, are left out.
+    pub fn to_rows(&self) -> Vec<DiffRow> {
+        self.changes
+            .iter()
+            .filter_map(|change| {
+                let path = change.path();
+                let node = path.node()?;
+                let field = match path.section() {
+                    Some("nodes") => path.field().join("/"),
+                    _ => path.segments()[..1]
+                        .iter()
+                        .chain(path.field())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                };
+
+                Some(DiffRow {
+                    node,
+                    node_type: self.node_type(&node).unwrap_or_default().to_string(),
+                    field,
+                    change: match change {
+                        Change::Operation { name, .. } => name.clone(),
+                        _ => change.kind().to_string(),
+                    },
+                    before: change.before().map(display_value),
+                    after: change.after().map(display_value),
+                })
+            })
+            .collect()
+    }
+
+    /// The diff as text in the style of a unified diff, e.g. for failing tests.
+    ///
+    /// Changes are grouped under a `@@` header per node and side map, in the
+    /// order of [`Diff::sorted`]. Each removed value is a `-` line and each added
+    /// one a `+` line, and operations carry their name in a `#` line before them.
+    pub fn to_unified_text(&self) -> String {
+        let mut text = String::from("--- before\n+++ after\n");
+        let mut section = None;
+        for change in self.clone().sorted().changes {
+            let path = change.path();
+            let node = path.node();
+            let (header, field) = match node {
+                Some(_) => (path.segments()[..2].join("/"), path.field().join("/")),
+                None => (String::new(), path.to_string()),
+            };
+            if section.as_ref() != Some(&header) {
+                match node.and_then(|node| self.node_type(&node)) {
+                    Some(tag) => text.push_str(&format!("@@ {} ({}) @@\n", header, tag)),
+                    None if header.is_empty() => text.push_str("@@ bookkeeping @@\n"),
+                    None => text.push_str(&format!("@@ {} @@\n", header)),
+                }
+                section = Some(header);
+            }
+
+            let label = if field.is_empty() { "." } else { &field };
+            if let Change::Operation { name, .. } = &change {
+                text.push_str(&format!("# {}\n", name));
+            }
+            if let Some(before) = change.before() {
+                text.push_str(&format!("- {}: {}\n", label, display_value(before)));
+            }
+            if let Some(after) = change.after() {
+                text.push_str(&format!("+ {}: {}\n", label, display_value(after)));
+            }
+        }
+        text
+    }
+
+    /// The [`Diff::to_rows`] as csv, with a header row and `\r\n` line ends.
+    /// Fields holding commas, quotes or line breaks are quoted, missing values are empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("node,node_type,field,change,before,after\r\n");
+        for row in self.to_rows() {
+            let fields = [
+                row.node.to_string(),
+                row.node_type,
+                row.field,
+                row.change,
+                row.before.unwrap_or_default(),
+                row.after.unwrap_or_default(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+
+    pub(crate) fn set_node_type(&mut self, uuid: Uuid, tag: String) {
+        self.node_types.insert(uuid, tag);
+    }
+
+    /// The categories the node types override, keyed by the path of the change.
+    pub(crate) fn overridden_categories(&self) -> &BTreeMap<String, ChangeCategory> {
+        &self.categories
+    }
+
+    pub(crate) fn set_category(&mut self, path: String, category: ChangeCategory) {
+        self.categories.insert(path, category);
+    }
+
+    /// The extra field keys known for the touched nodes.
+    pub(crate) fn extra_keys(&self) -> &BTreeMap<Uuid, BTreeSet<String>> {
+        &self.extra_keys
+    }
+
+    pub(crate) fn add_extra_key(&mut self, uuid: Uuid, key: String) {
+        self.extra_keys.entry(uuid).or_default().insert(key);
+    }
+
+    /// Whether the change is to an extra field of its node.
+    fn is_extra_field(&self, change: &Change) -> bool {
+        let path = change.path();
+        match (path.node(), path.field().first()) {
+            (Some(uuid), Some(key)) => self.extra_keys.get(&uuid).is_some_and(|k| k.contains(key)),
+            _ => false,
+        }
+    }
+
+    /// Takes over the type tags, categories and extra field keys `other` knows
+    /// for the changes of `self`, keeping those `self` knows already.
+    pub(crate) fn adopt_node_types(&mut self, other: &Diff) {
+        for change in &self.changes {
+            let path = change.path();
+            if let Some(uuid) = path.node() {
+                if let Some(tag) = other.node_types.get(&uuid) {
+                    self.node_types.entry(uuid).or_insert_with(|| tag.clone());
+                }
+                if let Some(keys) = other.extra_keys.get(&uuid) {
+                    self.extra_keys
+                        .entry(uuid)
+                        .or_default()
+                        .extend(keys.iter().cloned());
+                }
+            }
+            let path = path.to_string();
+            if let Some(category) = other.categories.get(&path) {
+                self.categories.entry(path).or_insert(*category);
+            }
+        }
+    }
+
+    /// Looks up the type tags of all touched nodes and the categories their
+    /// types give to the changes, preferring the newer collection, and which
+    /// changed fields are extra fields on either side.
+    pub(crate) fn record_node_types(&mut self, before: &NodeCollection, after: &NodeCollection) {
+        for change in &self.changes {
+            let Some(uuid) = change.path().node() else {
+                continue;
+            };
+            if let Some(key) = change.path().field().first() {
+                let sides = [before.nodes.get(&uuid), after.nodes.get(&uuid)];
+                if sides
+                    .iter()
+                    .flatten()
+                    .any(|n| n.extra().is_some_and(|e| e.contains_key(key)))
+                {
+                    self.extra_keys.entry(uuid).or_default().insert(key.clone());
+                }
+            }
+            if let Some(node) = after.nodes.get(&uuid).or_else(|| before.nodes.get(&uuid)) {
+                self.node_types.insert(uuid, node.type_tag().to_string());
+                if let Some(category) = node.change_category(change.path().field()) {
+                    self.categories.insert(change.path().to_string(), category);
+                }
+            }
+        }
+    }
+
+    /// The same diff with its changes in a reproducible order: by node,
+    /// then by path, comparing array indices numerically, then by kind.
+    /// Changes to collection wide bookkeeping come first.
+    pub fn sorted(mut self) -> Diff {
+        self.changes.sort_by(|a, b| {
+            a.path()
+                .node()
+                .cmp(&b.path().node())
+                .then_with(|| compare_paths(a.path(), b.path()))
+                .then_with(|| a.kind().cmp(b.kind()))
+        });
+        self
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The summed severity of all changes, to rank revisions by their impact.
+    pub fn total_severity(&self) -> f64 {
+        self.changes.iter().map(|c| self.severity(c)).sum()
+    }
+
+    /// The category of one of the changes of this diff, as the type of its node
+    /// classifies it, see [`Change::category_for`].
+    pub fn category(&self, change: &Change) -> ChangeCategory {
+        self.categories
+            .get(&change.path().to_string())
+            .copied()
+            .unwrap_or_else(|| change.category())
+    }
+
+    /// The severity of one of the changes of this diff, as the type of its node
+    /// categorizes it, see [`Change::severity_for`].
+    pub fn severity(&self, change: &Change) -> f64 {
+        change.severity_as(self.category(change))
+    }
+
+    /// The changes of the given category, e.g. only positional ones for move detection.
+    pub fn by_category(&self, category: ChangeCategory) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .filter(move |c| self.category(c) == category)
+    }
+
+    /// Splits the diff into the changes to the nodes themselves and the changes
+    /// to the side maps, like names, layers, locks and indices, keeping the order.
+    ///
+    /// Extra fields are stored on the nodes, but hold application data rather
+    /// than geometry, so their changes count as metadata.
+    pub fn partition(&self) -> (Diff, Diff) {
+        let (geometry, metadata): (Vec<Change>, Vec<Change>) = self
+            .changes
+            .iter()
+            .cloned()
+            .partition(|c| c.path().section() == Some("nodes") && !self.is_extra_field(c));
+        (self.with_changes(geometry), self.with_changes(metadata))
+    }
+
+    /// A diff of `changes`, keeping the type tags and categories known for them.
+    pub(crate) fn with_changes(&self, changes: Vec<Change>) -> Diff {
+        let mut diff = Diff::new(changes);
+        diff.adopt_node_types(self);
+        diff
+    }
+}
+
+pub(crate) fn node_value(node: &dyn GeometryNode) -> Value {
+    serde_json::to_value(node).expect("geometry nodes serialize to json")
+}
+
+impl NodeCollection {
+    /// Diffs the serialized forms of a single node between `self` and `other`,
+    /// unless its type diffs itself through [`DiffAware`](crate::domain::DiffAware).
+    ///
+    /// Returns `None` if the node is unchanged or absent from both collections.
+    /// The change carries the current author of `other`.
+    /// Fails like [`NodeCollection::diff`] if either side holds NaN or infinite floats.
+    pub fn diff_node(
+        &self,
+        other: &NodeCollection,
+        uuid: &Uuid,
+    ) -> Result<Option<NodeChange>, GeodiffError> {
+        let (before, after) = (self.nodes.get(uuid), other.nodes.get(uuid));
+        for node in before.iter().chain(after.iter()) {
+            check_node_finite(node.as_ref())?;
+        }
+        Ok(self.diff_finite_node(other, uuid).map(|kind| NodeChange {
+            kind,
+            author: other.current_author().map(str::to_string),
+        }))
+    }
+
+    fn diff_finite_node(&self, other: &NodeCollection, uuid: &Uuid) -> Option<NodeChangeKind> {
+        if let (Some(before), Some(after)) = (self.nodes.get(uuid), other.nodes.get(uuid)) {
+            if before.type_tag() != after.type_tag() {
+                return Some(NodeChangeKind::TypeChanged {
+                    from: before.type_tag().to_string(),
+                    to: after.type_tag().to_string(),
+                });
+            }
+        }
+
+        match (self.nodes.get(uuid), other.nodes.get(uuid)) {
+            (None, None) => None,
+            (None, Some(after)) => Some(NodeChangeKind::Added(node_value(after.as_ref()))),
+            (Some(before), None) => Some(NodeChangeKind::Removed(node_value(before.as_ref()))),
+            (Some(before), Some(after)) => {
+                let changes = diff_nodes(before.as_ref(), after.as_ref());
+                (!changes.is_empty()).then_some(NodeChangeKind::Modified(changes))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        shapes::{Ellipse, Polyline},
+        Point3, Rectangle,
+    };
+
+    /// A collection holding one rectangle, and its uuid.
+    fn with_rectangle() -> (NodeCollection, Uuid) {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        (collection, id)
+    }
+
+    fn modified(path: ChangePath) -> Change {
+        Change::Modified {
+            path,
+            before: Value::from(1.0),
+            after: Value::from(2.0),
+        }
+    }
+
+    #[test]
+    fn width_is_dimensional_and_anchor_x_positional() {
+        let id = Uuid::new_v4();
+        let root = ChangePath::node_root(&id);
+        assert_eq!(
+            modified(root.join("width")).category(),
+            ChangeCategory::Dimensional
+        );
+        assert_eq!(
+            modified(root.join("anchor").join("x")).category(),
+            ChangeCategory::Positional
+        );
+    }
+
+    #[test]
+    fn node_types_override_the_category_of_their_fields() {
+        let mut before = NodeCollection::new();
+        let (ellipse, mut polyline) = (Ellipse::new(), Polyline::new());
+        polyline.push(0.0, 0.0, 0.0);
+        let (ellipse_id, polyline_id) = (ellipse.uuid(), polyline.uuid());
+        before.push(Box::new(ellipse)).unwrap();
+        before.push(Box::new(polyline)).unwrap();
+        let mut after = before.snapshot();
+        after
+            .update(&ellipse_id, |e: &mut Ellipse| *e.radius_x_mut() = 2.0)
+            .unwrap();
+        after.translate_nodes(&[polyline_id], 0.001, 0.0, 0.0);
+
+        let diff = before.diff(&after).unwrap();
+
+        let paths = |category| {
+            diff.by_category(category)
+                .map(|c| c.path().field().join("/"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(ChangeCategory::Dimensional), ["radius_x"]);
+        assert_eq!(paths(ChangeCategory::Positional), ["points/0/0"]);
+        let nudge = modified(
+            ChangePath::node_root(&polyline_id)
+                .join("points")
+                .join("0")
+                .join("0"),
+        );
+        assert_eq!(nudge.category(), ChangeCategory::Structural);
+        assert_eq!(
+            nudge.category_for(after.get(&polyline_id).unwrap()),
+            ChangeCategory::Positional
+        );
+    }
+
+    #[test]
+    fn edits_of_a_rectangle_are_categorized_by_field() {
+        let (before, id) = with_rectangle();
+        let mut after = before.snapshot();
+        after
+            .update(&id, |r: &mut Rectangle| {
+                *r.width_mut() = 3.0;
+                r.anchor_mut().x = 1.0;
+            })
+            .unwrap();
+
+        let diff = before.diff(&after).unwrap();
+        let category = |field: &str| {
+            diff.changes()
+                .iter()
+                .find(|c| c.path().field().join("/") == field)
+                .map(Change::category)
+        };
+        assert_eq!(category("width"), Some(ChangeCategory::Dimensional));
+        assert_eq!(category("anchor/x"), Some(ChangeCategory::Positional));
+    }
+
+    #[test]
+    fn rows_of_a_modified_rectangle() {
+        let (before, id) = with_rectangle();
+        let mut after = before.snapshot();
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 3.5)
+            .unwrap();
+        let width = before.try_get_typed::<Rectangle>(&id).unwrap().width;
+
+        let rows = before.diff(&after).unwrap().to_rows();
+
+        assert_eq!(
+            rows,
+            vec![DiffRow {
+                node: id,
+                node_type: "Rectangle".to_string(),
+                field: "width".to_string(),
+                change: "modified".to_string(),
+                before: Some(Value::from(width).to_string()),
+                after: Some("3.5".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn replacing_a_point_with_a_rectangle_is_a_type_change() {
+        let mut before = NodeCollection::new();
+        let point = Point3::new();
+        let id = point.uuid;
+        before.push(Box::new(point)).unwrap();
+        let mut after = before.snapshot();
+        after.remove(&id).unwrap();
+        let mut rectangle = Rectangle::new();
+        rectangle.uuid = id;
+        after.push(Box::new(rectangle)).unwrap();
+
+        let change = before.diff_node(&after, &id).unwrap().unwrap();
+
+        assert_eq!(
+            change.kind,
+            NodeChangeKind::TypeChanged {
+                from: "Point3".to_string(),
+                to: "Rectangle".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn adding_a_node_outranks_a_tiny_nudge() {
+        let (before, id) = with_rectangle();
+        let mut nudged = before.snapshot();
+        nudged
+            .update(&id, |r: &mut Rectangle| r.anchor_mut().x += 0.001)
+            .unwrap();
+        let mut extended = before.snapshot();
+        extended.push(Box::new(Point3::new())).unwrap();
+
+        let nudge = before.diff(&nudged).unwrap();
+        let add = before.diff(&extended).unwrap();
+
+        assert!(node_severity(&nudge) < 0.01);
+        assert_eq!(node_severity(&add), STRUCTURAL_SEVERITY);
+        assert!(add.total_severity() > nudge.total_severity());
+    }
+
+    /// The severity of the first change of `diff` to a node.
+    fn node_severity(diff: &Diff) -> f64 {
+        let change = diff
+            .changes()
+            .iter()
+            .find(|c| c.path().section() == Some("nodes"));
+        diff.severity(change.unwrap())
+    }
+
+    #[test]
+    fn a_tiny_nudge_of_a_polyline_point_scores_low() {
+        let mut before = NodeCollection::new();
+        let mut polyline = Polyline::new();
+        polyline.push(0.0, 0.0, 0.0);
+        let id = polyline.uuid();
+        before.push(Box::new(polyline)).unwrap();
+        let mut nudged = before.snapshot();
+        nudged.translate_nodes(&[id], 0.001, 0.0, 0.0);
+        let mut extended = before.snapshot();
+        extended.push(Box::new(Point3::new())).unwrap();
+
+        let nudge = before.diff(&nudged).unwrap();
+        let add = before.diff(&extended).unwrap();
+
+        assert!(node_severity(&nudge) < 0.01);
+        assert!(nudge.changes()[0].severity_for(nudged.get(&id).unwrap()) < 0.01);
+        assert!(add.total_severity() > nudge.total_severity());
+    }
+
+    #[test]
+    fn a_combined_edit_partitions_into_geometry_and_metadata() {
+        let (before, id) = with_rectangle();
+        let mut after = before.snapshot();
+        after
+            .update(&id, |r: &mut Rectangle| {
+                *r.width_mut() = 3.0;
+                r.anchor_mut().x = 1.0;
+            })
+            .unwrap();
+        after.set_name(&id, "door").unwrap();
+        after
+            .set_extra_field(&id, "material", "oak".into())
+            .unwrap();
+
+        let (geometry, metadata) = before.diff(&after).unwrap().sorted().partition();
+
+        fn sections(diff: &Diff) -> Vec<Option<&str>> {
+            diff.changes().iter().map(|c| c.path().section()).collect()
+        }
+        assert_eq!(sections(&geometry), [Some("nodes"), Some("nodes")]);
+        assert_eq!(sections(&metadata), [Some("names"), Some("nodes")]);
+        assert_eq!(metadata.changes()[1].path().field(), ["material"]);
+        assert_eq!(geometry.changes()[0].path().node(), Some(id));
+    }
+
+    #[test]
+    fn csv_fields_holding_commas_and_quotes_are_quoted() {
+        let (before, id) = with_rectangle();
+        let mut after = before.snapshot();
+        after.set_name(&id, "door, \"left\"").unwrap();
+
+        let csv = before.diff(&after).unwrap().to_csv();
+
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], "node,node_type,field,change,before,after");
+        assert_eq!(
+            lines[1],
+            format!("{},Rectangle,names,added,,\"door, \"\"left\"\"\"", id)
+        );
+        assert_eq!(lines[2..], [""]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use uuid::Uuid;
+
+use crate::{
+    diff::{node_value, Change, ChangePath},
+    shapes::Line,
+    GeometryNode, NodeCollection,
+};
+
+/// Node types that diff themselves, to report edits the generic json diff
+/// only sees as unrelated field changes, like a line swapping its end points.
+///
+/// Hook a type up through [`GeometryNode::diff_against_node`], the diff
+/// falls back to the json diff for all other types.
+pub trait DiffAware: GeometryNode + Sized {
+    /// The changes turning `self` into `other`, with paths from the collection root.
+    fn diff_against(&self, other: &Self) -> Vec<Change> {
+        json_diff(self, other)
+    }
+}
+
+/// Diffs the serialized forms of two versions of a node.
+pub(crate) fn json_diff(before: &dyn GeometryNode, after: &dyn GeometryNode) -> Vec<Change> {
+    Change::record(
+        &ChangePath::node_root(&before.uuid()),
+        &node_value(before),
+        &node_value(after),
+    )
+}
+
+/// Diffs two versions of a node, preferring a [`DiffAware`] implementation.
+pub(crate) fn diff_nodes(before: &dyn GeometryNode, after: &dyn GeometryNode) -> Vec<Change> {
+    before
+        .diff_against_node(after)
+        .unwrap_or_else(|| json_diff(before, after))
+}
+
+/// Replaces the json changes of all nodes of a [`DiffAware`] type with the
+/// changes the type reports itself.
+pub(crate) fn prefer_domain_changes(
+    mut changes: Vec<Change>,
+    before: &NodeCollection,
+    after: &NodeCollection,
+) -> Vec<Change> {
+    let touched: BTreeSet<Uuid> = changes
+        .iter()
+        .filter(|c| c.path().section() == Some("nodes"))
+        .filter_map(|c| c.path().node())
+        .collect();
+
+    for uuid in touched {
+        let (Some(old), Some(new)) = (before.nodes.get(&uuid), after.nodes.get(&uuid)) else {
+            continue;
+        };
+        if let Some(domain) = old.diff_against_node(new.as_ref()) {
+            changes
+                .retain(|c| c.path().section() != Some("nodes") || c.path().node() != Some(uuid));
+            changes.extend(domain);
+        }
+    }
+
+    changes
+}
+
+/// Reports a line whose end points swapped as one `reversed` operation.
+impl DiffAware for Line {
+    fn diff_against(&self, other: &Self) -> Vec<Change> {
+        let reversed = self.start.same_position(&other.end)
+            && self.end.same_position(&other.start)
+            && !self.start.same_position(&self.end)
+            && self.extra == other.extra;
+        if !reversed {
+            return json_diff(self, other);
+        }
+
+        vec![Change::Operation {
+            path: ChangePath::node_root(&self.uuid),
+            name: "reversed".to_string(),
+            before: node_value(self),
+            after: node_value(other),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A collection holding a line from `[0, 0, 0]` to `[3, 4, 0]`, and its uuid.
+    fn with_line() -> (NodeCollection, Uuid) {
+        let mut line = Line::new();
+        (line.end_mut().x, line.end_mut().y) = (3.0, 4.0);
+        let id = line.uuid();
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(line)).unwrap();
+        (collection, id)
+    }
+
+    #[test]
+    fn reversing_a_line_diffs_as_one_operation() {
+        let (before, id) = with_line();
+        let mut after = before.snapshot();
+        after.update(&id, Line::reverse).unwrap();
+
+        let diff = before.diff(&after).unwrap();
+
+        assert!(matches!(
+            diff.changes(),
+            [Change::Operation { path, name, .. }]
+                if *path == ChangePath::node_root(&id) && name == "reversed"
+        ));
+        let mut applied = before.snapshot();
+        applied.apply(&diff).unwrap();
+        assert_eq!(applied.diff(&after).unwrap().changes(), []);
+    }
+
+    #[test]
+    fn other_line_edits_diff_field_by_field() {
+        let (before, id) = with_line();
+        let mut after = before.snapshot();
+        after
+            .update(&id, |l: &mut Line| l.end_mut().x = 6.0)
+            .unwrap();
+
+        let diff = before.diff(&after).unwrap();
+
+        assert!(matches!(
+            diff.changes(),
+            [Change::Modified { path, .. }] if path.field() == ["end", "x"]
+        ));
+    }
+}
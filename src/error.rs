@@ -0,0 +1,136 @@
+use std::fmt::Display;
+
+use uuid::Uuid;
+
+use crate::lock::EditError;
+
+/// Errors of reading and writing collections.
+#[derive(Debug)]
+pub enum GeodiffError {
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    #[cfg(feature = "cbor")]
+    CborDeserialize(ciborium::de::Error<std::io::Error>),
+    #[cfg(feature = "cbor")]
+    CborSerialize(ciborium::ser::Error<std::io::Error>),
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    #[cfg(feature = "toml")]
+    TomlDeserialize(toml::de::Error),
+    #[cfg(feature = "toml")]
+    TomlSerialize(toml::ser::Error),
+    /// A float of the node is NaN or infinite, which json can not represent.
+    NonFiniteValue {
+        uuid: Uuid,
+        field: String,
+    },
+    /// A node the operation would change can not be edited, e.g. as it is locked.
+    Edit(EditError),
+    /// A line of canonical text, counted from 1, does not read,
+    /// see [`NodeCollection::from_canonical_text`](crate::NodeCollection::from_canonical_text).
+    CanonicalText {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl Display for GeodiffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeodiffError::Json(e) => write!(f, "json error: {}", e),
+            GeodiffError::Io(e) => write!(f, "io error: {}", e),
+            #[cfg(feature = "cbor")]
+            GeodiffError::CborDeserialize(e) => write!(f, "cbor error: {}", e),
+            #[cfg(feature = "cbor")]
+            GeodiffError::CborSerialize(e) => write!(f, "cbor error: {}", e),
+            #[cfg(feature = "yaml")]
+            GeodiffError::Yaml(e) => write!(f, "yaml error: {}", e),
+            #[cfg(feature = "toml")]
+            GeodiffError::TomlDeserialize(e) => write!(f, "toml error: {}", e),
+            #[cfg(feature = "toml")]
+            GeodiffError::TomlSerialize(e) => write!(f, "toml error: {}", e),
+            GeodiffError::NonFiniteValue { uuid, field } => {
+                write!(f, "non finite value in {} of node {}", field, uuid)
+            }
+            GeodiffError::Edit(e) => write!(f, "edit error: {}", e),
+            GeodiffError::CanonicalText { line, reason } => {
+                write!(f, "canonical text error in line {}: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeodiffError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GeodiffError::Json(e) => Some(e),
+            GeodiffError::Io(e) => Some(e),
+            #[cfg(feature = "cbor")]
+            GeodiffError::CborDeserialize(e) => Some(e),
+            #[cfg(feature = "cbor")]
+            GeodiffError::CborSerialize(e) => Some(e),
+            #[cfg(feature = "yaml")]
+            GeodiffError::Yaml(e) => Some(e),
+            #[cfg(feature = "toml")]
+            GeodiffError::TomlDeserialize(e) => Some(e),
+            #[cfg(feature = "toml")]
+            GeodiffError::TomlSerialize(e) => Some(e),
+            GeodiffError::NonFiniteValue { .. } => None,
+            GeodiffError::Edit(e) => Some(e),
+            GeodiffError::CanonicalText { .. } => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for GeodiffError {
+    fn from(e: serde_json::Error) -> Self {
+        GeodiffError::Json(e)
+    }
+}
+
+impl From<std::io::Error> for GeodiffError {
+    fn from(e: std::io::Error) -> Self {
+        GeodiffError::Io(e)
+    }
+}
+
+impl From<EditError> for GeodiffError {
+    fn from(e: EditError) -> Self {
+        GeodiffError::Edit(e)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<ciborium::de::Error<std::io::Error>> for GeodiffError {
+    fn from(e: ciborium::de::Error<std::io::Error>) -> Self {
+        GeodiffError::CborDeserialize(e)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<ciborium::ser::Error<std::io::Error>> for GeodiffError {
+    fn from(e: ciborium::ser::Error<std::io::Error>) -> Self {
+        GeodiffError::CborSerialize(e)
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for GeodiffError {
+    fn from(e: serde_yaml::Error) -> Self {
+        GeodiffError::Yaml(e)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<toml::de::Error> for GeodiffError {
+    fn from(e: toml::de::Error) -> Self {
+        GeodiffError::TomlDeserialize(e)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<toml::ser::Error> for GeodiffError {
+    fn from(e: toml::ser::Error) -> Self {
+        GeodiffError::TomlSerialize(e)
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::NodeCollection;
+
+/// Notification about an edit to a node of a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeEvent {
+    Added(Uuid),
+    Removed(Uuid),
+    Modified(Uuid),
+    /// Several edits made during a [`NodeCollection::batch`], listing each
+    /// touched node once in the order it was first touched.
+    Batch(Vec<Uuid>),
+}
+
+impl NodeEvent {
+    /// The nodes the event is about.
+    pub fn uuids(&self) -> &[Uuid] {
+        match self {
+            NodeEvent::Added(uuid) | NodeEvent::Removed(uuid) | NodeEvent::Modified(uuid) => {
+                std::slice::from_ref(uuid)
+            }
+            NodeEvent::Batch(uuids) => uuids,
+        }
+    }
+}
+
+pub type Listener = Box<dyn FnMut(&NodeEvent) + Send + Sync>;
+
+impl NodeCollection {
+    /// Registers a listener that is called after every edit to the collection.
+    pub fn on_change(&mut self, listener: impl FnMut(&NodeEvent) + Send + Sync + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Runs `f` with individual events held back, then emits a single
+    /// [`NodeEvent::Batch`] if `f` touched any node.
+    ///
+    /// Nested batches fold into the outermost one.
+    pub fn batch(&mut self, f: impl FnOnce(&mut NodeCollection)) {
+        if self.batched.is_some() {
+            f(self);
+            return;
+        }
+
+        self.batched = Some(Vec::new());
+        f(self);
+        let mut seen = HashSet::new();
+        let touched: Vec<Uuid> = self
+            .batched
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|uuid| seen.insert(*uuid))
+            .collect();
+        if !touched.is_empty() {
+            self.emit(NodeEvent::Batch(touched));
+        }
+    }
+
+    pub(crate) fn emit(&mut self, event: NodeEvent) {
+        self.attribute(&event);
+        if let Some(batched) = &mut self.batched {
+            batched.extend_from_slice(event.uuids());
+            return;
+        }
+
+        for listener in &mut self.listeners {
+            listener(&event);
+        }
+        self.notify_watchers(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{GeometryNode, Point3};
+
+    /// Records every event the collection emits.
+    fn recorded(collection: &mut NodeCollection) -> Arc<Mutex<Vec<NodeEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        collection.on_change(move |event| sink.lock().unwrap().push(event.clone()));
+        events
+    }
+
+    #[test]
+    fn editing_three_nodes_in_a_batch_emits_one_event() {
+        let mut collection = NodeCollection::new();
+        let points = [Point3::new(), Point3::new(), Point3::new()];
+        let ids = points.each_ref().map(|p| p.uuid());
+        for point in points {
+            collection.push(Box::new(point)).unwrap();
+        }
+        let events = recorded(&mut collection);
+
+        collection.batch(|c| {
+            for id in &ids {
+                c.update(id, |p: &mut Point3| p.x += 1.0).unwrap();
+            }
+            c.update(&ids[0], |p: &mut Point3| p.y += 1.0).unwrap();
+        });
+
+        assert_eq!(*events.lock().unwrap(), [NodeEvent::Batch(ids.to_vec())]);
+    }
+
+    #[test]
+    fn edits_outside_of_a_batch_emit_one_event_each() {
+        let mut collection = NodeCollection::new();
+        let point = Point3::new();
+        let id = point.uuid();
+        let events = recorded(&mut collection);
+
+        collection.push(Box::new(point)).unwrap();
+        collection.update(&id, |p: &mut Point3| p.x = 1.0).unwrap();
+        collection.remove(&id).unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                NodeEvent::Added(id),
+                NodeEvent::Modified(id),
+                NodeEvent::Removed(id)
+            ]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{events::NodeEvent, lock::EditError, NodeCollection};
+
+/// Application specific data stored on a node, keyed by field name.
+///
+/// Node types opt in with a `#[serde(flatten)] extra: ExtraFields` field,
+/// which keeps json keys the type does not know through round trips.
+pub type ExtraFields = BTreeMap<String, Value>;
+
+impl NodeCollection {
+    /// The extra field `name` of the node, if it has one.
+    pub fn extra_field(&self, key: &Uuid, name: &str) -> Option<&Value> {
+        self.nodes.get(key)?.extra()?.get(name)
+    }
+
+    /// Sets the extra field `name` of the node.
+    ///
+    /// Fails if the node is missing, locked or keeps no extra fields.
+    pub fn set_extra_field(
+        &mut self,
+        key: &Uuid,
+        name: impl Into<String>,
+        value: Value,
+    ) -> Result<(), EditError> {
+        self.extra_fields_mut(key)?.insert(name.into(), value);
+        self.emit(NodeEvent::Modified(*key));
+        Ok(())
+    }
+
+    /// Removes the extra field `name` of the node, returning its value if it had one.
+    ///
+    /// Fails if the node is missing, locked or keeps no extra fields.
+    pub fn remove_extra_field(
+        &mut self,
+        key: &Uuid,
+        name: &str,
+    ) -> Result<Option<Value>, EditError> {
+        let removed = self.extra_fields_mut(key)?.remove(name);
+        if removed.is_some() {
+            self.emit(NodeEvent::Modified(*key));
+        }
+        Ok(removed)
+    }
+
+    fn extra_fields_mut(&mut self, key: &Uuid) -> Result<&mut ExtraFields, EditError> {
+        self.check_editable(key)?;
+        let node = self
+            .nodes
+            .get_mut(key)
+            .ok_or(EditError::MissingNode(*key))?;
+        let found = node.type_tag();
+        node.extra_mut()
+            .ok_or(EditError::WrongType { uuid: *key, found })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{diff::Change, GeometryNode, Rectangle};
+
+    #[test]
+    fn an_unknown_key_survives_a_round_trip() {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        let mut value = collection.to_value().unwrap();
+        value["nodes"][id.to_string()]["color"] = json!({"fill": "red"});
+
+        let read = NodeCollection::from_value(value).unwrap();
+
+        assert_eq!(
+            read.extra_field(&id, "color"),
+            Some(&json!({"fill": "red"}))
+        );
+        let value = read.to_value().unwrap();
+        assert_eq!(value["nodes"][id.to_string()]["color"]["fill"], "red");
+    }
+
+    #[test]
+    fn extra_fields_diff_like_other_fields() {
+        let mut before = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        before.push(Box::new(rectangle)).unwrap();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after.set_extra_field(&id, "tag", json!("door")).unwrap();
+
+        let diff = before.diff(&after).unwrap();
+
+        assert!(matches!(
+            diff.changes(),
+            [Change::Added { path, value }] if path.field() == ["tag"] && *value == "door"
+        ));
+    }
+}
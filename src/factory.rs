@@ -0,0 +1,67 @@
+use uuid::Uuid;
+
+use crate::NodeCollection;
+
+/// Generates the uuids of newly created nodes.
+pub trait NodeFactory: Send + Sync {
+    fn new_id(&self) -> Uuid;
+}
+
+/// Random uuids, the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct V4Factory;
+
+impl NodeFactory for V4Factory {
+    fn new_id(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Time ordered uuids, so nodes sort by their creation time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct V7Factory;
+
+impl NodeFactory for V7Factory {
+    fn new_id(&self) -> Uuid {
+        Uuid::now_v7()
+    }
+}
+
+pub(crate) fn default_factory() -> Box<dyn NodeFactory> {
+    Box::new(V4Factory)
+}
+
+impl NodeCollection {
+    /// Creates an empty collection whose nodes get their uuids from `factory`.
+    pub fn with_factory(factory: impl NodeFactory + 'static) -> Self {
+        let mut collection = Self::new();
+        collection.factory = Box::new(factory);
+        collection
+    }
+
+    /// The factory to create nodes for this collection with.
+    pub fn factory(&self) -> &dyn NodeFactory {
+        self.factory.as_ref()
+    }
+
+    pub fn new_id(&self) -> Uuid {
+        self.factory.new_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3, Rectangle};
+
+    #[test]
+    fn v7_nodes_created_in_sequence_have_increasing_uuids() {
+        let collection = NodeCollection::with_factory(V7Factory);
+        let first = Point3::from_factory(collection.factory()).uuid();
+        let second = Rectangle::from_factory(collection.factory()).uuid();
+        let third = collection.new_id();
+
+        assert!(first < second);
+        assert!(second < third);
+    }
+}
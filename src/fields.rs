@@ -0,0 +1,458 @@
+use std::fmt::Display;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{
+    apply::{value_at, value_at_mut},
+    block::Block,
+    bounds::node_bounds,
+    diff::{node_value, Change, ChangePath},
+    events::NodeEvent,
+    lock::LockInfo,
+    shapes::{Arc, Circle, Ellipse, Line, PointCloud, Polyline},
+    GeometryNode, NodeCollection, Point3, Rectangle,
+};
+
+/// A typed view of the field paths of one node type.
+pub trait FieldPath: Sized {
+    /// Maps the keys below a node, as in [`crate::diff::ChangePath::field`], to a variant.
+    fn from_field(field: &[String]) -> Self;
+}
+
+impl Change {
+    /// The changed field as a typed path of the node type `F` belongs to.
+    pub fn typed_field<F: FieldPath>(&self) -> F {
+        F::from_field(self.path().field())
+    }
+}
+
+/// Declares a [`FieldPath`] enum for a node type, mapping each variant to a
+/// `.` separated field path. Paths not listed map to `Raw` with `/` separators.
+///
+/// Variants in the optional `indexed` section name an entry of an array or list field
+/// and carry its index, for changes to the entry or to a value inside of it,
+/// e.g. `points/3/0` maps to `Point(3)`.
+///
+/// The paths are checked against the node's fields at compile time, so
+/// renaming a field without updating its path fails the build.
+macro_rules! field_paths {
+    ($node:ty => $name:ident {
+        $($variant:ident: $($segment:ident).+,)*
+    } $(indexed {
+        $($indexed:ident: $($prefix:ident).+,)*
+    })?) => {
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant,)*
+            $($($indexed(usize),)*)?
+            Raw(String),
+        }
+
+        impl FieldPath for $name {
+            fn from_field(field: &[String]) -> Self {
+                let field: Vec<&str> = field.iter().map(String::as_str).collect();
+                $(
+                    if field == [$(stringify!($segment)),+] {
+                        return $name::$variant;
+                    }
+                )*
+                $($(
+                    let prefix = [$(stringify!($prefix)),+];
+                    if field.len() > prefix.len() && field[..prefix.len()] == prefix {
+                        if let Ok(index) = field[prefix.len()].parse() {
+                            return $name::$indexed(index);
+                        }
+                    }
+                )*)?
+                $name::Raw(field.join("/"))
+            }
+        }
+
+        const _: fn(&$node) = |node| {
+            $(let _ = &node.$($segment).+;)*
+            $($(let _ = &node.$($prefix).+[0];)*)?
+        };
+    };
+}
+
+field_paths!(Point3 => Point3Field {
+    X: x,
+    Y: y,
+    Z: z,
+    Uuid: uuid,
+});
+
+field_paths!(Rectangle => RectangleField {
+    AnchorX: anchor.x,
+    AnchorY: anchor.y,
+    AnchorZ: anchor.z,
+    AnchorUuid: anchor.uuid,
+    AnchorKind: anchor_kind,
+    Width: width,
+    Height: height,
+    Uuid: uuid,
+});
+
+field_paths!(Circle => CircleField {
+    CenterX: center.x,
+    CenterY: center.y,
+    CenterZ: center.z,
+    CenterUuid: center.uuid,
+    Radius: radius,
+    Uuid: uuid,
+});
+
+field_paths!(Line => LineField {
+    StartX: start.x,
+    StartY: start.y,
+    StartZ: start.z,
+    StartUuid: start.uuid,
+    EndX: end.x,
+    EndY: end.y,
+    EndZ: end.z,
+    EndUuid: end.uuid,
+    Uuid: uuid,
+});
+
+field_paths!(Ellipse => EllipseField {
+    CenterX: center.x,
+    CenterY: center.y,
+    CenterZ: center.z,
+    CenterUuid: center.uuid,
+    RadiusX: radius_x,
+    RadiusY: radius_y,
+    Uuid: uuid,
+});
+
+field_paths!(Arc => ArcField {
+    CenterX: center.x,
+    CenterY: center.y,
+    CenterZ: center.z,
+    CenterUuid: center.uuid,
+    Radius: radius,
+    StartAngle: start_angle,
+    SweepAngle: sweep_angle,
+    Uuid: uuid,
+});
+
+field_paths!(Polyline => PolylineField {
+    Points: points,
+    Closed: closed,
+    Uuid: uuid,
+} indexed {
+    Point: points,
+});
+
+field_paths!(PointCloud => PointCloudField {
+    Points: points,
+    Uuid: uuid,
+} indexed {
+    Point: points,
+});
+
+field_paths!(Block => BlockField {
+    Members: members,
+    Transform: transform,
+    Rotation: transform.rotation,
+    Scale: transform.scale,
+    Uuid: uuid,
+} indexed {
+    Member: members,
+    Translation: transform.translation,
+});
+
+/// Why [`GeometryNode::set_field`] could not write a field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldError {
+    /// The node has no top level field of that name.
+    UnknownField(String),
+    /// The field can not be written, like the uuid the collection keys the node by.
+    ReadOnly(String),
+    /// The value does not deserialize as the field's type.
+    InvalidValue { field: String, message: String },
+    /// The node type does not support writing fields.
+    Unsupported,
+    /// The collection holds no node with the uuid.
+    MissingNode(Uuid),
+    /// The node is locked, see [`NodeCollection::lock`].
+    Locked { uuid: Uuid, info: LockInfo },
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldError::UnknownField(field) => write!(f, "no field {}", field),
+            FieldError::ReadOnly(field) => write!(f, "field {} is read only", field),
+            FieldError::InvalidValue { field, message } => {
+                write!(f, "invalid value for field {}: {}", field, message)
+            }
+            FieldError::Unsupported => write!(f, "the node type does not support writing fields"),
+            FieldError::MissingNode(uuid) => write!(f, "no node {}", uuid),
+            FieldError::Locked { uuid, info } => {
+                write!(f, "node {} is locked: {}", uuid, info.reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// The largest difference at which [`NodeCollection::where_field`] still counts two numbers equal.
+pub const FIELD_EPSILON: f64 = 1e-9;
+
+/// The top level fields of a serialized node, without its `geometry_node` tag.
+pub(crate) fn node_fields(node: &dyn GeometryNode) -> Map<String, Value> {
+    match node_value(node) {
+        Value::Object(mut fields) => {
+            fields.remove("geometry_node");
+            fields
+        }
+        _ => Map::new(),
+    }
+}
+
+/// Writes a top level field of `node` by round tripping it through json,
+/// for node types to implement [`GeometryNode::set_field`] with.
+pub(crate) fn set_field_by_json<T>(node: &mut T, name: &str, value: Value) -> Result<(), FieldError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    if name == "uuid" {
+        return Err(FieldError::ReadOnly(name.to_string()));
+    }
+    let Ok(Value::Object(mut fields)) = serde_json::to_value(&*node) else {
+        return Err(FieldError::Unsupported);
+    };
+    match fields.get_mut(name) {
+        Some(field) => *field = value,
+        None => return Err(FieldError::UnknownField(name.to_string())),
+    }
+
+    *node =
+        serde_json::from_value(Value::Object(fields)).map_err(|e| FieldError::InvalidValue {
+            field: name.to_string(),
+            message: e.to_string(),
+        })?;
+    Ok(())
+}
+
+/// Json equality, except that numbers match if they differ by at most [`FIELD_EPSILON`].
+fn values_match(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() <= FIELD_EPSILON,
+            _ => x == y,
+        },
+        (Value::Array(xs), Value::Array(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| values_match(x, y))
+        }
+        (Value::Object(xs), Value::Object(ys)) => {
+            xs.len() == ys.len()
+                && xs
+                    .iter()
+                    .all(|(key, x)| ys.get(key).is_some_and(|y| values_match(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+impl NodeCollection {
+    /// All nodes whose serialized field at `field` matches `value`, sorted,
+    /// e.g. `where_field("width", &json!(10.0))` for everything 10 wide.
+    ///
+    /// The field is a path below the node like `anchor/x`, indexing arrays by position.
+    /// Numbers, also inside of arrays and objects, match if they differ by at most
+    /// [`FIELD_EPSILON`]. Nodes without the field never match.
+    pub fn where_field(&self, field: &str, value: &Value) -> Vec<Uuid> {
+        let segments: Vec<String> = field.split('/').map(str::to_string).collect();
+        let mut matches: Vec<Uuid> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| {
+                let fields = Value::Object(node_fields(node.as_ref()));
+                value_at(&fields, &segments).is_some_and(|v| values_match(v, value))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        matches.sort();
+        matches
+    }
+
+    /// The current value at `path` in the serialized collection,
+    /// e.g. `nodes/<uuid>/anchor/x` or `layers/<uuid>`.
+    pub fn get_value_at(&self, path: &ChangePath) -> Option<Value> {
+        let segments = path.segments();
+        if path.section() == Some("nodes") {
+            let node = self.nodes.get(&path.node()?)?;
+            let fields = Value::Object(node_fields(node.as_ref()));
+            return value_at(&fields, path.field()).cloned();
+        }
+
+        let bookkeeping =
+            serde_json::to_value(&self.bookkeeping).expect("bookkeeping serializes to json");
+        value_at(&bookkeeping, segments).cloned()
+    }
+
+    /// Writes the value at `path` below a node, e.g. `nodes/<uuid>/anchor/x`,
+    /// by rewriting its top level field through [`NodeCollection::set_field`].
+    ///
+    /// Only values that already exist can be written. Paths outside of
+    /// the nodes and paths to whole nodes are read only.
+    pub fn set_value_at(&mut self, path: &ChangePath, value: Value) -> Result<(), FieldError> {
+        let field = path.field();
+        let (Some("nodes"), Some(key), Some(name)) = (path.section(), path.node(), field.first())
+        else {
+            return Err(FieldError::ReadOnly(path.to_string()));
+        };
+        let Some(node) = self.nodes.get(&key) else {
+            return Err(FieldError::MissingNode(key));
+        };
+
+        let mut top = node_fields(node.as_ref())
+            .remove(name)
+            .ok_or_else(|| FieldError::UnknownField(name.clone()))?;
+        let target = value_at_mut(&mut top, &field[1..])
+            .ok_or_else(|| FieldError::UnknownField(field.join("/")))?;
+        *target = value;
+        self.set_field(&key, name, top)
+    }
+
+    /// Writes a top level field of a node through [`GeometryNode::set_field`],
+    /// keeping the caches of the collection in sync.
+    pub fn set_field(&mut self, key: &Uuid, name: &str, value: Value) -> Result<(), FieldError> {
+        if let Some(info) = self.lock_info(key) {
+            return Err(FieldError::Locked {
+                uuid: *key,
+                info: info.clone(),
+            });
+        }
+        let Some(node) = self.nodes.get_mut(key) else {
+            return Err(FieldError::MissingNode(*key));
+        };
+        let before = node_bounds(node.as_ref());
+        node.set_field(name, value)?;
+
+        let after = node_bounds(node.as_ref());
+        self.shrink_bounds(before);
+        self.expand_bounds(after);
+        self.rehash(*key, after);
+        self.emit(NodeEvent::Modified(*key));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn a_rectangles_width_reads_and_writes_through_the_generic_fields() {
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 2.0;
+
+        let fields = rectangle.fields();
+        assert_eq!(fields["width"], 2.0);
+        assert!(fields.contains_key("height") && !fields.contains_key("geometry_node"));
+
+        rectangle.set_field("width", json!(5.5)).unwrap();
+        assert_eq!(rectangle.fields()["width"], 5.5);
+    }
+
+    #[test]
+    fn invalid_field_writes_are_refused() {
+        let mut rectangle = Rectangle::new();
+
+        assert_eq!(
+            rectangle.set_field("depth", json!(1.0)),
+            Err(FieldError::UnknownField("depth".to_string()))
+        );
+        assert_eq!(
+            rectangle.set_field("uuid", json!(Uuid::new_v4())),
+            Err(FieldError::ReadOnly("uuid".to_string()))
+        );
+        assert!(matches!(
+            rectangle.set_field("width", json!("wide")),
+            Err(FieldError::InvalidValue { field, .. }) if field == "width"
+        ));
+        assert_eq!(rectangle.fields()["width"], 0.0);
+    }
+
+    #[test]
+    fn changes_read_back_as_typed_fields() {
+        let (mut before, mut rectangle) = (NodeCollection::new(), Rectangle::new());
+        let id = rectangle.uuid();
+        before.push(Box::new(rectangle.clone())).unwrap();
+        let mut after = NodeCollection::new();
+        rectangle.anchor_mut().x = 2.0;
+        *rectangle.width_mut() = 3.0;
+        after.push(Box::new(rectangle)).unwrap();
+
+        let fields: Vec<RectangleField> = (before.diff(&after).unwrap().sorted().changes())
+            .iter()
+            .filter(|c| c.path().node() == Some(id))
+            .map(Change::typed_field)
+            .collect();
+
+        assert_eq!(fields, [RectangleField::AnchorX, RectangleField::Width]);
+        let point = Change::Modified {
+            path: ChangePath::node_root(&id)
+                .join("points")
+                .join("3")
+                .join("0"),
+            before: json!(0.0),
+            after: json!(1.0),
+        };
+        assert_eq!(
+            point.typed_field::<PolylineField>(),
+            PolylineField::Point(3)
+        );
+        assert_eq!(
+            point.typed_field::<RectangleField>(),
+            RectangleField::Raw("points/3/0".to_string())
+        );
+    }
+
+    #[test]
+    fn a_rectangles_anchor_x_reads_and_writes_by_path() {
+        let mut collection = NodeCollection::new();
+        let mut rectangle = Rectangle::new();
+        rectangle.anchor_mut().x = 1.5;
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        let path = ChangePath::node_root(&id).join("anchor").join("x");
+
+        assert_eq!(collection.get_value_at(&path), Some(json!(1.5)));
+
+        collection.set_value_at(&path, json!(4.0)).unwrap();
+        assert_eq!(collection.get_value_at(&path), Some(json!(4.0)));
+        let rectangle = collection.try_get_typed::<Rectangle>(&id).unwrap();
+        assert_eq!(rectangle.anchor.x, 4.0);
+    }
+
+    #[test]
+    fn rectangles_are_found_by_width_in_a_mixed_collection() {
+        let mut collection = NodeCollection::new();
+        let mut wide = Vec::new();
+        for width in [10.0, 3.0, 10.0 + FIELD_EPSILON / 10.0] {
+            let mut rectangle = Rectangle::new();
+            *rectangle.width_mut() = width;
+            if width != 3.0 {
+                wide.push(rectangle.uuid());
+            }
+            collection.push(Box::new(rectangle)).unwrap();
+        }
+        let mut circle = Circle::new();
+        *circle.radius_mut() = 10.0;
+        collection.push(Box::new(circle)).unwrap();
+        collection.push(Box::new(Point3::new())).unwrap();
+        wide.sort();
+
+        assert_eq!(collection.where_field("width", &json!(10.0)), wide);
+        assert_eq!(collection.where_field("width", &json!(10)), wide);
+        assert!(collection.where_field("width", &json!("10")).is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use serde::{ser, Serialize};
+
+use crate::{error::GeodiffError, GeometryNode, NodeCollection};
+
+/// Stops the search, carrying the path of the first non finite float found
+/// or an error of the value's `Serialize` impl.
+#[derive(Debug)]
+enum Stop {
+    NonFinite(String),
+    Custom(String),
+}
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stop::NonFinite(path) => write!(f, "non finite value at {}", path),
+            Stop::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Stop {}
+
+impl ser::Error for Stop {
+    fn custom<T: Display>(msg: T) -> Self {
+        Stop::Custom(msg.to_string())
+    }
+}
+
+/// A serializer that looks for floats json can not represent, optionally
+/// hashing everything it visits on the way.
+///
+/// Building the path allocates for every field, so it is only tracked when
+/// a first, untracked pass found a non finite float.
+#[derive(Default)]
+struct FiniteCheck {
+    path: Vec<String>,
+    track_path: bool,
+    hasher: Option<DefaultHasher>,
+}
+
+impl FiniteCheck {
+    fn float(&mut self, v: f64) -> Result<(), Stop> {
+        if v.is_finite() {
+            self.feed(&v.to_bits());
+            Ok(())
+        } else {
+            Err(Stop::NonFinite(self.path.join("/")))
+        }
+    }
+
+    fn feed<T: Hash + ?Sized>(&mut self, v: &T) {
+        if let Some(hasher) = &mut self.hasher {
+            v.hash(hasher);
+        }
+    }
+
+    fn nested<T: Serialize + ?Sized>(
+        &mut self,
+        key: impl FnOnce() -> String,
+        value: &T,
+    ) -> Result<(), Stop> {
+        if !self.track_path {
+            return value.serialize(&mut *self);
+        }
+        self.path.push(key());
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    /// Enters an enum variant, see [`Compound::end`].
+    fn enter_variant(&mut self, variant: &'static str) -> Compound<'_> {
+        self.feed(variant);
+        if self.track_path {
+            self.path.push(variant.to_string());
+        }
+        Compound::variant(self)
+    }
+}
+
+/// Map keys in the collection are strings or numbers, read back for the path.
+fn key_of<T: Serialize + ?Sized>(key: &T) -> String {
+    match serde_json::to_value(key) {
+        Ok(serde_json::Value::String(s)) => s,
+        Ok(other) => other.to_string(),
+        Err(_) => "?".to_string(),
+    }
+}
+
+macro_rules! accept {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(fn $method(self, v: $ty) -> Result<(), Stop> {
+            self.feed(&v);
+            Ok(())
+        })*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut FiniteCheck {
+    type Ok = ();
+    type Error = Stop;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    accept!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+    );
+
+    fn serialize_f32(self, v: f32) -> Result<(), Stop> {
+        self.float(v as f64)
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Stop> {
+        self.float(v)
+    }
+    fn serialize_none(self) -> Result<(), Stop> {
+        self.feed(&None::<()>);
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Stop> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Stop> {
+        self.feed(&());
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Stop> {
+        self.feed(&());
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Stop> {
+        self.feed(variant);
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Stop> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Stop> {
+        self.feed(variant);
+        self.nested(|| variant.to_string(), value)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Stop> {
+        Ok(Compound::new(self))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, Stop> {
+        Ok(Compound::new(self))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Stop> {
+        Ok(Compound::new(self))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Stop> {
+        Ok(self.enter_variant(variant))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Stop> {
+        Ok(Compound::new(self))
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>, Stop> {
+        Ok(Compound::new(self))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Stop> {
+        Ok(self.enter_variant(variant))
+    }
+}
+
+/// Walks the elements, fields or entries of a sequence, struct or map.
+struct Compound<'a> {
+    check: &'a mut FiniteCheck,
+    index: usize,
+    key: Option<String>,
+    /// Whether the compound is an enum variant that pushed its name onto the path.
+    variant: bool,
+}
+
+impl<'a> Compound<'a> {
+    fn new(check: &'a mut FiniteCheck) -> Self {
+        Self {
+            check,
+            index: 0,
+            key: None,
+            variant: false,
+        }
+    }
+
+    fn variant(check: &'a mut FiniteCheck) -> Self {
+        Self {
+            variant: check.track_path,
+            ..Self::new(check)
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Stop> {
+        let index = self.index;
+        self.index += 1;
+        self.check.feed(&index);
+        self.check.nested(|| index.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), Stop> {
+        // Marks the end, so that `[[1, 2], [3]]` and `[[1], [2, 3]]` hash apart.
+        self.check.feed(&usize::MAX);
+        if self.variant {
+            self.check.path.pop();
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Stop> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Stop> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Stop> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Stop> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Stop> {
+        if self.check.hasher.is_some() {
+            key.serialize(&mut *self.check)?;
+        }
+        if self.check.track_path {
+            self.key = Some(key_of(key));
+        }
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Stop> {
+        let key = self.key.take().unwrap_or_default();
+        self.check.nested(|| key, value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Stop> {
+        self.check.feed(key);
+        self.check.nested(|| key.to_string(), value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Stop;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Stop> {
+        self.check.feed(key);
+        self.check.nested(|| key.to_string(), value)
+    }
+    fn end(self) -> Result<(), Stop> {
+        Compound::end(self)
+    }
+}
+
+/// The `/` separated path of the first NaN or infinite float in `value`.
+pub(crate) fn first_non_finite<T: Serialize + ?Sized>(value: &T) -> Option<String> {
+    if !matches!(
+        value.serialize(&mut FiniteCheck::default()),
+        Err(Stop::NonFinite(_))
+    ) {
+        return None;
+    }
+    let mut check = FiniteCheck {
+        track_path: true,
+        ..FiniteCheck::default()
+    };
+    match value.serialize(&mut check) {
+        Err(Stop::NonFinite(path)) => Some(path),
+        _ => None,
+    }
+}
+
+/// Hashes everything `node` serializes in one pass that also checks it for
+/// floats json can not represent, see [`crate::hash::content_hash`].
+pub(crate) fn finite_hash(node: &dyn GeometryNode) -> Result<u64, GeodiffError> {
+    let mut check = FiniteCheck {
+        hasher: Some(DefaultHasher::new()),
+        ..FiniteCheck::default()
+    };
+    match node.serialize(&mut check) {
+        Ok(()) => Ok(check.hasher.map_or(0, |hasher| hasher.finish())),
+        Err(Stop::NonFinite(_)) => Err(GeodiffError::NonFiniteValue {
+            uuid: node.uuid(),
+            field: first_non_finite(node).unwrap_or_default(),
+        }),
+        Err(Stop::Custom(msg)) => Err(GeodiffError::Json(ser::Error::custom(msg))),
+    }
+}
+
+/// Checks that `node` serializes to valid json, see [`NodeCollection::check_finite`].
+pub(crate) fn check_node_finite(node: &dyn GeometryNode) -> Result<(), GeodiffError> {
+    match first_non_finite(node) {
+        Some(field) => Err(GeodiffError::NonFiniteValue {
+            uuid: node.uuid(),
+            field,
+        }),
+        None => Ok(()),
+    }
+}
+
+impl NodeCollection {
+    /// Checks that all nodes serialize to valid json, which can not hold NaN
+    /// or infinite floats. `serde_json` would silently write `null` for them.
+    ///
+    /// Everything that serializes nodes to diff, hash, merge or write them runs
+    /// this check first. The first offending node by uuid is reported.
+    pub fn check_finite(&self) -> Result<(), GeodiffError> {
+        let mut nodes: Vec<_> = self.nodes.iter().collect();
+        nodes.sort_by_key(|(uuid, _)| **uuid);
+        for (_, node) in nodes {
+            check_node_finite(node.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{Point3, Rectangle};
+
+    /// The uuid and field of a [`GeodiffError::NonFiniteValue`].
+    fn non_finite<T>(result: Result<T, GeodiffError>) -> Option<(Uuid, String)> {
+        match result {
+            Err(GeodiffError::NonFiniteValue { uuid, field }) => Some((uuid, field)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn a_nan_point_fails_to_serialize_and_diff() {
+        let mut collection = NodeCollection::new();
+        let mut point = Point3::new();
+        point.x = f64::NAN;
+        let id = point.uuid();
+        collection.push(Box::new(point)).unwrap();
+        let expected = Some((id, "x".to_string()));
+
+        assert_eq!(non_finite(collection.to_value()), expected);
+        assert_eq!(
+            non_finite(collection.diff(&NodeCollection::new())),
+            expected
+        );
+        assert_eq!(
+            non_finite(NodeCollection::new().diff(&collection)),
+            expected
+        );
+    }
+
+    #[test]
+    fn nested_infinite_floats_report_their_path() {
+        let mut rectangle = Rectangle::new();
+        rectangle.anchor_mut().y = f64::NEG_INFINITY;
+
+        assert_eq!(first_non_finite(&rectangle), Some("anchor/y".to_string()));
+        assert_eq!(first_non_finite(&Rectangle::new()), None);
+    }
+
+    #[test]
+    fn hashing_checks_for_non_finite_floats_in_the_same_pass() {
+        let mut rectangle = Rectangle::new();
+        let before = finite_hash(&rectangle).unwrap();
+        assert_eq!(finite_hash(&rectangle).unwrap(), before);
+
+        rectangle.anchor_mut().y = 1.0;
+        assert_ne!(finite_hash(&rectangle).unwrap(), before);
+
+        rectangle.anchor_mut().y = f64::NAN;
+        assert_eq!(
+            non_finite(finite_hash(&rectangle)),
+            Some((rectangle.uuid(), "anchor/y".to_string()))
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{error::GeodiffError, finite::finite_hash, GeometryNode, NodeCollection};
+
+/// A hash over the serialized form of a node.
+/// Nodes with equal hashes are, barring collisions, unchanged.
+///
+/// Fails on NaN or infinite floats, which would hash like `null`.
+pub fn content_hash(node: &dyn GeometryNode) -> Result<u64, GeodiffError> {
+    finite_hash(node)
+}
+
+/// A hash over a json value, e.g. a part of a serialized node.
+pub(crate) fn value_hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+impl NodeCollection {
+    /// The [`content_hash`] of the node, `None` if the collection does not hold it.
+    pub fn content_hash(&self, key: &Uuid) -> Result<Option<u64>, GeodiffError> {
+        self.nodes
+            .get(key)
+            .map(|n| content_hash(n.as_ref()))
+            .transpose()
+    }
+}
@@ -0,0 +1,142 @@
+use uuid::Uuid;
+
+use crate::{
+    changelog::ChangeLog,
+    commit::{Commit, CommitLog},
+    diff::Diff,
+};
+
+/// The message of the commits [`History`] makes on its own.
+pub const AUTO_COMMIT_MESSAGE: &str = "Autosave";
+
+pub type CommitListener = Box<dyn FnMut(&Commit)>;
+
+/// A [`CommitLog`] fed by the edits of one author, which are collected in a
+/// [`ChangeLog`] until they are committed, e.g. for autosaving a document.
+pub struct History {
+    log: CommitLog,
+    pending: ChangeLog,
+    author: String,
+    auto_commit_threshold: Option<usize>,
+    listeners: Vec<CommitListener>,
+}
+
+impl History {
+    pub fn new(author: impl Into<String>) -> Self {
+        Self::from_log(CommitLog::new(), author)
+    }
+
+    /// A history committing on top of the head of an existing log.
+    pub fn from_log(log: CommitLog, author: impl Into<String>) -> Self {
+        Self {
+            log,
+            pending: ChangeLog::new(),
+            author: author.into(),
+            auto_commit_threshold: None,
+            listeners: Vec::new(),
+        }
+    }
+
+    pub fn log(&self) -> &CommitLog {
+        &self.log
+    }
+
+    /// The changes recorded since the last commit.
+    pub fn pending(&self) -> &ChangeLog {
+        &self.pending
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn auto_commit_threshold(&self) -> Option<usize> {
+        self.auto_commit_threshold
+    }
+
+    /// Commits the pending changes on their own once more than `threshold`
+    /// of them are recorded, `None` to only commit when asked to.
+    pub fn set_auto_commit_threshold(&mut self, threshold: Option<usize>) {
+        self.auto_commit_threshold = threshold;
+    }
+
+    /// Registers a listener that is called after every commit, including automatic ones.
+    pub fn on_commit(&mut self, listener: impl FnMut(&Commit) + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Records an edit, committing the pending changes with [`AUTO_COMMIT_MESSAGE`]
+    /// if they now exceed the auto commit threshold. Returns the id of that commit.
+    pub fn record(&mut self, diff: &Diff) -> Option<Uuid> {
+        self.pending.record(diff);
+        match self.auto_commit_threshold {
+            Some(threshold) if self.pending.len() > threshold => self.commit(AUTO_COMMIT_MESSAGE),
+            _ => None,
+        }
+    }
+
+    /// Commits the pending changes as one diff, returning the new commit's id,
+    /// or `None` if nothing is pending.
+    pub fn commit(&mut self, message: impl Into<String>) -> Option<Uuid> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let id = self
+            .log
+            .commit(self.author.clone(), message, self.pending.take());
+        if let Some(commit) = self.log.get(&id) {
+            for listener in &mut self.listeners {
+                listener(commit);
+            }
+        }
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{GeometryNode, NodeCollection, Point3};
+
+    /// Eleven diffs, each moving the same point along x.
+    fn moves() -> Vec<Diff> {
+        let mut current = NodeCollection::new();
+        let point = Point3::new();
+        let id = point.uuid();
+        current.push(Box::new(point)).unwrap();
+
+        (1..=11)
+            .map(|i| {
+                let mut moved = NodeCollection::from_value(current.to_value().unwrap()).unwrap();
+                moved.update(&id, |p: &mut Point3| p.x = i as f64).unwrap();
+                let diff = current.diff(&moved).unwrap();
+                current = moved;
+                diff
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_eleventh_change_commits_with_a_threshold_of_ten() {
+        let mut history = History::new("ada");
+        history.set_auto_commit_threshold(Some(10));
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&messages);
+        history.on_commit(move |commit| recorded.borrow_mut().push(commit.message.clone()));
+        let moves = moves();
+
+        for diff in &moves[..10] {
+            assert_eq!(history.record(diff), None);
+        }
+        assert_eq!(history.pending().len(), 10);
+        let id = history.record(&moves[10]);
+
+        assert!(id.is_some());
+        assert_eq!(history.log().head_id(), id);
+        assert!(history.pending().is_empty());
+        assert_eq!(*messages.borrow(), [AUTO_COMMIT_MESSAGE]);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    concrete_node,
+    shapes::{PointCloud, Polyline},
+    NodeCollection, Point3,
+};
+
+/// The z component of the cross product of `a - o` and `b - o`,
+/// positive if `o`, `a`, `b` turn counterclockwise.
+fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+/// Appends `p` to a hull chain, dropping the points it makes turn clockwise or run straight.
+fn push_hull_point(chain: &mut Vec<[f64; 2]>, p: [f64; 2]) {
+    while chain.len() >= 2 && cross(chain[chain.len() - 2], chain[chain.len() - 1], p) <= 0.0 {
+        chain.pop();
+    }
+    chain.push(p);
+}
+
+/// The convex hull of `points` counterclockwise from the point with the
+/// smallest x, and smallest y among those, by Andrew's monotone chain.
+fn monotone_chain(mut points: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut lower = Vec::new();
+    for &p in &points {
+        push_hull_point(&mut lower, p);
+    }
+    let mut upper = Vec::new();
+    for &p in points.iter().rev() {
+        push_hull_point(&mut upper, p);
+    }
+
+    // each chain ends where the other starts
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+impl NodeCollection {
+    /// The convex hull of all points in the collection, projected to the XY plane,
+    /// as a closed counterclockwise polyline at `z = 0`.
+    ///
+    /// Points are the [`Point3`] nodes and the points of [`PointCloud`] nodes.
+    /// With fewer than three distinct points the polyline holds just those, and
+    /// if all points lie on one line it holds its two end points. In both
+    /// cases it is left open, and empty for a collection without points.
+    pub fn convex_hull_xy(&self) -> Polyline {
+        let mut points = Vec::new();
+        for node in self.nodes.values() {
+            if let Some(p) = concrete_node::<Point3>(node.as_ref()) {
+                points.push([p.x, p.y]);
+            } else if let Some(cloud) = concrete_node::<PointCloud>(node.as_ref()) {
+                points.extend(cloud.points.iter().map(|p| [p[0], p[1]]));
+            }
+        }
+
+        let mut hull = Polyline::from_factory(self.factory());
+        hull.points = monotone_chain(points)
+            .into_iter()
+            .map(|[x, y]| [x, y, 0.0])
+            .collect();
+        hull.closed = hull.points.len() >= 3;
+        hull
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_points(points: &[[f64; 2]]) -> NodeCollection {
+        let mut collection = NodeCollection::new();
+        for &[x, y] in points {
+            let mut point = Point3::new();
+            (point.x, point.y) = (x, y);
+            collection.push(Box::new(point)).unwrap();
+        }
+        collection
+    }
+
+    #[test]
+    fn the_hull_of_a_square_skips_its_inner_points() {
+        let mut collection = with_points(&[[0.0, 0.0], [2.0, 0.0], [1.0, 1.0], [0.0, 2.0]]);
+        let mut cloud = PointCloud::new();
+        cloud.points = vec![[2.0, 2.0, 5.0], [0.5, 1.5, 0.0]];
+        collection.push(Box::new(cloud)).unwrap();
+
+        let hull = collection.convex_hull_xy();
+
+        assert!(hull.closed);
+        assert_eq!(
+            hull.points,
+            [
+                [0.0, 0.0, 0.0],
+                [2.0, 0.0, 0.0],
+                [2.0, 2.0, 0.0],
+                [0.0, 2.0, 0.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn collinear_points_give_the_open_segment_between_their_ends() {
+        let collection = with_points(&[[1.0, 1.0], [0.0, 0.0], [3.0, 3.0], [2.0, 2.0]]);
+
+        let hull = collection.convex_hull_xy();
+
+        assert!(!hull.closed);
+        assert_eq!(hull.points, [[0.0, 0.0, 0.0], [3.0, 3.0, 0.0]]);
+    }
+
+    #[test]
+    fn fewer_than_three_distinct_points_are_kept_as_they_are() {
+        let two = with_points(&[[1.0, 0.0], [0.0, 1.0], [1.0, 0.0]]).convex_hull_xy();
+        assert!(!two.closed);
+        assert_eq!(two.points, [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]);
+
+        let empty = NodeCollection::new().convex_hull_xy();
+        assert!(!empty.closed && empty.points.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::NodeCollection;
+
+/// Bidirectional map between node uuids and their numeric indices.
+/// Serializes as the uuid to index map only.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NodeIndices {
+    by_uuid: HashMap<Uuid, u32>,
+    by_index: HashMap<u32, Uuid>,
+}
+
+/// Compares what serializes, the reverse map follows from it.
+impl PartialEq for NodeIndices {
+    fn eq(&self, other: &Self) -> bool {
+        self.by_uuid == other.by_uuid
+    }
+}
+
+impl NodeIndices {
+    pub(crate) fn insert(&mut self, uuid: Uuid, index: u32) {
+        self.by_uuid.insert(uuid, index);
+        self.by_index.insert(index, uuid);
+    }
+
+    pub(crate) fn remove(&mut self, uuid: &Uuid) {
+        if let Some(index) = self.by_uuid.remove(uuid) {
+            self.by_index.remove(&index);
+        }
+    }
+
+    pub(crate) fn uuids(&self) -> impl Iterator<Item = &Uuid> {
+        self.by_uuid.keys()
+    }
+}
+
+impl From<HashMap<Uuid, u32>> for NodeIndices {
+    fn from(by_uuid: HashMap<Uuid, u32>) -> Self {
+        let by_index = by_uuid.iter().map(|(id, i)| (*i, *id)).collect();
+        Self { by_uuid, by_index }
+    }
+}
+
+impl Serialize for NodeIndices {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.by_uuid.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeIndices {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HashMap::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl NodeCollection {
+    /// Hands out the next index to a node that does not have one yet.
+    /// Indices of removed nodes are never handed out again.
+    pub(crate) fn assign_index(&mut self, uuid: Uuid) {
+        if self.bookkeeping.indices.by_uuid.contains_key(&uuid) {
+            return;
+        }
+        self.bookkeeping
+            .indices
+            .insert(uuid, self.bookkeeping.next_index);
+        self.bookkeeping.next_index += 1;
+    }
+
+    /// The stable numeric index assigned to the node when it was pushed.
+    pub fn index_of(&self, uuid: &Uuid) -> Option<u32> {
+        self.bookkeeping.indices.by_uuid.get(uuid).copied()
+    }
+
+    /// The node the numeric index was assigned to.
+    pub fn uuid_of(&self, index: u32) -> Option<Uuid> {
+        self.bookkeeping.indices.by_index.get(&index).copied()
+    }
+
+    /// Repairs the indices of a collection rebuilt from json against the
+    /// `previous` state, e.g. after applying a diff made on another copy.
+    ///
+    /// Nodes keep the index the json gives them, so nodes a diff adds back
+    /// return to their old position. Where several nodes claim one index the
+    /// node that held it in `previous` keeps it, nodes left without an index
+    /// get new ones in uuid order, and no index still in use is handed out again.
+    pub(crate) fn settle_indices(&mut self, previous: &NodeCollection) {
+        let mut claims: BTreeMap<u32, Vec<Uuid>> = BTreeMap::new();
+        for (uuid, index) in &self.bookkeeping.indices.by_uuid {
+            if self.nodes.contains_key(uuid) {
+                claims.entry(*index).or_default().push(*uuid);
+            }
+        }
+
+        let mut indices = NodeIndices::default();
+        let mut unindexed: Vec<Uuid> = self
+            .nodes
+            .keys()
+            .filter(|uuid| !self.bookkeeping.indices.by_uuid.contains_key(uuid))
+            .copied()
+            .collect();
+        for (index, mut uuids) in claims {
+            uuids.sort();
+            let keeper = uuids
+                .iter()
+                .position(|uuid| previous.index_of(uuid) == Some(index))
+                .unwrap_or(0);
+            indices.insert(uuids.remove(keeper), index);
+            unindexed.extend(uuids);
+        }
+
+        let next = indices.by_index.keys().max().map_or(0, |max| max + 1);
+        self.bookkeeping.next_index = self.bookkeeping.next_index.max(next);
+        self.bookkeeping.indices = indices;
+        unindexed.sort();
+        for uuid in unindexed {
+            self.assign_index(uuid);
+        }
+    }
+}
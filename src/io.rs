@@ -0,0 +1,582 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{diff::Diff, error::GeodiffError, fields::node_fields, GeometryNode, NodeCollection};
+
+/// Rounds all floats below `value` to multiples of `1 / scale`, leaving integers as they are.
+fn round_floats(value: &mut Value, scale: f64) {
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let rounded = n.as_f64().map(|f| (f * scale).round() / scale);
+            if let Some(rounded) = rounded.and_then(serde_json::Number::from_f64) {
+                *n = rounded;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| round_floats(v, scale)),
+        Value::Object(map) => map.values_mut().for_each(|v| round_floats(v, scale)),
+        _ => (),
+    }
+}
+
+/// Writes one `path = json` line per leaf of `value` for [`NodeCollection::to_canonical_text`].
+/// Arrays of plain values count as leaves, so a point stays on one line.
+fn write_canonical_lines(text: &mut String, path: &str, value: &Value) {
+    let join = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{}/{}", path, key),
+    };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, v) in map {
+                write_canonical_lines(text, &join(&canonical_key(key)), v);
+            }
+        }
+        Value::Array(items) if items.iter().any(|v| v.is_object() || v.is_array()) => {
+            for (i, v) in items.iter().enumerate() {
+                write_canonical_lines(text, &join(&i.to_string()), v);
+            }
+        }
+        _ => text.push_str(&format!("{} = {}\n", path, value)),
+    }
+}
+
+/// A key of a canonical text path, quoted as a json string unless it is plain,
+/// so it can neither be taken for an array index nor split at a `/`.
+fn canonical_key(key: &str) -> String {
+    let plain = !key.is_empty()
+        && (key.chars()).all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !key.chars().all(|c| c.is_ascii_digit());
+    if plain {
+        key.to_string()
+    } else {
+        Value::from(key).to_string()
+    }
+}
+
+/// A segment of a canonical text path, see [`NodeCollection::from_canonical_text`].
+enum CanonicalSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits a `path = json` line into the segments of its path and its value.
+fn parse_canonical_line(line: &str) -> Result<(Vec<CanonicalSegment>, Value), String> {
+    let mut segments = Vec::new();
+    let mut rest = line;
+    loop {
+        let (segment, tail) = if rest.starts_with('"') {
+            let end = closing_quote(rest).ok_or("unterminated key")?;
+            let key: String = serde_json::from_str(&rest[..=end]).map_err(|e| e.to_string())?;
+            (CanonicalSegment::Key(key), &rest[end + 1..])
+        } else {
+            let end = rest.find(['/', ' ']).ok_or("missing ` = `")?;
+            let token = &rest[..end];
+            let segment = match token.parse::<usize>() {
+                _ if token.is_empty() => return Err("empty path segment".to_string()),
+                Ok(index) if token.chars().all(|c| c.is_ascii_digit()) => {
+                    CanonicalSegment::Index(index)
+                }
+                _ => CanonicalSegment::Key(token.to_string()),
+            };
+            (segment, &rest[end..])
+        };
+        segments.push(segment);
+
+        if let Some(tail) = tail.strip_prefix('/') {
+            rest = tail;
+            continue;
+        }
+        let value = tail.strip_prefix(" = ").ok_or("missing ` = `")?;
+        let value = serde_json::from_str(value).map_err(|e| e.to_string())?;
+        return Ok((segments, value));
+    }
+}
+
+/// The byte index of the quote closing the json string `quoted` starts with.
+fn closing_quote(quoted: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in quoted.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Writes `value` at the path of `segments` below `parent`, creating the objects
+/// and arrays on the way. Array entries have to come in order.
+fn insert_canonical(
+    parent: &mut Value,
+    segments: &[CanonicalSegment],
+    value: Value,
+) -> Result<(), String> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return Err("empty path".to_string());
+    };
+    let slot = match (segment, parent) {
+        (CanonicalSegment::Key(key), parent) => {
+            if parent.is_null() {
+                *parent = Value::Object(Map::new());
+            }
+            let Value::Object(map) = parent else {
+                return Err(format!("{} is below a value that is no object", key));
+            };
+            map.entry(key.clone()).or_insert(Value::Null)
+        }
+        (CanonicalSegment::Index(index), parent) => {
+            if parent.is_null() {
+                *parent = Value::Array(Vec::new());
+            }
+            let Value::Array(items) = parent else {
+                return Err(format!("{} is below a value that is no array", index));
+            };
+            if *index == items.len() {
+                items.push(Value::Null);
+            }
+            items
+                .get_mut(*index)
+                .ok_or_else(|| format!("array index {} is out of order", index))?
+        }
+    };
+
+    if !rest.is_empty() {
+        return insert_canonical(slot, rest, value);
+    }
+    if !slot.is_null() {
+        return Err("the path is given twice".to_string());
+    }
+    *slot = value;
+    Ok(())
+}
+
+/// Adds a node read by [`NodeCollection::from_canonical_text`] to `nodes`,
+/// with the uuid and type its block header gives.
+fn finish_canonical_node(
+    nodes: &mut Map<String, Value>,
+    uuid: String,
+    tag: String,
+    mut fields: Value,
+) {
+    if let Value::Object(map) = &mut fields {
+        map.insert("uuid".to_string(), Value::String(uuid.clone()));
+        map.insert("geometry_node".to_string(), Value::String(tag));
+    }
+    nodes.insert(uuid, fields);
+}
+
+impl NodeCollection {
+    /// The collection as json, failing on NaN or infinite floats,
+    /// see [`NodeCollection::check_finite`].
+    pub fn to_value(&self) -> Result<Value, GeodiffError> {
+        self.check_finite()?;
+        Ok(serde_json::to_value(self)?)
+    }
+
+    /// Like [`NodeCollection::to_value`], but with every float rounded to
+    /// `decimals` decimal places, e.g. for smaller files and quieter diffs.
+    ///
+    /// Unlike [`NodeCollection::quantize`] this only changes the output,
+    /// the collection keeps its values at full precision.
+    pub fn to_value_rounded(&self, decimals: u32) -> Result<Value, GeodiffError> {
+        let mut value = self.to_value()?;
+        round_floats(&mut value, 10f64.powi(decimals as i32));
+        Ok(value)
+    }
+
+    pub fn from_value(value: Value) -> Result<NodeCollection, GeodiffError> {
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// The collection as json text, indented for editing by hand if `pretty`
+    /// and compact for transport otherwise.
+    pub fn to_json_string(&self, pretty: bool) -> Result<String, GeodiffError> {
+        self.check_finite()?;
+        let json = if pretty {
+            serde_json::to_string_pretty(self)?
+        } else {
+            serde_json::to_string(self)?
+        };
+        Ok(json)
+    }
+
+    /// Reads a collection from either form of [`NodeCollection::to_json_string`].
+    pub fn from_json_string(json: &str) -> Result<NodeCollection, GeodiffError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Streams the collection as json into `writer`.
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), GeodiffError> {
+        self.check_finite()?;
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a collection written by [`NodeCollection::write_json`].
+    /// Floats are parsed back exactly, so a round trip diffs as unchanged.
+    pub fn read_json<R: Read>(reader: R) -> Result<NodeCollection, GeodiffError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes one json object per node and line, in the order the nodes were added.
+    ///
+    /// Every line carries its `geometry_node` tag, so it deserializes on its own.
+    /// Only the nodes are written, names, layers and other bookkeeping are not.
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> Result<(), GeodiffError> {
+        self.check_finite()?;
+        let mut nodes: Vec<&dyn GeometryNode> = self.iter().collect();
+        nodes.sort_by_key(|n| (self.index_of(&n.uuid()), n.uuid()));
+        for node in nodes {
+            serde_json::to_writer(&mut writer, node)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a collection from lines written by [`NodeCollection::write_ndjson`],
+    /// skipping blank lines.
+    pub fn read_ndjson<R: Read>(reader: R) -> Result<NodeCollection, GeodiffError> {
+        let mut collection = NodeCollection::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            collection.insert_node(serde_json::from_str(&line)?);
+        }
+        Ok(collection)
+    }
+
+    /// The collection as text tuned for line based diffs and merges, e.g. to keep
+    /// geometry in git, where an edit of one field changes only the lines of that field.
+    ///
+    /// Each node is a block headed by `[<uuid> <type>]`, the nodes sorted by uuid,
+    /// followed by a `[bookkeeping]` block, with blank lines between blocks.
+    /// A block holds one `path = json` line per field, sorted by path, with objects
+    /// and arrays of objects or arrays split into one line per entry, e.g.
+    /// `anchor/x = 1.5` or `points/0 = [0.0,0.0,0.0]`. Keys that are empty, all
+    /// digits or hold other characters than ascii letters, digits, `_`, `-` and `.`
+    /// are quoted as json strings, e.g. `"my key" = 1`.
+    ///
+    /// [`NodeCollection::from_canonical_text`] reads the text back.
+    ///
+    /// Fails like [`NodeCollection::to_value`] on NaN or infinite floats.
+    pub fn to_canonical_text(&self) -> Result<String, GeodiffError> {
+        self.check_finite()?;
+        let mut ids: Vec<&Uuid> = self.nodes.keys().collect();
+        ids.sort();
+
+        let mut text = String::new();
+        for id in ids {
+            let node = self.nodes[id].as_ref();
+            text.push_str(&format!("[{} {}]\n", id, node.type_tag()));
+            let mut fields = node_fields(node);
+            fields.remove("uuid");
+            write_canonical_lines(&mut text, "", &Value::Object(fields));
+            text.push('\n');
+        }
+
+        let bookkeeping =
+            serde_json::to_value(&self.bookkeeping).expect("bookkeeping serializes to json");
+        text.push_str("[bookkeeping]\n");
+        write_canonical_lines(&mut text, "", &bookkeeping);
+        Ok(text)
+    }
+
+    /// Reads a collection written by [`NodeCollection::to_canonical_text`],
+    /// e.g. after a line based merge. Blank lines are skipped.
+    ///
+    /// Fails with [`GeodiffError::CanonicalText`] on lines that are no block header
+    /// or `path = json` line, and like [`NodeCollection::from_value`] on blocks
+    /// that do not make up a collection.
+    pub fn from_canonical_text(text: &str) -> Result<NodeCollection, GeodiffError> {
+        let mut nodes = Map::new();
+        let mut root = Value::Object(Map::new());
+        // the uuid and type of the node block being read, `None` in the bookkeeping block
+        let mut block: Option<Option<(String, String)>> = None;
+        let mut fields = Value::Object(Map::new());
+
+        for (i, line) in text.lines().enumerate() {
+            let error = |reason: String| GeodiffError::CanonicalText {
+                line: i + 1,
+                reason,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if let Some(Some((uuid, tag))) = block.take() {
+                    finish_canonical_node(&mut nodes, uuid, tag, fields);
+                }
+                fields = Value::Object(Map::new());
+                block = Some(match header {
+                    "bookkeeping" => None,
+                    _ => {
+                        let (uuid, tag) = header
+                            .split_once(' ')
+                            .ok_or_else(|| error(format!("bad block header [{}]", header)))?;
+                        let uuid = Uuid::parse_str(uuid).map_err(|e| error(e.to_string()))?;
+                        if nodes.contains_key(&uuid.to_string()) {
+                            return Err(error(format!("node {} is given twice", uuid)));
+                        }
+                        Some((uuid.to_string(), tag.to_string()))
+                    }
+                });
+                continue;
+            }
+
+            let (segments, value) = parse_canonical_line(line).map_err(error)?;
+            let target = match &block {
+                None => return Err(error("line outside of a block".to_string())),
+                Some(Some(_)) => &mut fields,
+                Some(None) => &mut root,
+            };
+            insert_canonical(target, &segments, value).map_err(error)?;
+        }
+        if let Some(Some((uuid, tag))) = block {
+            finish_canonical_node(&mut nodes, uuid, tag, fields);
+        }
+
+        if let Value::Object(map) = &mut root {
+            map.insert("nodes".to_string(), Value::Object(nodes));
+        }
+        Self::from_value(root)
+    }
+
+    /// Streams the collection as gzip compressed json into `writer`.
+    ///
+    /// The repeated field names and tags of geometry compress well,
+    /// a collection of rectangles shrinks to about a third of its json size.
+    #[cfg(feature = "compression")]
+    pub fn write_gzip<W: Write>(&self, writer: W) -> Result<(), GeodiffError> {
+        let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+        self.write_json(&mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Reads a collection written by [`NodeCollection::write_gzip`].
+    #[cfg(feature = "compression")]
+    pub fn read_gzip<R: Read>(reader: R) -> Result<NodeCollection, GeodiffError> {
+        Self::read_json(flate2::read::GzDecoder::new(reader))
+    }
+
+    /// The collection as yaml, e.g. for geometry written by hand.
+    /// Unlike json, yaml represents NaN and infinite floats.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml_str(&self) -> Result<String, GeodiffError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Reads a collection from yaml, with nodes tagged by `geometry_node` as in json.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(yaml: &str) -> Result<NodeCollection, GeodiffError> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// The collection as toml. Fails on extra fields holding `null`,
+    /// which toml can not represent.
+    #[cfg(feature = "toml")]
+    pub fn to_toml_str(&self) -> Result<String, GeodiffError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Reads a collection from toml, with nodes tagged by `geometry_node` as in json.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(toml: &str) -> Result<NodeCollection, GeodiffError> {
+        Ok(toml::from_str(toml)?)
+    }
+}
+
+impl Diff {
+    /// The diff as cbor, a compact binary encoding for sending diffs over the network.
+    ///
+    /// Unlike json the values keep their types without quoting, and floats
+    /// shrink to the smallest width that holds them exactly.
+    #[cfg(feature = "cbor")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GeodiffError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads a diff written by [`Diff::to_bytes`].
+    #[cfg(feature = "cbor")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Diff, GeodiffError> {
+        Ok(ciborium::from_reader(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        shapes::{Circle, Polyline},
+        Rectangle,
+    };
+
+    /// A collection of `n` rectangles of growing width.
+    fn rectangles(n: usize) -> NodeCollection {
+        let mut collection = NodeCollection::new();
+        for i in 0..n {
+            let mut rectangle = Rectangle::new();
+            *rectangle.width_mut() = i as f64;
+            collection.push(Box::new(rectangle)).unwrap();
+        }
+        collection
+    }
+
+    #[test]
+    fn json_round_trips_through_a_writer() {
+        let collection = rectangles(3);
+        let mut json = Vec::new();
+        collection.write_json(&mut json).unwrap();
+
+        let read = NodeCollection::read_json(json.as_slice()).unwrap();
+        assert_eq!(read.to_value().unwrap(), collection.to_value().unwrap());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn gzip_round_trips_and_is_smaller_than_json() {
+        let collection = rectangles(100);
+        let mut json = Vec::new();
+        collection.write_json(&mut json).unwrap();
+        let mut gzip = Vec::new();
+        collection.write_gzip(&mut gzip).unwrap();
+
+        // The repeated field names compress well, to about a fifth here,
+        // with the random uuids making up most of what is left.
+        assert!(gzip.len() * 4 < json.len());
+        let read = NodeCollection::read_gzip(gzip.as_slice()).unwrap();
+        assert_eq!(read.to_value().unwrap(), collection.to_value().unwrap());
+    }
+
+    /// Two rectangles, a polyline and a circle.
+    fn mixed() -> NodeCollection {
+        let mut collection = rectangles(2);
+        let mut polyline = Polyline::new();
+        polyline.push(1.0, 2.0, 3.0);
+        let mut circle = Circle::new();
+        *circle.radius_mut() = 0.5;
+        collection.push(Box::new(polyline)).unwrap();
+        collection.push(Box::new(circle)).unwrap();
+        collection
+    }
+
+    #[test]
+    fn ndjson_round_trips_one_tagged_node_per_line() {
+        let collection = mixed();
+        let mut ndjson = Vec::new();
+        collection.write_ndjson(&mut ndjson).unwrap();
+
+        let text = String::from_utf8(ndjson).unwrap();
+        assert_eq!(text.lines().count(), 4);
+        for line in text.lines() {
+            let node: Value = serde_json::from_str(line).unwrap();
+            assert!(node["geometry_node"].is_string());
+        }
+
+        let read = NodeCollection::read_ndjson(text.as_bytes()).unwrap();
+        assert_eq!(read.diff(&collection).unwrap().changes(), []);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn a_mixed_collection_round_trips_through_yaml() {
+        let mut collection = mixed();
+        let id = collection.iter().next().unwrap().uuid();
+        collection.set_name(&id, "first").unwrap();
+
+        let yaml = collection.to_yaml_str().unwrap();
+        assert!(yaml.contains("geometry_node: Circle"));
+
+        let read = NodeCollection::from_yaml_str(&yaml).unwrap();
+        assert_eq!(read.diff(&collection).unwrap().changes(), []);
+        assert_eq!(read.name(&id), Some("first"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn a_mixed_collection_round_trips_through_toml() {
+        let collection = mixed();
+
+        let toml = collection.to_toml_str().unwrap();
+        let read = NodeCollection::from_toml_str(&toml).unwrap();
+
+        assert_eq!(read.diff(&collection).unwrap().changes(), []);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn a_diff_round_trips_through_cbor_in_fewer_bytes_than_json() {
+        let diff = NodeCollection::new().diff(&mixed()).unwrap();
+
+        let bytes = diff.to_bytes().unwrap();
+        let json = serde_json::to_vec(&diff).unwrap();
+
+        assert!(bytes.len() < json.len());
+        assert_eq!(Diff::from_bytes(&bytes).unwrap(), diff);
+    }
+
+    #[test]
+    fn rounded_output_leaves_the_collection_at_full_precision() {
+        let mut collection = NodeCollection::new();
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 1.23456;
+        rectangle.anchor_mut().x = 2.999;
+        let id = rectangle.uuid;
+        collection.push(Box::new(rectangle)).unwrap();
+
+        let value = collection.to_value_rounded(2).unwrap();
+
+        let node = &value["nodes"][id.to_string()];
+        assert_eq!(node["width"], 1.23);
+        assert_eq!(node["anchor"]["x"], 3.0);
+        assert_eq!(value["next_index"], 1);
+        let rectangle = collection.try_get_typed::<Rectangle>(&id).unwrap();
+        assert_eq!([rectangle.width, rectangle.anchor.x], [1.23456, 2.999]);
+    }
+
+    #[test]
+    fn changing_one_field_changes_one_line_of_canonical_text() {
+        let before = mixed();
+        let id = before.uuid_of(0).unwrap();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 3.5)
+            .unwrap();
+
+        let (old, new) = (
+            before.to_canonical_text().unwrap(),
+            after.to_canonical_text().unwrap(),
+        );
+
+        let (old, new): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+        assert_eq!(old.len(), new.len());
+        let changed: Vec<(&str, &str)> = old
+            .iter()
+            .zip(&new)
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| (*a, *b))
+            .collect();
+        assert_eq!(changed, [("width = 0.0", "width = 3.5")]);
+    }
+
+    #[test]
+    fn canonical_text_round_trips() {
+        let mut collection = mixed();
+        let id = collection.uuid_of(1).unwrap();
+        collection.set_name(&id, "my \"second\" one").unwrap();
+
+        let text = collection.to_canonical_text().unwrap();
+        let read = NodeCollection::from_canonical_text(&text).unwrap();
+
+        assert_eq!(read.diff(&collection).unwrap().changes(), []);
+        assert_eq!(read.to_canonical_text().unwrap(), text);
+    }
+}
@@ -0,0 +1,129 @@
+use serde_json::{Map, Value};
+
+use crate::{fields::node_fields, GeometryNode, NodeCollection};
+
+impl NodeCollection {
+    /// A collection between `self` at `t = 0` and `other` at `t = 1`, e.g. for tweening
+    /// between two versions of a drawing.
+    ///
+    /// Nodes of the same type on both sides, matched by uuid, get every number of their
+    /// fields interpolated linearly, integers rounded. Everything else snaps to `other`
+    /// at `t >= 0.5`: the other fields, nodes whose type changed, and the bookkeeping
+    /// like names and layers. Nodes on only one side fade out and in the same way,
+    /// so a removed node is held until `t = 0.5` and an added one appears from there.
+    ///
+    /// `t` outside of `0..=1` extrapolates the numbers.
+    pub fn lerp(&self, other: &NodeCollection, t: f64) -> NodeCollection {
+        let mut tweened = if t < 0.5 { self } else { other }.snapshot();
+
+        for (id, from) in &self.nodes {
+            let Some(to) = other.nodes.get(id) else {
+                continue;
+            };
+            if from.type_tag() != to.type_tag() {
+                continue;
+            }
+            tweened.insert_node(lerp_node(from.as_ref(), to.as_ref(), t));
+        }
+        tweened
+    }
+}
+
+/// Writes the interpolated fields into a copy of the snapped node, one top level field
+/// at a time, so a field the node refuses snaps instead of losing the whole node.
+fn lerp_node(from: &dyn GeometryNode, to: &dyn GeometryNode, t: f64) -> Box<dyn GeometryNode> {
+    let mut node = if t < 0.5 {
+        from.clone_box()
+    } else {
+        to.clone_box()
+    };
+    let to_fields = node_fields(to);
+    for (name, a) in node_fields(from) {
+        let Some(b) = to_fields.get(&name) else {
+            continue;
+        };
+        if a == *b || name == "uuid" {
+            continue;
+        }
+        // Refused writes keep the snapped value the copy already holds.
+        let _ = node.set_field(&name, lerp_value(&a, b, t));
+    }
+    node
+}
+
+fn lerp_value(a: &Value, b: &Value, t: f64) -> Value {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (Some(fx), Some(fy)) = (x.as_f64(), y.as_f64()) else {
+                return snap(a, b, t);
+            };
+            let lerped = fx + (fy - fx) * t;
+            if x.is_f64() || y.is_f64() {
+                Value::from(lerped)
+            } else if x.is_u64() && y.is_u64() && lerped >= 0.0 {
+                Value::from(lerped.round() as u64)
+            } else {
+                Value::from(lerped.round() as i64)
+            }
+        }
+        (Value::Array(xs), Value::Array(ys)) if xs.len() == ys.len() => Value::Array(
+            xs.iter()
+                .zip(ys)
+                .map(|(x, y)| lerp_value(x, y, t))
+                .collect(),
+        ),
+        (Value::Object(xs), Value::Object(ys)) if xs.len() == ys.len() => {
+            let mut lerped = Map::new();
+            for (key, x) in xs {
+                let Some(y) = ys.get(key) else {
+                    return snap(a, b, t);
+                };
+                lerped.insert(key.clone(), lerp_value(x, y, t));
+            }
+            Value::Object(lerped)
+        }
+        _ => snap(a, b, t),
+    }
+}
+
+fn snap(a: &Value, b: &Value, t: f64) -> Value {
+    if t < 0.5 {
+        a.clone()
+    } else {
+        b.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+
+    #[test]
+    fn a_point_halfway_between_two_positions() {
+        let mut from = NodeCollection::new();
+        let point = Point3::new();
+        let id = point.uuid();
+        from.push(Box::new(point)).unwrap();
+        let mut to = from.snapshot();
+        to.update(&id, |p: &mut Point3| p.x = 10.0).unwrap();
+
+        let halfway = from.lerp(&to, 0.5);
+
+        let point = halfway.try_get_typed::<Point3>(&id).unwrap();
+        assert_eq!([point.x, point.y, point.z], [5.0, 0.0, 0.0]);
+        assert_eq!(point.uuid(), id);
+    }
+
+    #[test]
+    fn nodes_on_one_side_snap_at_the_middle() {
+        let from = NodeCollection::new();
+        let mut to = from.snapshot();
+        let point = Point3::new();
+        let id = point.uuid();
+        to.push(Box::new(point)).unwrap();
+
+        assert!(from.lerp(&to, 0.4).get(&id).is_none());
+        assert!(from.lerp(&to, 0.5).get(&id).is_some());
+    }
+}
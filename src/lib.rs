@@ -0,0 +1,781 @@
+pub mod apply;
+pub mod blame;
+pub mod block;
+pub mod bounds;
+pub mod changelog;
+pub mod commit;
+pub mod concurrent;
+pub mod context;
+pub mod delta;
+pub mod diff;
+pub mod domain;
+pub mod error;
+pub mod events;
+pub mod extra;
+pub mod factory;
+pub mod fields;
+pub mod finite;
+pub mod hash;
+pub mod history;
+pub mod hull;
+pub mod indices;
+pub mod io;
+pub mod lerp;
+pub mod lock;
+pub mod measure;
+pub mod merge;
+pub mod merge_patch;
+pub mod minimize;
+pub mod offset;
+pub mod order;
+pub mod overlap;
+pub mod raycast;
+pub mod registry;
+pub mod repair;
+pub mod schema;
+pub mod sets;
+pub mod shapes;
+pub mod shared;
+pub mod simplify;
+pub mod size;
+pub mod spatial;
+pub mod sphere;
+pub mod stats;
+pub mod strategy;
+pub mod streaming;
+pub mod subset;
+pub mod summary;
+pub mod tessellate;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod tombstone;
+pub mod transform;
+pub mod view;
+pub mod watch;
+pub mod winding;
+
+use std::{
+    any::Any,
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use blame::Authorship;
+use bounds::{node_bounds, Bounded, BoundsCache};
+use diff::{Change, ChangeCategory};
+use events::{Listener, NodeEvent};
+use extra::ExtraFields;
+use factory::{default_factory, NodeFactory, V4Factory};
+use fields::{node_fields, set_field_by_json, FieldError};
+use hash::content_hash;
+use indices::NodeIndices;
+use lock::{EditError, LockInfo};
+use measure::Measurable;
+use raycast::RayIntersect;
+use schema::register_schema;
+use spatial::SpatialHash;
+use sphere::BoundedSphere;
+use tessellate::Tessellate;
+use transform::Transformable;
+use watch::Watchers;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+pub struct NodeCollection {
+    nodes: HashMap<Uuid, Box<dyn GeometryNode>>,
+    #[serde(flatten)]
+    bookkeeping: Bookkeeping,
+    #[serde(skip)]
+    bounds_cache: Mutex<BoundsCache>,
+    #[serde(skip)]
+    spatial: Mutex<SpatialHash>,
+    #[serde(skip)]
+    listeners: Vec<Listener>,
+    /// The nodes touched so far by a running [`NodeCollection::batch`].
+    #[serde(skip)]
+    batched: Option<Vec<Uuid>>,
+    #[serde(skip)]
+    authorship: Authorship,
+    #[serde(skip)]
+    watchers: Watchers,
+    #[serde(skip, default = "default_factory")]
+    factory: Box<dyn NodeFactory>,
+}
+
+/// Per node state the collection keeps next to the nodes themselves.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq)]
+struct Bookkeeping {
+    #[serde(default)]
+    names: HashMap<Uuid, String>,
+    #[serde(default)]
+    layers: HashMap<Uuid, String>,
+    /// Locked nodes, see [`NodeCollection::lock`].
+    #[serde(
+        default,
+        alias = "frozen",
+        deserialize_with = "lock::deserialize_locks"
+    )]
+    locks: HashMap<Uuid, LockInfo>,
+    #[serde(default)]
+    #[schemars(with = "HashMap<Uuid, u32>")]
+    indices: NodeIndices,
+    #[serde(default)]
+    next_index: u32,
+    /// Draw order, see [`NodeCollection::ordered_for_draw`].
+    #[serde(default)]
+    z_order: HashMap<Uuid, i64>,
+    /// Removed nodes, see [`NodeCollection::set_keep_tombstones`].
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tombstones: BTreeSet<Uuid>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    keep_tombstones: bool,
+}
+
+impl Bookkeeping {
+    /// Drops all state kept for a removed node.
+    fn forget(&mut self, key: &Uuid) {
+        self.names.remove(key);
+        self.layers.remove(key);
+        self.locks.remove(key);
+        self.indices.remove(key);
+        self.z_order.remove(key);
+    }
+}
+
+fn concrete_node<T>(node: &dyn GeometryNode) -> Option<&T>
+where
+    T: GeometryNode + 'static,
+{
+    node.as_any().downcast_ref::<T>()
+}
+
+fn concrete_node_mut<T>(node: &mut dyn GeometryNode) -> Option<&mut T>
+where
+    T: GeometryNode + 'static,
+{
+    node.as_any_mut().downcast_mut::<T>()
+}
+
+impl NodeCollection {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty collection with room for at least `capacity` nodes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: HashMap::with_capacity(capacity),
+            bookkeeping: Bookkeeping::default(),
+            bounds_cache: Mutex::new(BoundsCache::Clean(None)),
+            spatial: Mutex::default(),
+            listeners: Vec::new(),
+            batched: None,
+            authorship: Authorship::default(),
+            watchers: Watchers::default(),
+            factory: default_factory(),
+        }
+    }
+
+    /// A copy of the nodes and bookkeeping, without listeners, watchers or an author,
+    /// like a json round trip, but without one.
+    pub(crate) fn snapshot(&self) -> NodeCollection {
+        let mut copy = Self::with_capacity(self.nodes.len());
+        copy.nodes = (self.nodes.iter())
+            .map(|(id, n)| (*id, n.clone_box()))
+            .collect();
+        copy.bookkeeping = self.bookkeeping.clone();
+        copy.invalidate_bounds();
+        copy.invalidate_spatial();
+        copy
+    }
+
+    /// Reserves room for at least `additional` more nodes, e.g. before a bulk import.
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, key: &Uuid) -> Option<&dyn GeometryNode> {
+        self.nodes.get(key).map(|n| n.as_ref())
+    }
+
+    /// All nodes, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn GeometryNode> + '_ {
+        self.nodes.values().map(|n| n.as_ref())
+    }
+
+    /// Adds the node, replacing the node with the same uuid if there is one.
+    ///
+    /// Fails if the node to replace is locked.
+    pub fn push(&mut self, node: Box<dyn GeometryNode>) -> Result<(), EditError> {
+        self.check_unlocked(&node.uuid())?;
+        self.insert_node(node);
+        Ok(())
+    }
+
+    /// Adds or replaces the node without checking locks, for collections being built.
+    pub(crate) fn insert_node(&mut self, node: Box<dyn GeometryNode>) {
+        let uuid = node.uuid();
+        let added = node_bounds(node.as_ref());
+        let event = match self.nodes.insert(uuid, node) {
+            Some(replaced) => {
+                self.shrink_bounds(node_bounds(replaced.as_ref()));
+                NodeEvent::Modified(uuid)
+            }
+            None => NodeEvent::Added(uuid),
+        };
+        self.bookkeeping.tombstones.remove(&uuid);
+        self.assign_index(uuid);
+        self.expand_bounds(added);
+        self.rehash(uuid, added);
+        self.emit(event);
+    }
+
+    /// Copies the name, layer and draw order `source` keeps for `from` over to the node `to`,
+    /// without checking locks.
+    pub(crate) fn copy_bookkeeping(&mut self, source: &NodeCollection, from: &Uuid, to: Uuid) {
+        let bookkeeping = &source.bookkeeping;
+        if let Some(name) = bookkeeping.names.get(from) {
+            self.bookkeeping.names.insert(to, name.clone());
+        }
+        if let Some(layer) = bookkeeping.layers.get(from) {
+            self.bookkeeping.layers.insert(to, layer.clone());
+        }
+        if let Some(z_index) = bookkeeping.z_order.get(from) {
+            self.bookkeeping.z_order.insert(to, *z_index);
+        }
+    }
+
+    /// Removes the node and returns it. Fails if the node is missing or locked.
+    pub fn remove(&mut self, key: &Uuid) -> Result<Box<dyn GeometryNode>, EditError> {
+        self.check_editable(key)?;
+        let node = self.nodes.remove(key).ok_or(EditError::MissingNode(*key))?;
+        self.bookkeeping.forget(key);
+        if self.bookkeeping.keep_tombstones {
+            self.bookkeeping.tombstones.insert(*key);
+        }
+        self.shrink_bounds(node_bounds(node.as_ref()));
+        self.rehash(*key, None);
+        self.emit(NodeEvent::Removed(*key));
+        Ok(node)
+    }
+
+    /// Gives the node a human readable name. Fails if the node is missing or locked.
+    pub fn set_name(&mut self, key: &Uuid, name: impl Into<String>) -> Result<(), EditError> {
+        self.check_editable(key)?;
+        self.bookkeeping.names.insert(*key, name.into());
+        Ok(())
+    }
+
+    pub fn name(&self, key: &Uuid) -> Option<&str> {
+        self.bookkeeping.names.get(key).map(String::as_str)
+    }
+
+    /// Moves the node onto the given layer. Fails if the node is missing or locked.
+    pub fn set_layer(&mut self, key: &Uuid, layer: impl Into<String>) -> Result<(), EditError> {
+        self.check_editable(key)?;
+        self.bookkeeping.layers.insert(*key, layer.into());
+        Ok(())
+    }
+
+    pub fn layer(&self, key: &Uuid) -> Option<&str> {
+        self.bookkeeping.layers.get(key).map(String::as_str)
+    }
+
+    /// All nodes on the given layer.
+    pub fn nodes_on_layer(&self, layer: &str) -> Vec<Uuid> {
+        self.bookkeeping
+            .layers
+            .iter()
+            .filter(|(_, l)| l.as_str() == layer)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn try_get_typed<'a, T>(&'a self, key: &'a Uuid) -> Option<&'a T>
+    where
+        T: GeometryNode + 'static,
+    {
+        self.nodes
+            .get(key)
+            .and_then(|n| concrete_node::<T>(n.as_ref()))
+    }
+
+    /// Locks the node without giving a reason, see [`NodeCollection::lock`].
+    /// Returns false if the collection does not hold the node.
+    pub fn freeze(&mut self, key: &Uuid) -> bool {
+        self.lock(key, LockInfo::default())
+    }
+
+    pub fn unfreeze(&mut self, key: &Uuid) {
+        self.unlock(key);
+    }
+
+    /// True if the node is locked, with or without a reason.
+    pub fn is_frozen(&self, key: &Uuid) -> bool {
+        self.bookkeeping.locks.contains_key(key)
+    }
+
+    /// Runs `f` on the node and keeps the collection's bookkeeping in sync with the edit.
+    /// This is the preferred way to edit a node in place.
+    ///
+    /// Fails if the node is missing, locked or not of type `T`.
+    pub fn update<T>(&mut self, key: &Uuid, f: impl FnOnce(&mut T)) -> Result<(), EditError>
+    where
+        T: GeometryNode + 'static,
+    {
+        let node = self.check_editable(key)?;
+        let before = node_bounds(node);
+        let node = self
+            .nodes
+            .get_mut(key)
+            .ok_or(EditError::MissingNode(*key))?;
+        let found = node.type_tag();
+        let typed = concrete_node_mut::<T>(node.as_mut())
+            .ok_or(EditError::WrongType { uuid: *key, found })?;
+
+        f(typed);
+
+        let after = node_bounds(node.as_ref());
+        self.shrink_bounds(before);
+        self.expand_bounds(after);
+        self.rehash(*key, after);
+        self.emit(NodeEvent::Modified(*key));
+        Ok(())
+    }
+
+    /// Mutable access to a node. As the caller may move the node,
+    /// this invalidates the cached bounds and spatial hash, but fires no events.
+    /// Prefer [`NodeCollection::update`] where possible.
+    ///
+    /// Fails if the node is missing, locked or not of type `T`.
+    pub fn try_get_typed_mut<'a, T>(&'a mut self, key: &'a Uuid) -> Result<&'a mut T, EditError>
+    where
+        T: GeometryNode + 'static,
+    {
+        self.check_editable(key)?;
+        self.invalidate_bounds();
+        self.invalidate_spatial();
+        let node = self
+            .nodes
+            .get_mut(key)
+            .ok_or(EditError::MissingNode(*key))?;
+        let found = node.type_tag();
+        concrete_node_mut::<T>(node.as_mut()).ok_or(EditError::WrongType { uuid: *key, found })
+    }
+}
+
+impl Default for NodeCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # TODO
+/// A macro to implement uuid(), as_any() and as_any_mut()
+/// for structs that implement GeometryNode.
+///
+/// Also think about if we could rather represent the whole architecture with an ecs.
+/// Could give exactly the flexibility we want, while also being super granular with changes.
+/// I think I actually like that more...
+#[typetag::serde(tag = "geometry_node")]
+pub trait GeometryNode: Send + Sync {
+    fn uuid(&self) -> Uuid;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn clone_box(&self) -> Box<dyn GeometryNode>;
+    /// The `geometry_node` tag the node serializes with.
+    fn type_tag(&self) -> &'static str {
+        self.typetag_name()
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        None
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        None
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        None
+    }
+    fn as_measurable(&self) -> Option<&dyn Measurable> {
+        None
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        None
+    }
+    fn as_ray_intersect(&self) -> Option<&dyn RayIntersect> {
+        None
+    }
+    /// Application specific fields, for the node types that keep them.
+    fn extra(&self) -> Option<&ExtraFields> {
+        None
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        None
+    }
+    /// Overrides the category of changes to `field`, for fields the
+    /// generic classification in [`Change::category`] gets wrong.
+    fn change_category(&self, _field: &[String]) -> Option<ChangeCategory> {
+        None
+    }
+    /// Uuids of other nodes this node refers to.
+    fn dependencies(&self) -> Vec<Uuid> {
+        Vec::new()
+    }
+    /// Drops the reference to `uuid`, for nodes that only refer to it by uuid.
+    /// Returns false if the node does not refer to it or needs the reference,
+    /// e.g. because it embeds a copy of the referred node.
+    fn drop_dependency(&mut self, _uuid: &Uuid) -> bool {
+        false
+    }
+    /// The copy of the node `uuid` this node embeds, e.g. the anchor of a rectangle,
+    /// for restoring that node should it go missing.
+    fn embedded_dependency(&self, _uuid: &Uuid) -> Option<Box<dyn GeometryNode>> {
+        None
+    }
+    /// True if the nodes describe the same thing, e.g. for finding duplicates.
+    ///
+    /// Defaults to comparing content hashes, so only identical nodes, uuids
+    /// included, are equal, and nodes holding NaN equal nothing. Types override this to compare their geometry
+    /// and ignore uuids or fields derived from others. Overrides still compare the
+    /// [`dependencies`](GeometryNode::dependencies), as a rectangle anchored at
+    /// another point at the same position is not the same rectangle.
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        match (content_hash(self.clone_box().as_ref()), content_hash(other)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+    /// The changes turning `self` into `other`, for types implementing
+    /// [`DiffAware`](domain::DiffAware).
+    /// `None` diffs the serialized forms instead.
+    fn diff_against_node(&self, _other: &dyn GeometryNode) -> Option<Vec<Change>> {
+        None
+    }
+    /// The top level fields of the serialized node, e.g. for a generic property editor.
+    fn fields(&self) -> Map<String, Value> {
+        node_fields(self.clone_box().as_ref())
+    }
+    /// Writes a top level field. Fails for types that do not support it, which is the default.
+    fn set_field(&mut self, _name: &str, _value: Value) -> Result<(), FieldError> {
+        Err(FieldError::Unsupported)
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    uuid: Uuid,
+}
+
+impl Point3 {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            uuid: factory.new_id(),
+        }
+    }
+
+    /// True if the points lie at the same coordinates, whatever their uuids.
+    pub fn same_position(&self, other: &Point3) -> bool {
+        (self.x, self.y, self.z) == (other.x, other.y, other.z)
+    }
+}
+
+impl Default for Point3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Point3);
+
+#[typetag::serde]
+impl GeometryNode for Point3 {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(*self)
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        Some(self)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        Some(self)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Point3>(other).is_some_and(|o| self.same_position(o))
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Rectangle {
+    anchor: Point3,
+    /// Which point of the rectangle the anchor is.
+    #[serde(default)]
+    anchor_kind: AnchorKind,
+    width: f64,
+    height: f64,
+    uuid: Uuid,
+    #[serde(flatten)]
+    extra: ExtraFields,
+}
+
+/// The point of a [`Rectangle`] its anchor sits at.
+/// Rectangles extend along positive x and y from their bottom left corner.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnchorKind {
+    /// The corner with the smallest x and y, used by documents predating the kind.
+    #[default]
+    BottomLeft,
+    Center,
+    /// The corner with the smallest x and the largest y.
+    TopLeft,
+}
+
+impl Rectangle {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            anchor: Point3::from_factory(factory),
+            anchor_kind: AnchorKind::BottomLeft,
+            width: 0.0,
+            height: 0.0,
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    /// How far the anchor sits from the bottom left corner.
+    fn anchor_offset(&self) -> (f64, f64) {
+        match self.anchor_kind {
+            AnchorKind::BottomLeft => (0.0, 0.0),
+            AnchorKind::Center => (self.width / 2.0, self.height / 2.0),
+            AnchorKind::TopLeft => (0.0, self.height),
+        }
+    }
+
+    /// The bottom left corner, with the anchor's uuid.
+    pub fn corner(&self) -> Point3 {
+        let (dx, dy) = self.anchor_offset();
+        let mut corner = self.anchor;
+        corner.x -= dx;
+        corner.y -= dy;
+        corner
+    }
+
+    /// Moves the rectangle so its bottom left corner sits at `corner`.
+    pub fn set_corner(&mut self, corner: Point3) {
+        let (dx, dy) = self.anchor_offset();
+        self.anchor = corner;
+        self.anchor.x += dx;
+        self.anchor.y += dy;
+    }
+
+    pub fn anchor_kind(&self) -> AnchorKind {
+        self.anchor_kind
+    }
+
+    /// Changes which point the anchor is, moving the anchor but not the rectangle.
+    pub fn set_anchor_kind(&mut self, kind: AnchorKind) {
+        let corner = self.corner();
+        self.anchor_kind = kind;
+        self.set_corner(corner);
+    }
+
+    pub fn anchor_mut(&mut self) -> &mut Point3 {
+        &mut self.anchor
+    }
+
+    pub fn width_mut(&mut self) -> &mut f64 {
+        &mut self.width
+    }
+    pub fn height_mut(&mut self) -> &mut f64 {
+        &mut self.height
+    }
+}
+
+impl Default for Rectangle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Rectangle);
+
+#[typetag::serde]
+impl GeometryNode for Rectangle {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        Some(self)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        Some(self)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn as_measurable(&self) -> Option<&dyn Measurable> {
+        Some(self)
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        Some(self)
+    }
+    fn as_ray_intersect(&self) -> Option<&dyn RayIntersect> {
+        Some(self)
+    }
+    fn dependencies(&self) -> Vec<Uuid> {
+        vec![self.anchor.uuid]
+    }
+    fn embedded_dependency(&self, uuid: &Uuid) -> Option<Box<dyn GeometryNode>> {
+        (self.anchor.uuid == *uuid).then(|| self.anchor.clone_box())
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Rectangle>(other).is_some_and(|o| {
+            self.anchor.same_position(&o.anchor)
+                && self.anchor_kind == o.anchor_kind
+                && (self.width, self.height) == (o.width, o.height)
+                && self.extra == o.extra
+                && self.dependencies() == o.dependencies()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::node_value;
+
+    #[test]
+    fn reserved_collection_loads_a_large_fixture_without_growing() {
+        let fixture: Vec<Value> = (0..5000)
+            .map(|i| {
+                let mut point = Point3::new();
+                point.x = i as f64;
+                node_value(&point)
+            })
+            .collect();
+
+        let mut collection = NodeCollection::with_capacity(16);
+        collection.reserve(fixture.len());
+        let capacity = collection.nodes.capacity();
+        assert!(capacity >= fixture.len());
+
+        for value in fixture {
+            collection
+                .push(serde_json::from_value(value).unwrap())
+                .unwrap();
+        }
+        assert_eq!(collection.len(), 5000);
+        assert_eq!(collection.nodes.capacity(), capacity);
+    }
+
+    /// A square caching its area, which its equality ignores.
+    #[derive(Serialize, Deserialize, Clone)]
+    struct Square {
+        side: f64,
+        area: f64,
+        uuid: Uuid,
+    }
+
+    #[typetag::serde]
+    impl GeometryNode for Square {
+        fn uuid(&self) -> Uuid {
+            self.uuid
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+        fn clone_box(&self) -> Box<dyn GeometryNode> {
+            Box::new(self.clone())
+        }
+        fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+            concrete_node::<Square>(other).is_some_and(|o| self.side == o.side)
+        }
+    }
+
+    #[test]
+    fn a_semantic_eq_override_ignores_a_cached_field() {
+        let stale = Square {
+            side: 2.0,
+            area: 0.0,
+            uuid: Uuid::new_v4(),
+        };
+        let fresh = Square {
+            area: 4.0,
+            ..stale.clone()
+        };
+        let other = Square {
+            side: 3.0,
+            ..fresh.clone()
+        };
+        assert!(stale.semantic_eq(&fresh));
+        assert!(!fresh.semantic_eq(&other));
+        // the default compares content hashes, which see the cached field
+        assert_ne!(content_hash(&stale).unwrap(), content_hash(&fresh).unwrap());
+
+        let mut older = NodeCollection::new();
+        older.push(Box::new(stale)).unwrap();
+        let mut newer = NodeCollection::new();
+        newer.push(Box::new(fresh.clone())).unwrap();
+        assert_eq!(older.reconcile(&newer).unchanged, [fresh.uuid]);
+    }
+}
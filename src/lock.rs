@@ -0,0 +1,193 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+
+use crate::{GeometryNode, NodeCollection};
+
+/// Why a node is locked against edits, for showing to users.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LockInfo {
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_by: Option<String>,
+}
+
+impl LockInfo {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            locked_by: None,
+        }
+    }
+
+    pub fn by(mut self, locked_by: impl Into<String>) -> Self {
+        self.locked_by = Some(locked_by.into());
+        self
+    }
+}
+
+/// Why an edit of a node was refused.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    /// The collection holds no node with the uuid.
+    MissingNode(Uuid),
+    /// The node is locked, see [`NodeCollection::lock`].
+    Locked { uuid: Uuid, info: LockInfo },
+    /// The node is not of the type the edit expects.
+    WrongType { uuid: Uuid, found: &'static str },
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::MissingNode(uuid) => write!(f, "no node {}", uuid),
+            EditError::Locked { uuid, info } => {
+                write!(f, "node {} is locked: {}", uuid, info.reason)?;
+                match &info.locked_by {
+                    Some(by) => write!(f, " (by {})", by),
+                    None => Ok(()),
+                }
+            }
+            EditError::WrongType { uuid, found } => write!(f, "node {} is a {}", uuid, found),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Collections written before locks carried reasons store a list of frozen uuids.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredLocks {
+    Locks(HashMap<Uuid, LockInfo>),
+    Frozen(HashSet<Uuid>),
+}
+
+pub(crate) fn deserialize_locks<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<Uuid, LockInfo>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match StoredLocks::deserialize(deserializer)? {
+        StoredLocks::Locks(locks) => locks,
+        StoredLocks::Frozen(frozen) => frozen
+            .into_iter()
+            .map(|uuid| (uuid, LockInfo::default()))
+            .collect(),
+    })
+}
+
+impl NodeCollection {
+    /// Locks the node against edits through [`NodeCollection::update`], the
+    /// transforms and the other mutators, replacing an earlier lock.
+    /// Mutators refuse locked nodes with [`EditError::Locked`], diffs touching
+    /// them fail to apply.
+    /// Returns false if the collection does not hold the node.
+    pub fn lock(&mut self, key: &Uuid, info: LockInfo) -> bool {
+        if !self.nodes.contains_key(key) {
+            return false;
+        }
+        self.bookkeeping.locks.insert(*key, info);
+        true
+    }
+
+    /// Lifts the lock of the node, returning it if the node was locked.
+    pub fn unlock(&mut self, key: &Uuid) -> Option<LockInfo> {
+        self.bookkeeping.locks.remove(key)
+    }
+
+    pub fn lock_info(&self, key: &Uuid) -> Option<&LockInfo> {
+        self.bookkeeping.locks.get(key)
+    }
+
+    /// The lock keeping the node from being edited, as an error.
+    pub(crate) fn check_unlocked(&self, key: &Uuid) -> Result<(), EditError> {
+        match self.lock_info(key) {
+            Some(info) => Err(EditError::Locked {
+                uuid: *key,
+                info: info.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Like [`NodeCollection::check_unlocked`], but also fails if the node is missing.
+    pub(crate) fn check_editable(&self, key: &Uuid) -> Result<&dyn GeometryNode, EditError> {
+        self.check_unlocked(key)?;
+        self.nodes
+            .get(key)
+            .map(|n| n.as_ref())
+            .ok_or(EditError::MissingNode(*key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rectangle;
+
+    /// A collection holding a rectangle locked by ada, and the rectangle's uuid.
+    fn with_locked_rectangle() -> (NodeCollection, Uuid) {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        assert!(collection.lock(&id, LockInfo::new("approved").by("ada")));
+        (collection, id)
+    }
+
+    #[test]
+    fn editing_a_locked_node_returns_the_stored_reason() {
+        let (mut collection, id) = with_locked_rectangle();
+        let locked = EditError::Locked {
+            uuid: id,
+            info: LockInfo::new("approved").by("ada"),
+        };
+
+        assert_eq!(
+            collection.update(&id, |r: &mut Rectangle| *r.width_mut() = 1.0),
+            Err(locked.clone())
+        );
+        assert_eq!(collection.set_name(&id, "door"), Err(locked.clone()));
+        assert_eq!(collection.translate_nodes(&[id], 1.0, 0.0, 0.0), [id]);
+        assert_eq!(collection.remove(&id).err(), Some(locked.clone()));
+        assert_eq!(
+            locked.to_string(),
+            format!("node {} is locked: approved (by ada)", id)
+        );
+
+        assert!(collection.unlock(&id).is_some());
+        collection
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 1.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn locks_persist_through_serialization() {
+        let (collection, id) = with_locked_rectangle();
+
+        let read = NodeCollection::from_value(collection.to_value().unwrap()).unwrap();
+
+        assert_eq!(
+            read.lock_info(&id),
+            Some(&LockInfo::new("approved").by("ada"))
+        );
+    }
+
+    #[test]
+    fn frozen_lists_of_older_collections_read_as_locks() {
+        let (collection, id) = with_locked_rectangle();
+        let mut value = collection.to_value().unwrap();
+        value["locks"] = serde_json::json!([id]);
+
+        let read = NodeCollection::from_value(value).unwrap();
+
+        assert_eq!(read.lock_info(&id), Some(&LockInfo::default()));
+    }
+}
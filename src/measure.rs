@@ -0,0 +1,89 @@
+use std::f64::consts::PI;
+
+use crate::{
+    shapes::{Circle, Ellipse},
+    NodeCollection, Rectangle,
+};
+
+/// Closed shapes that enclose an area.
+pub trait Measurable {
+    fn area(&self) -> f64;
+    fn perimeter(&self) -> f64;
+}
+
+impl Measurable for Rectangle {
+    fn area(&self) -> f64 {
+        (self.width * self.height).abs()
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * (self.width.abs() + self.height.abs())
+    }
+}
+
+impl Measurable for Circle {
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * PI * self.radius.abs()
+    }
+}
+
+impl Measurable for Ellipse {
+    fn area(&self) -> f64 {
+        PI * (self.radius_x * self.radius_y).abs()
+    }
+
+    /// Ramanujan's approximation, exact for circles.
+    fn perimeter(&self) -> f64 {
+        let (a, b) = (self.radius_x.abs(), self.radius_y.abs());
+        PI * (3.0 * (a + b) - ((3.0 * a + b) * (a + 3.0 * b)).sqrt())
+    }
+}
+
+impl NodeCollection {
+    /// The summed area of all measurable nodes.
+    pub fn total_area(&self) -> f64 {
+        self.nodes
+            .values()
+            .filter_map(|n| n.as_measurable())
+            .map(|m| m.area())
+            .sum()
+    }
+
+    /// The summed perimeter of all measurable nodes.
+    pub fn total_perimeter(&self) -> f64 {
+        self.nodes
+            .values()
+            .filter_map(|n| n.as_measurable())
+            .map(|m| m.perimeter())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point3;
+
+    fn rectangle(width: f64, height: f64) -> Rectangle {
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = width;
+        *rectangle.height_mut() = height;
+        rectangle
+    }
+
+    #[test]
+    fn totals_of_two_rectangles() {
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(rectangle(2.0, 3.0))).unwrap();
+        collection.push(Box::new(rectangle(4.0, 5.0))).unwrap();
+        // Open shapes are excluded.
+        collection.push(Box::new(Point3::new())).unwrap();
+
+        assert_eq!(collection.total_area(), 6.0 + 20.0);
+        assert_eq!(collection.total_perimeter(), 10.0 + 18.0);
+    }
+}
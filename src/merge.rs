@@ -0,0 +1,305 @@
+use std::fmt::Display;
+
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{diff::ChangePath, error::GeodiffError, NodeCollection};
+
+/// How to settle a value both sides of a merge changed differently.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    TakeOurs,
+    TakeTheirs,
+    /// Take this value instead. It has to deserialize as the field's type.
+    Value(Value),
+    /// Leave the value conflicting.
+    Conflict,
+}
+
+/// Decides conflicts of a [`NodeCollection::merge`] field by field.
+pub trait ConflictResolver {
+    /// Resolves a value changed to `ours` on one side and to `theirs` on the
+    /// other. `field` is the `/` separated path below the node, or below the
+    /// map entry for bookkeeping like names and layers.
+    fn resolve(&self, field: &str, base: &Value, ours: &Value, theirs: &Value) -> Resolution;
+}
+
+/// Resolves nothing, so every value changed on both sides conflicts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlwaysConflict;
+
+impl ConflictResolver for AlwaysConflict {
+    fn resolve(&self, _field: &str, _base: &Value, _ours: &Value, _theirs: &Value) -> Resolution {
+        Resolution::Conflict
+    }
+}
+
+/// Meets in the middle of two moves, averaging `x`, `y` and `z` coordinates
+/// both sides changed. Other values conflict.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AveragePositions;
+
+impl ConflictResolver for AveragePositions {
+    fn resolve(&self, field: &str, _base: &Value, ours: &Value, theirs: &Value) -> Resolution {
+        let coordinate = matches!(field.rsplit('/').next(), Some("x" | "y" | "z"));
+        match (ours.as_f64(), theirs.as_f64()) {
+            (Some(ours), Some(theirs)) if coordinate => {
+                Resolution::Value(((ours + theirs) / 2.0).into())
+            }
+            _ => Resolution::Conflict,
+        }
+    }
+}
+
+/// A value both sides of a merge changed, that the resolver left conflicting.
+/// `None` means the side does not have the value, e.g. because it removed the node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConflict {
+    pub path: ChangePath,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+/// Why a [`NodeCollection::merge`] failed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MergeError {
+    Conflicts(Vec<MergeConflict>),
+    /// The merged values do not form a collection, e.g. because a
+    /// resolver picked a value of the wrong type.
+    Invalid(String),
+    /// A float of the node is NaN or infinite in one of the versions,
+    /// see [`NodeCollection::check_finite`].
+    NonFiniteValue {
+        uuid: Uuid,
+        field: String,
+    },
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::Conflicts(conflicts) => {
+                write!(f, "{} conflicts:", conflicts.len())?;
+                for conflict in conflicts {
+                    write!(f, " {};", conflict.path)?;
+                }
+                Ok(())
+            }
+            MergeError::Invalid(e) => write!(f, "invalid merge result: {}", e),
+            MergeError::NonFiniteValue { uuid, field } => {
+                write!(f, "non finite value in {} of node {}", field, uuid)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Bookkeeping that is recomputed after a merge instead of merged.
+const RENUMBERED: [&str; 2] = ["indices", "next_index"];
+
+struct Merge<'r> {
+    resolver: &'r dyn ConflictResolver,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merge<'_> {
+    /// Merges the three versions of the value at `path`, `None` if it ends up absent.
+    fn merge(
+        &mut self,
+        path: &ChangePath,
+        base: Option<&Value>,
+        ours: Option<&Value>,
+        theirs: Option<&Value>,
+    ) -> Option<Value> {
+        if ours == theirs || theirs == base {
+            return ours.cloned();
+        }
+        if ours == base {
+            return theirs.cloned();
+        }
+
+        if let (Some(Value::Object(ours)), Some(Value::Object(theirs))) = (ours, theirs) {
+            let empty = Map::new();
+            let base = match base {
+                Some(Value::Object(base)) => base,
+                _ => &empty,
+            };
+            return Some(Value::Object(self.merge_objects(path, base, ours, theirs)));
+        }
+
+        let resolution = match (base, ours, theirs) {
+            (Some(base), Some(ours), Some(theirs)) => {
+                self.resolver
+                    .resolve(&path.field().join("/"), base, ours, theirs)
+            }
+            _ => Resolution::Conflict,
+        };
+        match resolution {
+            Resolution::TakeOurs => ours.cloned(),
+            Resolution::TakeTheirs => theirs.cloned(),
+            Resolution::Value(value) => Some(value),
+            Resolution::Conflict => {
+                self.conflicts.push(MergeConflict {
+                    path: path.clone(),
+                    base: base.cloned(),
+                    ours: ours.cloned(),
+                    theirs: theirs.cloned(),
+                });
+                ours.cloned()
+            }
+        }
+    }
+
+    fn merge_objects(
+        &mut self,
+        path: &ChangePath,
+        base: &Map<String, Value>,
+        ours: &Map<String, Value>,
+        theirs: &Map<String, Value>,
+    ) -> Map<String, Value> {
+        let mut keys: Vec<&String> = base
+            .keys()
+            .chain(ours.keys())
+            .chain(theirs.keys())
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let merged = self.merge(
+                    &path.join(key.clone()),
+                    base.get(key),
+                    ours.get(key),
+                    theirs.get(key),
+                )?;
+                Some((key.clone(), merged))
+            })
+            .collect()
+    }
+}
+
+fn collection_object(collection: &NodeCollection) -> Map<String, Value> {
+    match serde_json::to_value(collection).expect("collections serialize to json") {
+        Value::Object(mut object) => {
+            for key in RENUMBERED {
+                object.remove(key);
+            }
+            object
+        }
+        _ => unreachable!("collections serialize to json objects"),
+    }
+}
+
+impl NodeCollection {
+    /// Three way merges the edits `self` and `theirs` made since `base`.
+    ///
+    /// Values changed on one side only take that side's change. Values both
+    /// sides changed alike take the change as well, objects both sides changed
+    /// merge key by key and all other values both sides changed go to `resolver`.
+    /// Pass [`AlwaysConflict`] to let all of them conflict.
+    ///
+    /// Nodes keep the indices they have in `self`, nodes only `theirs` added
+    /// get new ones in uuid order.
+    pub fn merge(
+        &self,
+        base: &NodeCollection,
+        theirs: &NodeCollection,
+        resolver: &dyn ConflictResolver,
+    ) -> Result<NodeCollection, MergeError> {
+        for version in [base, self, theirs] {
+            match version.check_finite() {
+                Err(GeodiffError::NonFiniteValue { uuid, field }) => {
+                    return Err(MergeError::NonFiniteValue { uuid, field })
+                }
+                Err(e) => return Err(MergeError::Invalid(e.to_string())),
+                Ok(()) => (),
+            }
+        }
+        let mut merge = Merge {
+            resolver,
+            conflicts: Vec::new(),
+        };
+        let merged = merge.merge_objects(
+            &ChangePath::default(),
+            &collection_object(base),
+            &collection_object(self),
+            &collection_object(theirs),
+        );
+        if !merge.conflicts.is_empty() {
+            return Err(MergeError::Conflicts(merge.conflicts));
+        }
+
+        let mut merged = NodeCollection::from_value(Value::Object(merged))
+            .map_err(|e| MergeError::Invalid(e.to_string()))?;
+        merged.bookkeeping.next_index = self
+            .bookkeeping
+            .next_index
+            .max(theirs.bookkeeping.next_index);
+        let mut uuids: Vec<_> = merged.nodes.keys().copied().collect();
+        uuids.sort();
+        for uuid in uuids {
+            match self.index_of(&uuid) {
+                Some(index) => merged.bookkeeping.indices.insert(uuid, index),
+                None => merged.assign_index(uuid),
+            }
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3};
+
+    /// A point at the origin, moved to `[2, 4, 0]` by us and to `[4, 4, 6]` by them,
+    /// as the base, our and their version, and the point's uuid.
+    fn doubly_moved_point() -> (NodeCollection, NodeCollection, NodeCollection, Uuid) {
+        let mut base = NodeCollection::new();
+        let point = Point3::new();
+        let id = point.uuid();
+        base.push(Box::new(point)).unwrap();
+        let mut ours = base.snapshot();
+        ours.update(&id, |p: &mut Point3| (p.x, p.y) = (2.0, 4.0))
+            .unwrap();
+        let mut theirs = base.snapshot();
+        theirs
+            .update(&id, |p: &mut Point3| (p.x, p.y, p.z) = (4.0, 4.0, 6.0))
+            .unwrap();
+        (base, ours, theirs, id)
+    }
+
+    #[test]
+    fn averaging_meets_a_doubly_moved_point_in_the_middle() {
+        let (base, ours, theirs, id) = doubly_moved_point();
+
+        let merged = ours.merge(&base, &theirs, &AveragePositions).unwrap();
+
+        let point = merged.try_get_typed::<Point3>(&id).unwrap();
+        // x moved differently and is averaged, y moved alike,
+        // z moved on their side only
+        assert_eq!([point.x, point.y, point.z], [3.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn without_a_resolver_the_double_move_conflicts() {
+        let (base, ours, theirs, id) = doubly_moved_point();
+
+        let Err(MergeError::Conflicts(conflicts)) = ours.merge(&base, &theirs, &AlwaysConflict)
+        else {
+            panic!("the merge should conflict");
+        };
+        assert_eq!(
+            conflicts,
+            [MergeConflict {
+                path: ChangePath::node_root(&id).join("x"),
+                base: Some(0.0.into()),
+                ours: Some(2.0.into()),
+                theirs: Some(4.0.into()),
+            }]
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{
+    diff::{Change, ChangePath, NodeChange, NodeChangeKind},
+    error::GeodiffError,
+    NodeCollection,
+};
+
+/// Merges `patch` into `target` as described by RFC 7386.
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        unreachable!("target was made an object");
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+impl NodeCollection {
+    /// Applies a JSON Merge Patch (RFC 7386) to the serialized collection.
+    ///
+    /// Objects in the patch merge into the collection field by field, other
+    /// values replace what they point at and `null` deletes it, so
+    /// `{"nodes": {"<uuid>": null}}` removes a node.
+    ///
+    /// Fails if the patch edits or removes a locked node.
+    /// On error the collection is left unchanged.
+    pub fn apply_merge_patch(&mut self, patch: &Value) -> Result<(), GeodiffError> {
+        let touched: BTreeSet<Uuid> = patch
+            .get("nodes")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|nodes| nodes.keys())
+            .filter_map(|key| Uuid::parse_str(key).ok())
+            .collect();
+        for key in &touched {
+            self.check_unlocked(key)?;
+        }
+
+        let mut value = self.to_value()?;
+        merge(&mut value, patch);
+        let mut applied = Self::from_value(value)?;
+        for removed in touched.iter().filter(|id| !applied.nodes.contains_key(id)) {
+            applied.bookkeeping.forget(removed);
+        }
+        self.replace_with(applied, touched);
+        Ok(())
+    }
+}
+
+/// Why a [`NodeChange`] has no merge patch form, see [`NodeChange::as_sparse_json`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SparseJsonError {
+    /// The change edits an array in place, e.g. a point of a polyline. Merge patches
+    /// can only replace arrays as a whole, which the change does not describe.
+    InsideArray(ChangePath),
+}
+
+impl Display for SparseJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SparseJsonError::InsideArray(path) => {
+                write!(f, "change inside of an array at {}", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SparseJsonError {}
+
+impl NodeChange {
+    /// The node's uuid and the new values of only the fields that changed,
+    /// nested as in the serialized node, with `null` for removed fields.
+    ///
+    /// Wrapped as `{"nodes": {"<uuid>": ...}}` it is a merge patch for
+    /// [`NodeCollection::apply_merge_patch`] that reproduces the change.
+    ///
+    /// Added nodes give their whole serialized form. Removed nodes and changed
+    /// types give `null`, as no set of field values describes them.
+    ///
+    /// Merge patches can only replace arrays as a whole, so a change inside of an
+    /// array, like to the points of a polyline, fails with [`SparseJsonError::InsideArray`].
+    /// Send the whole node for those instead.
+    pub fn as_sparse_json(&self) -> Result<Value, SparseJsonError> {
+        let changes = match &self.kind {
+            NodeChangeKind::Added(value) => return Ok(value.clone()),
+            NodeChangeKind::Removed(_) | NodeChangeKind::TypeChanged { .. } => {
+                return Ok(Value::Null)
+            }
+            NodeChangeKind::Modified(changes) => changes,
+        };
+        if let Some(change) = changes.iter().find(|c| {
+            c.path()
+                .field()
+                .iter()
+                .any(|segment| segment.parse::<usize>().is_ok())
+        }) {
+            return Err(SparseJsonError::InsideArray(change.path().clone()));
+        }
+
+        let mut sparse = Map::new();
+        if let Some(uuid) = changes.first().and_then(|c| c.path().node()) {
+            sparse.insert("uuid".to_string(), Value::String(uuid.to_string()));
+        }
+        for change in changes {
+            let field = change.path().field();
+            let Some((last, parents)) = field.split_last() else {
+                // an operation on the node as a whole
+                if let Some(Value::Object(after)) = change.after() {
+                    sparse.extend(after.clone());
+                }
+                continue;
+            };
+
+            let mut object = &mut sparse;
+            for parent in parents {
+                let entry = object
+                    .entry(parent.clone())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if !entry.is_object() {
+                    *entry = Value::Object(Map::new());
+                }
+                let Value::Object(nested) = entry else {
+                    unreachable!("entry was made an object");
+                };
+                object = nested;
+            }
+            let value = match change {
+                Change::Removed { .. } => Value::Null,
+                _ => change.after().cloned().unwrap_or(Value::Null),
+            };
+            object.insert(last.clone(), value);
+        }
+        Ok(Value::Object(sparse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        lock::{EditError, LockInfo},
+        shapes::Polyline,
+        GeometryNode, Point3, Rectangle,
+    };
+
+    #[test]
+    fn merge_patch_sets_a_width_and_deletes_a_node() {
+        let mut collection = NodeCollection::new();
+        let (rectangle, point) = (Rectangle::new(), Point3::new());
+        let (rectangle_id, point_id) = (rectangle.uuid(), point.uuid());
+        collection.push(Box::new(rectangle)).unwrap();
+        collection.push(Box::new(point)).unwrap();
+
+        collection
+            .apply_merge_patch(&json!({
+                "nodes": {
+                    rectangle_id.to_string(): {"width": 7.5},
+                    point_id.to_string(): null,
+                }
+            }))
+            .unwrap();
+
+        let rectangle = collection
+            .try_get_typed::<Rectangle>(&rectangle_id)
+            .unwrap();
+        assert_eq!(rectangle.width, 7.5);
+        assert!(collection.get(&point_id).is_none());
+        assert_eq!(collection.len(), 1);
+    }
+
+    #[test]
+    fn merge_patches_leave_locked_nodes_alone() {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        collection.lock(&id, LockInfo::new("approved"));
+
+        for patch in [json!({"width": 7.5}), Value::Null] {
+            let result = collection.apply_merge_patch(&json!({"nodes": {id.to_string(): patch}}));
+
+            assert!(matches!(
+                result,
+                Err(GeodiffError::Edit(EditError::Locked { uuid, info }))
+                    if uuid == id && info.reason == "approved"
+            ));
+        }
+        let rectangle = collection.try_get_typed::<Rectangle>(&id).unwrap();
+        assert_eq!(rectangle.width, 0.0);
+    }
+
+    #[test]
+    fn a_width_change_gives_only_the_uuid_and_width() {
+        let mut before = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        before.push(Box::new(rectangle)).unwrap();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 3.0)
+            .unwrap();
+        let change = before.diff_node(&after, &id).unwrap().unwrap();
+
+        let sparse = change.as_sparse_json().unwrap();
+
+        assert_eq!(sparse, json!({"uuid": id.to_string(), "width": 3.0}));
+        before
+            .apply_merge_patch(&json!({"nodes": {id.to_string(): sparse}}))
+            .unwrap();
+        assert_eq!(before.diff(&after).unwrap().changes(), []);
+    }
+
+    #[test]
+    fn a_change_inside_of_an_array_has_no_sparse_form() {
+        let mut before = NodeCollection::new();
+        let mut polyline = Polyline::new();
+        polyline.push(0.0, 0.0, 0.0);
+        let id = polyline.uuid();
+        before.push(Box::new(polyline)).unwrap();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after
+            .update(&id, |p: &mut Polyline| p.push(1.0, 1.0, 0.0))
+            .unwrap();
+        let change = before.diff_node(&after, &id).unwrap().unwrap();
+
+        assert_eq!(
+            change.as_sparse_json(),
+            Err(SparseJsonError::InsideArray(
+                ChangePath::node_root(&id).join("points").join("1")
+            ))
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{
+    diff::{Change, ChangePath, Diff},
+    GeometryNode,
+};
+
+/// The name of the [`Change::Operation`] that moves a whole node from the uuid
+/// of its path to the uuid of its `after` value, see [`Diff::minimize`].
+pub const MOVED: &str = "moved";
+
+/// The uuid a [`MOVED`] operation moves its node to, `None` for other changes.
+pub(crate) fn moved_to(change: &Change) -> Option<Uuid> {
+    let Change::Operation {
+        path, name, after, ..
+    } = change
+    else {
+        return None;
+    };
+    if name != MOVED || path.section() != Some("nodes") || !path.field().is_empty() {
+        return None;
+    }
+    let node: Box<dyn GeometryNode> = serde_json::from_value(after.clone()).ok()?;
+    Some(node.uuid())
+}
+
+/// True if `a` lies strictly below `b` or the other way round.
+fn nested(a: &ChangePath, b: &ChangePath) -> bool {
+    let (a, b) = (a.segments(), b.segments());
+    a.len() != b.len() && a.iter().zip(b).all(|(a, b)| a == b)
+}
+
+impl Diff {
+    /// The same diff without churn, as left by joining the diffs of successive
+    /// edits, e.g. in a [`ChangeLog`](crate::changelog::ChangeLog).
+    ///
+    /// A value removed and added back at the same path becomes one modification,
+    /// or vanishes if it came back unchanged, like a node removed and pushed
+    /// again. A value added and removed again vanishes. Paths with further
+    /// changes above or below them are left as they are.
+    ///
+    /// A node replaced by an equal copy under a new uuid, see [`Diff::churn`],
+    /// becomes one [`MOVED`] operation at the path of the old uuid, so the
+    /// node still ends up under the new one the references point to.
+    pub fn minimize(&self) -> Diff {
+        let mut by_path: BTreeMap<&ChangePath, Vec<usize>> = BTreeMap::new();
+        for (i, change) in self.changes().iter().enumerate() {
+            by_path.entry(change.path()).or_default().push(i);
+        }
+
+        let mut dropped = HashSet::new();
+        let mut merged = BTreeMap::new();
+        for (path, positions) in &by_path {
+            let [first, second] = positions[..] else {
+                continue;
+            };
+            if self.changes().iter().any(|c| nested(c.path(), path)) {
+                continue;
+            }
+
+            match (&self.changes()[first], &self.changes()[second]) {
+                (Change::Removed { value: before, .. }, Change::Added { value: after, .. }) => {
+                    dropped.insert(second);
+                    if before == after {
+                        dropped.insert(first);
+                    } else {
+                        let modified = Change::Modified {
+                            path: (*path).clone(),
+                            before: before.clone(),
+                            after: after.clone(),
+                        };
+                        merged.insert(first, modified);
+                    }
+                }
+                (Change::Added { .. }, Change::Removed { .. }) => {
+                    dropped.extend([first, second]);
+                }
+                _ => (),
+            }
+        }
+
+        let changes = self
+            .changes()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !dropped.contains(i))
+            .map(|(i, change)| merged.remove(&i).unwrap_or_else(|| change.clone()))
+            .collect();
+        self.with_changes(changes).collapse_churn()
+    }
+
+    /// Replaces the removal and addition of each [`Diff::churn`] pair by a
+    /// [`MOVED`] operation, unless other changes reach into either node.
+    fn collapse_churn(self) -> Diff {
+        let mut changes = self.changes().to_vec();
+        for (old, new) in self.churn() {
+            let (from, to) = (ChangePath::node_root(&old), ChangePath::node_root(&new));
+            let at = |root: &ChangePath| -> Vec<usize> {
+                (changes.iter().enumerate())
+                    .filter(|(_, c)| c.path().segments().starts_with(root.segments()))
+                    .map(|(i, _)| i)
+                    .collect()
+            };
+            let ([removal], [addition]) = (&at(&from)[..], &at(&to)[..]) else {
+                continue;
+            };
+            let (removal, addition) = (*removal, *addition);
+            let (Some(before), Some(after)) = (
+                changes[removal].before().cloned(),
+                changes[addition].after().cloned(),
+            ) else {
+                continue;
+            };
+            changes[removal] = Change::Operation {
+                path: from,
+                name: MOVED.to_string(),
+                before,
+                after,
+            };
+            changes.remove(addition);
+        }
+        self.with_changes(changes)
+    }
+
+    /// Pairs of a removed and an added node that are
+    /// [`GeometryNode::semantic_eq`] but differ in their uuids, as
+    /// `(removed, added)`, sorted. Such a pair replaced a node by an equal copy.
+    ///
+    /// Each node is paired at most once. Nodes that fail to deserialize are skipped.
+    pub fn churn(&self) -> Vec<(Uuid, Uuid)> {
+        let nodes = |removed: bool| -> Vec<(Uuid, Box<dyn GeometryNode>)> {
+            self.changes()
+                .iter()
+                .filter(|c| c.path().field().is_empty() && c.path().section() == Some("nodes"))
+                .filter_map(|c| {
+                    let value = match (c, removed) {
+                        (Change::Removed { value, .. }, true)
+                        | (Change::Added { value, .. }, false) => value,
+                        _ => return None,
+                    };
+                    let node = serde_json::from_value(value.clone()).ok()?;
+                    Some((c.path().node()?, node))
+                })
+                .collect()
+        };
+        let (mut removed, mut added) = (nodes(true), nodes(false));
+        removed.sort_by_key(|(id, _)| *id);
+        added.sort_by_key(|(id, _)| *id);
+
+        let mut pairs = Vec::new();
+        for (old, node) in &removed {
+            let found = added
+                .iter()
+                .position(|(new, copy)| new != old && node.semantic_eq(copy.as_ref()));
+            if let Some(i) = found {
+                pairs.push((*old, added.remove(i).0));
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeCollection, Rectangle};
+
+    /// The diffs of two successive edits, joined into one.
+    fn joined(first: &Diff, second: &Diff) -> Diff {
+        Diff::new(
+            first
+                .changes()
+                .iter()
+                .chain(second.changes())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn a_node_removed_and_pushed_again_minimizes_away() {
+        let mut start = NodeCollection::new();
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 2.0;
+        let id = rectangle.uuid();
+        start.push(Box::new(rectangle.clone())).unwrap();
+        let mut removed = start.snapshot();
+        removed.remove(&id).unwrap();
+        let mut readded = removed.snapshot();
+        readded.push(Box::new(rectangle)).unwrap();
+        let churned = joined(
+            &start.diff(&removed).unwrap(),
+            &removed.diff(&readded).unwrap(),
+        );
+        let root = ChangePath::node_root(&id);
+        assert_eq!(
+            churned
+                .changes()
+                .iter()
+                .filter(|c| *c.path() == root)
+                .count(),
+            2
+        );
+
+        let minimized = churned.minimize();
+
+        assert!(minimized.changes().iter().all(|c| *c.path() != root));
+        let mut applied = start.snapshot();
+        applied.apply(&minimized).unwrap();
+        assert_eq!(applied.diff(&readded).unwrap().changes(), []);
+    }
+
+    #[test]
+    fn an_equal_copy_under_a_new_uuid_is_churn() {
+        let mut before = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let old = rectangle.uuid();
+        let mut copy = rectangle.clone();
+        copy.uuid = Uuid::new_v4();
+        let new = copy.uuid();
+        before.push(Box::new(rectangle)).unwrap();
+        let mut after = before.snapshot();
+        after.remove(&old).unwrap();
+        after.push(Box::new(copy)).unwrap();
+
+        let diff = before.diff(&after).unwrap();
+        assert_eq!(diff.churn(), [(old, new)]);
+
+        let minimized = diff.minimize();
+
+        let nodes: Vec<&Change> = (minimized.changes().iter())
+            .filter(|c| c.path().section() == Some("nodes"))
+            .collect();
+        assert!(matches!(
+            nodes[..],
+            [Change::Operation { path, name, .. }] if *path == ChangePath::node_root(&old) && name == MOVED
+        ));
+        assert!(minimized.churn().is_empty());
+        let mut applied = before.snapshot();
+        applied.apply(&minimized).unwrap();
+        assert_eq!(applied.diff(&after).unwrap().changes(), []);
+        assert!(applied.get(&new).is_some() && applied.get(&old).is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use uuid::Uuid;
+
+use crate::{lock::EditError, shapes::Circle, NodeCollection, Rectangle};
+
+impl Rectangle {
+    /// Grows the rectangle by `distance` on all sides, or shrinks it for a negative `distance`.
+    ///
+    /// Insetting past the rectangle's own size collapses the affected dimension to zero,
+    /// centered on where the rectangle was, instead of producing a negative size.
+    pub fn offset(&self, distance: f64) -> Rectangle {
+        let mut offset = self.clone();
+        let (width, dx) = offset_extent(self.width, distance);
+        let (height, dy) = offset_extent(self.height, distance);
+        let mut corner = self.corner();
+        corner.x -= dx;
+        corner.y -= dy;
+        offset.width = width;
+        offset.height = height;
+        offset.set_corner(corner);
+        offset
+    }
+}
+
+/// The new length of an extent grown by `distance` on both ends,
+/// and how far its start moves back, clamping the length to zero.
+fn offset_extent(length: f64, distance: f64) -> (f64, f64) {
+    let grown = length + 2.0 * distance;
+    if grown < 0.0 {
+        (0.0, -length / 2.0)
+    } else {
+        (grown, distance)
+    }
+}
+
+impl Circle {
+    /// Grows the radius by `distance`, or shrinks it for a negative `distance`.
+    ///
+    /// Insetting past the radius collapses the circle to a zero radius around its center.
+    pub fn offset(&self, distance: f64) -> Circle {
+        let mut offset = self.clone();
+        offset.radius = (self.radius + distance).max(0.0);
+        offset
+    }
+}
+
+impl NodeCollection {
+    /// Replaces a rectangle or circle with its offset, see [`Rectangle::offset`].
+    ///
+    /// Fails if the node is missing, locked or of neither type.
+    pub fn offset_node(&mut self, key: &Uuid, distance: f64) -> Result<(), EditError> {
+        match self.update::<Rectangle>(key, |r| *r = r.offset(distance)) {
+            Err(EditError::WrongType { .. }) => {
+                self.update::<Circle>(key, |c| *c = c.offset(distance))
+            }
+            updated => updated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeometryNode;
+
+    fn rectangle(width: f64, height: f64) -> Rectangle {
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = width;
+        *rectangle.height_mut() = height;
+        rectangle
+    }
+
+    #[test]
+    fn insetting_a_rectangle_past_its_size_collapses_it_around_its_center() {
+        let inset = rectangle(4.0, 2.0).offset(-3.0);
+
+        assert_eq!((inset.width, inset.height), (0.0, 0.0));
+        let corner = inset.corner();
+        assert_eq!((corner.x, corner.y), (2.0, 1.0));
+    }
+
+    #[test]
+    fn insetting_past_one_dimension_only_collapses_that_one() {
+        let inset = rectangle(10.0, 2.0).offset(-2.0);
+
+        assert_eq!((inset.width, inset.height), (6.0, 0.0));
+        let corner = inset.corner();
+        assert_eq!((corner.x, corner.y), (2.0, 1.0));
+    }
+
+    #[test]
+    fn offset_node_replaces_rectangles_and_circles_in_place() {
+        let mut collection = NodeCollection::new();
+        let rectangle = rectangle(4.0, 2.0);
+        let mut circle = Circle::new();
+        *circle.radius_mut() = 1.0;
+        let (rectangle_id, circle_id) = (rectangle.uuid(), circle.uuid());
+        collection.push(Box::new(rectangle)).unwrap();
+        collection.push(Box::new(circle)).unwrap();
+
+        collection.offset_node(&rectangle_id, -5.0).unwrap();
+        collection.offset_node(&circle_id, -5.0).unwrap();
+
+        let inset = collection
+            .try_get_typed::<Rectangle>(&rectangle_id)
+            .unwrap();
+        assert_eq!((inset.width, inset.height), (0.0, 0.0));
+        let inset = collection.try_get_typed::<Circle>(&circle_id).unwrap();
+        assert_eq!(inset.radius, 0.0);
+    }
+}
@@ -0,0 +1,90 @@
+use uuid::Uuid;
+
+use crate::{lock::EditError, NodeCollection};
+
+impl NodeCollection {
+    /// The node's position in the draw order. Nodes without one draw at 0.
+    pub fn z_index(&self, uuid: &Uuid) -> i64 {
+        self.bookkeeping.z_order.get(uuid).copied().unwrap_or(0)
+    }
+
+    /// Fails if the node is missing or locked.
+    pub fn set_z_index(&mut self, uuid: &Uuid, z_index: i64) -> Result<(), EditError> {
+        self.check_editable(uuid)?;
+        self.bookkeeping.z_order.insert(*uuid, z_index);
+        Ok(())
+    }
+
+    /// Draws the node above all others.
+    pub fn bring_to_front(&mut self, uuid: &Uuid) -> Result<(), EditError> {
+        let front = self
+            .nodes
+            .keys()
+            .filter(|id| *id != uuid)
+            .map(|id| self.z_index(id))
+            .max()
+            .unwrap_or(0);
+        self.set_z_index(uuid, front + 1)
+    }
+
+    /// Draws the node below all others.
+    pub fn send_to_back(&mut self, uuid: &Uuid) -> Result<(), EditError> {
+        let back = self
+            .nodes
+            .keys()
+            .filter(|id| *id != uuid)
+            .map(|id| self.z_index(id))
+            .min()
+            .unwrap_or(0);
+        self.set_z_index(uuid, back - 1)
+    }
+
+    /// All nodes from back to front. Nodes of equal z index draw in insertion order.
+    pub fn ordered_for_draw(&self) -> Vec<Uuid> {
+        let mut order: Vec<Uuid> = self.nodes.keys().copied().collect();
+        order.sort_by_key(|id| (self.z_index(id), self.index_of(id), *id));
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3};
+
+    /// Pushes `n` points, returning their uuids in order.
+    fn with_points(collection: &mut NodeCollection, n: usize) -> Vec<Uuid> {
+        (0..n)
+            .map(|_| {
+                let point = Point3::new();
+                let id = point.uuid();
+                collection.push(Box::new(point)).unwrap();
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bring_to_front_moves_a_node_to_the_end_of_the_draw_order() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, 3);
+        assert_eq!(collection.ordered_for_draw(), ids);
+
+        collection.bring_to_front(&ids[0]).unwrap();
+        assert_eq!(collection.ordered_for_draw(), [ids[1], ids[2], ids[0]]);
+
+        collection.send_to_back(&ids[0]).unwrap();
+        assert_eq!(collection.ordered_for_draw(), ids);
+    }
+
+    #[test]
+    fn the_draw_order_survives_a_round_trip() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, 3);
+        collection.bring_to_front(&ids[1]).unwrap();
+
+        let read =
+            NodeCollection::from_json_string(&collection.to_json_string(false).unwrap()).unwrap();
+        assert_eq!(read.ordered_for_draw(), [ids[0], ids[2], ids[1]]);
+    }
+}
@@ -0,0 +1,106 @@
+use uuid::Uuid;
+
+use crate::{bounds::Bounded, concrete_node, NodeCollection, Rectangle};
+
+/// The x and y ranges a rectangle covers, as `[min_x, min_y, max_x, max_y]`.
+fn extent_xy(rect: &Rectangle) -> [f64; 4] {
+    let b = rect.bounding_box();
+    [b.min[0], b.min[1], b.max[0], b.max[1]]
+}
+
+/// True if the extents share an area, not just an edge or a corner.
+fn overlaps(a: &[f64; 4], b: &[f64; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+impl Rectangle {
+    /// The region both rectangles cover in the xy plane, with its bottom left
+    /// corner as the anchor at the elevation of `self`, ignoring z otherwise.
+    ///
+    /// Rectangles that only touch along an edge or at a corner do not intersect,
+    /// as their overlap has no area, and neither do rectangles of zero area.
+    pub fn intersect(&self, other: &Rectangle) -> Option<Rectangle> {
+        let (a, b) = (extent_xy(self), extent_xy(other));
+        if !overlaps(&a, &b) {
+            return None;
+        }
+
+        let mut overlap = Rectangle::new();
+        overlap.anchor.x = a[0].max(b[0]);
+        overlap.anchor.y = a[1].max(b[1]);
+        overlap.anchor.z = self.corner().z;
+        overlap.width = a[2].min(b[2]) - overlap.anchor.x;
+        overlap.height = a[3].min(b[3]) - overlap.anchor.y;
+        Some(overlap)
+    }
+}
+
+impl NodeCollection {
+    /// All pairs of rectangles that [`Rectangle::intersect`], each pair
+    /// ordered by uuid and the list sorted, e.g. for a clash report.
+    pub fn overlapping_pairs(&self) -> Vec<(Uuid, Uuid)> {
+        let mut rects: Vec<(Uuid, [f64; 4])> = self
+            .nodes
+            .iter()
+            .filter_map(|(id, n)| Some((*id, extent_xy(concrete_node::<Rectangle>(n.as_ref())?))))
+            .collect();
+        rects.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]));
+
+        // sweep along x, comparing each rectangle with those starting before it ends
+        let mut pairs = Vec::new();
+        for (i, (id, extent)) in rects.iter().enumerate() {
+            for (other, other_extent) in rects[i + 1..].iter().take_while(|r| r.1[0] < extent[2]) {
+                if overlaps(extent, other_extent) {
+                    pairs.push((*id.min(other), *id.max(other)));
+                }
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeometryNode;
+
+    fn rectangle(x: f64, y: f64, width: f64, height: f64) -> Rectangle {
+        let mut rectangle = Rectangle::new();
+        (rectangle.anchor.x, rectangle.anchor.y) = (x, y);
+        (rectangle.width, rectangle.height) = (width, height);
+        rectangle
+    }
+
+    #[test]
+    fn touching_rectangles_do_not_intersect() {
+        let left = rectangle(0.0, 0.0, 2.0, 2.0);
+
+        assert!(left.intersect(&rectangle(2.0, 0.0, 2.0, 2.0)).is_none());
+        assert!(left.intersect(&rectangle(2.0, 2.0, 1.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn overlapping_rectangles_intersect_in_their_shared_region() {
+        let overlap = rectangle(0.0, 0.0, 2.0, 2.0)
+            .intersect(&rectangle(1.0, 0.5, 2.0, 2.0))
+            .unwrap();
+
+        assert_eq!([overlap.anchor.x, overlap.anchor.y], [1.0, 0.5]);
+        assert_eq!([overlap.width, overlap.height], [1.0, 1.5]);
+    }
+
+    #[test]
+    fn only_overlapping_rectangles_are_listed_as_pairs() {
+        let base = rectangle(0.0, 0.0, 2.0, 2.0);
+        let overlapping = rectangle(1.0, 1.0, 2.0, 2.0);
+        let touching = rectangle(-2.0, 0.0, 2.0, 2.0);
+        let (a, b) = (base.uuid(), overlapping.uuid());
+        let mut collection = NodeCollection::new();
+        for rectangle in [base, overlapping, touching] {
+            collection.push(Box::new(rectangle)).unwrap();
+        }
+
+        assert_eq!(collection.overlapping_pairs(), [(a.min(b), a.max(b))]);
+    }
+}
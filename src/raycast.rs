@@ -0,0 +1,136 @@
+use uuid::Uuid;
+
+use crate::{shapes::Circle, NodeCollection, Point3, Rectangle};
+
+/// A direction in space, not necessarily of unit length.
+pub type Vec3 = [f64; 3];
+
+/// A half line starting at `origin`, for picking nodes in a 3d view.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Point3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    /// The point `t` direction lengths along the ray.
+    pub fn point_at(&self, t: f64) -> [f64; 3] {
+        let (o, d) = (self.origin, self.direction);
+        [o.x + t * d[0], o.y + t * d[1], o.z + t * d[2]]
+    }
+
+    /// The ray parameter where it crosses the plane `z = height`, if it does so ahead of its origin.
+    /// Rays running inside of the plane never cross it.
+    fn cross_z_plane(&self, height: f64) -> Option<f64> {
+        let dz = self.direction[2];
+        if dz == 0.0 {
+            return None;
+        }
+        let t = (height - self.origin.z) / dz;
+        (t >= 0.0).then_some(t)
+    }
+
+    fn length(&self) -> f64 {
+        let d = self.direction;
+        (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+    }
+}
+
+/// Geometry a [`Ray`] can hit.
+pub trait RayIntersect {
+    /// The distance from the ray origin to the first hit, `None` on a miss.
+    fn intersect(&self, ray: &Ray) -> Option<f64>;
+}
+
+/// Hits the flat patch the rectangle spans in its XY plane, edges included.
+impl RayIntersect for Rectangle {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        let c = self.corner();
+        let t = ray.cross_z_plane(c.z)?;
+        let [x, y, _] = ray.point_at(t);
+        let (x0, x1) = (c.x.min(c.x + self.width), c.x.max(c.x + self.width));
+        let (y0, y1) = (c.y.min(c.y + self.height), c.y.max(c.y + self.height));
+        (x0 <= x && x <= x1 && y0 <= y && y <= y1).then(|| t * ray.length())
+    }
+}
+
+/// Hits the disc the circle encloses in its XY plane, the rim included.
+impl RayIntersect for Circle {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        let c = self.center;
+        let t = ray.cross_z_plane(c.z)?;
+        let [x, y, _] = ray.point_at(t);
+        let (dx, dy) = (x - c.x, y - c.y);
+        (dx * dx + dy * dy <= self.radius * self.radius).then(|| t * ray.length())
+    }
+}
+
+impl NodeCollection {
+    /// The node `ray` hits first and the distance to the hit.
+    ///
+    /// Only nodes implementing [`RayIntersect`] can be hit. Nodes hit at the
+    /// same distance are told apart by their uuid, so the result is stable.
+    pub fn raycast(&self, ray: &Ray) -> Option<(Uuid, f64)> {
+        self.nodes
+            .iter()
+            .filter_map(|(id, n)| Some((*id, n.as_ray_intersect()?.intersect(ray)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeometryNode;
+
+    /// A ray from `[x, y, 10]` straight down.
+    fn downward(x: f64, y: f64) -> Ray {
+        let mut origin = Point3::new();
+        (origin.x, origin.y, origin.z) = (x, y, 10.0);
+        Ray::new(origin, [0.0, 0.0, -2.0])
+    }
+
+    /// A collection holding a 4 by 2 rectangle at the origin, and its uuid.
+    fn with_rectangle() -> (NodeCollection, Uuid) {
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 4.0;
+        *rectangle.height_mut() = 2.0;
+        let id = rectangle.uuid();
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(rectangle)).unwrap();
+        (collection, id)
+    }
+
+    #[test]
+    fn a_ray_hits_a_rectangle_at_its_distance() {
+        let (collection, id) = with_rectangle();
+
+        assert_eq!(collection.raycast(&downward(3.0, 1.0)), Some((id, 10.0)));
+    }
+
+    #[test]
+    fn a_ray_misses_a_rectangle_beside_or_behind_it() {
+        let (collection, _) = with_rectangle();
+        let mut away = downward(1.0, 1.0);
+        away.direction = [0.0, 0.0, 1.0];
+
+        assert_eq!(collection.raycast(&downward(5.0, 1.0)), None);
+        assert_eq!(collection.raycast(&away), None);
+    }
+
+    #[test]
+    fn the_nearest_node_is_hit_first() {
+        let (mut collection, _) = with_rectangle();
+        let mut circle = Circle::new();
+        *circle.radius_mut() = 1.0;
+        circle.center.z = 4.0;
+        let id = circle.uuid();
+        collection.push(Box::new(circle)).unwrap();
+
+        assert_eq!(collection.raycast(&downward(0.5, 0.5)), Some((id, 6.0)));
+    }
+}
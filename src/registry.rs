@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use serde::de::{DeserializeOwned, Error};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    block::Block,
+    error::GeodiffError,
+    shapes::{Arc, Circle, Ellipse, Line, PointCloud, Polyline},
+    GeometryNode, NodeCollection, Point3, Rectangle,
+};
+
+/// Turns the fields of a serialized node, without its tag, into a node.
+pub type NodeDeserializer = Box<dyn Fn(Value) -> Result<Box<dyn GeometryNode>, serde_json::Error>>;
+
+/// Node types registered at runtime, keyed by their `geometry_node` tag.
+///
+/// Tags without an entry fall back to the types `typetag` knows statically,
+/// so a registry only needs to list the types a host loads dynamically.
+#[derive(Default)]
+pub struct NodeRegistry {
+    deserializers: HashMap<String, NodeDeserializer>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `deserializer` for nodes tagged `tag`, replacing any earlier entry.
+    pub fn register(
+        &mut self,
+        tag: impl Into<String>,
+        deserializer: impl Fn(Value) -> Result<Box<dyn GeometryNode>, serde_json::Error> + 'static,
+    ) {
+        self.deserializers
+            .insert(tag.into(), Box::new(deserializer));
+    }
+
+    /// Registers a node type by its `Deserialize` impl.
+    pub fn register_type<T>(&mut self, tag: impl Into<String>)
+    where
+        T: GeometryNode + DeserializeOwned + 'static,
+    {
+        self.register(tag, |value| {
+            Ok(Box::new(serde_json::from_value::<T>(value)?) as Box<dyn GeometryNode>)
+        });
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.deserializers.contains_key(tag)
+    }
+
+    /// Deserializes a single tagged node, preferring the registered types.
+    pub fn deserialize(
+        &self,
+        mut value: Value,
+    ) -> Result<Box<dyn GeometryNode>, serde_json::Error> {
+        let registered = value
+            .get("geometry_node")
+            .and_then(Value::as_str)
+            .and_then(|tag| self.deserializers.get(tag));
+
+        match registered {
+            Some(deserializer) => {
+                if let Some(fields) = value.as_object_mut() {
+                    fields.remove("geometry_node");
+                }
+                deserializer(value)
+            }
+            None => serde_json::from_value(value),
+        }
+    }
+}
+
+/// Creates a new node of one type, see [`NodeFactoryRegistry`].
+pub type NodeConstructor = Box<dyn Fn() -> Box<dyn GeometryNode>>;
+
+/// Constructors of default nodes keyed by their `geometry_node` tag,
+/// e.g. for a toolbar offering to add any registered type by name.
+#[derive(Default)]
+pub struct NodeFactoryRegistry {
+    constructors: HashMap<String, NodeConstructor>,
+}
+
+impl NodeFactoryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry of all node types of this crate.
+    pub fn with_builtin_types() -> Self {
+        let mut registry = Self::new();
+        registry.register::<Point3>();
+        registry.register::<Rectangle>();
+        registry.register::<Circle>();
+        registry.register::<Arc>();
+        registry.register::<Ellipse>();
+        registry.register::<Line>();
+        registry.register::<Polyline>();
+        registry.register::<PointCloud>();
+        registry.register::<Block>();
+        registry
+    }
+
+    /// Registers `T` under its tag, replacing any earlier entry.
+    /// Nodes are created by its `Default` impl and so get a fresh uuid each.
+    pub fn register<T>(&mut self)
+    where
+        T: GeometryNode + Default + 'static,
+    {
+        let tag = T::default().type_tag();
+        self.constructors
+            .insert(tag.to_string(), Box::new(|| Box::new(T::default())));
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.constructors.contains_key(tag)
+    }
+
+    /// The registered tags, sorted.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self.constructors.keys().map(String::as_str).collect();
+        tags.sort();
+        tags
+    }
+
+    /// A new node of the type registered under `tag`, if any.
+    pub fn create(&self, tag: &str) -> Option<Box<dyn GeometryNode>> {
+        self.constructors.get(tag).map(|constructor| constructor())
+    }
+}
+
+impl NodeCollection {
+    /// Like [`NodeCollection::from_value`], but deserializes nodes through `registry`.
+    pub fn from_value_with_registry(
+        mut value: Value,
+        registry: &NodeRegistry,
+    ) -> Result<NodeCollection, GeodiffError> {
+        let nodes = value
+            .get_mut("nodes")
+            .map(Value::take)
+            .ok_or_else(|| serde_json::Error::missing_field("nodes"))?;
+        let Value::Object(nodes) = nodes else {
+            return Err(serde_json::Error::custom("nodes is not a map").into());
+        };
+        value["nodes"] = Value::Object(Default::default());
+
+        let mut collection = Self::from_value(value)?;
+        for (key, node) in nodes {
+            let key = Uuid::parse_str(&key).map_err(serde_json::Error::custom)?;
+            collection.nodes.insert(key, registry.deserialize(node)?);
+        }
+        collection.invalidate_bounds();
+        collection.invalidate_spatial();
+        Ok(collection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concrete_node;
+
+    #[test]
+    fn a_rectangle_is_created_by_its_tag_with_a_fresh_uuid() {
+        let registry = NodeFactoryRegistry::with_builtin_types();
+
+        let first = registry.create("Rectangle").unwrap();
+        let second = registry.create("Rectangle").unwrap();
+
+        let rectangle = concrete_node::<Rectangle>(first.as_ref()).unwrap();
+        assert_eq!([rectangle.width, rectangle.height], [0.0, 0.0]);
+        assert!(!first.uuid().is_nil());
+        assert_ne!(first.uuid(), second.uuid());
+    }
+
+    #[test]
+    fn unregistered_tags_create_nothing() {
+        let mut registry = NodeFactoryRegistry::new();
+        registry.register::<Circle>();
+
+        assert!(registry.create("Rectangle").is_none());
+        assert_eq!(registry.tags(), ["Circle"]);
+    }
+
+    /// A collection holding a point at the origin and a rectangle, and their uuids.
+    fn point_and_rectangle() -> (NodeCollection, Uuid, Uuid) {
+        let mut collection = NodeCollection::new();
+        let (point, rectangle) = (Point3::new(), Rectangle::new());
+        let ids = (point.uuid(), rectangle.uuid());
+        collection.push(Box::new(point)).unwrap();
+        collection.push(Box::new(rectangle)).unwrap();
+        (collection, ids.0, ids.1)
+    }
+
+    #[test]
+    fn a_registered_tag_wins_over_typetag() {
+        let (collection, point, _) = point_and_rectangle();
+        let mut registry = NodeRegistry::new();
+        registry.register("Point3", |value| {
+            let mut point: Point3 = serde_json::from_value(value)?;
+            point.x = 7.0;
+            Ok(Box::new(point) as Box<dyn GeometryNode>)
+        });
+
+        let read =
+            NodeCollection::from_value_with_registry(collection.to_value().unwrap(), &registry)
+                .unwrap();
+
+        assert_eq!(read.try_get_typed::<Point3>(&point).unwrap().x, 7.0);
+    }
+
+    #[test]
+    fn unregistered_tags_still_load_through_typetag() {
+        let (collection, point, rectangle) = point_and_rectangle();
+        let mut registry = NodeRegistry::new();
+        registry.register_type::<Circle>("Circle");
+
+        let read =
+            NodeCollection::from_value_with_registry(collection.to_value().unwrap(), &registry)
+                .unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert!(read.try_get_typed::<Rectangle>(&rectangle).is_some());
+        assert_eq!(collection.diff(&read).unwrap().changes(), []);
+        assert!(read.get(&point).is_some());
+    }
+}
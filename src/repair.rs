@@ -0,0 +1,183 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{error::GeodiffError, NodeCollection};
+
+/// A reference from `node` to the missing node `missing`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DanglingReference {
+    pub node: Uuid,
+    pub missing: Uuid,
+}
+
+/// What [`NodeCollection::from_value_repairing`] fixed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// References dropped from nodes referring to others only by uuid.
+    /// Sorted by node, then by the missing node, as are the other lists of references.
+    pub dropped_references: Vec<DanglingReference>,
+    /// References resolved by restoring the missing node from the copy the node embeds.
+    pub restored_references: Vec<DanglingReference>,
+    /// References the node could neither drop nor restore, left dangling.
+    pub unrepaired_references: Vec<DanglingReference>,
+    /// Missing nodes the bookkeeping, e.g. the names or layers, held entries for. Sorted.
+    pub orphaned_entries: Vec<Uuid>,
+}
+
+impl RepairReport {
+    /// True if the document needed no repairs.
+    pub fn is_empty(&self) -> bool {
+        self.dropped_references.is_empty()
+            && self.restored_references.is_empty()
+            && self.unrepaired_references.is_empty()
+            && self.orphaned_entries.is_empty()
+    }
+}
+
+impl NodeCollection {
+    /// Like [`NodeCollection::from_value`], but repairs references to missing
+    /// nodes as left behind by hand edits.
+    ///
+    /// Nodes referring to other nodes only by uuid, like the members of a block,
+    /// drop dangling references. Nodes embedding a copy of the referred node,
+    /// like the anchor of a rectangle, get the missing node restored from that copy,
+    /// see [`crate::GeometryNode::embedded_dependency`]. Should several nodes embed the
+    /// missing node, the first by uuid provides the copy.
+    /// Bookkeeping entries of missing nodes are removed.
+    pub fn from_value_repairing(
+        value: Value,
+    ) -> Result<(NodeCollection, RepairReport), GeodiffError> {
+        let mut collection = Self::from_value(value)?;
+        let mut report = RepairReport::default();
+
+        let ids: BTreeSet<Uuid> = collection.nodes.keys().copied().collect();
+        let mut restored = Vec::new();
+        for id in &ids {
+            let node = collection
+                .nodes
+                .get_mut(id)
+                .expect("ids are taken from the nodes");
+            for missing in node.dependencies() {
+                if ids.contains(&missing) {
+                    continue;
+                }
+                let reference = DanglingReference { node: *id, missing };
+                if node.drop_dependency(&missing) {
+                    report.dropped_references.push(reference);
+                } else if let Some(copy) = node.embedded_dependency(&missing) {
+                    report.restored_references.push(reference);
+                    restored.push(copy);
+                } else {
+                    report.unrepaired_references.push(reference);
+                }
+            }
+        }
+        for copy in restored {
+            if !collection.nodes.contains_key(&copy.uuid()) {
+                collection.insert_node(copy);
+            }
+        }
+
+        let bookkeeping = &collection.bookkeeping;
+        let orphaned: BTreeSet<Uuid> = bookkeeping
+            .names
+            .keys()
+            .chain(bookkeeping.layers.keys())
+            .chain(bookkeeping.locks.keys())
+            .chain(bookkeeping.z_order.keys())
+            .chain(bookkeeping.indices.uuids())
+            .filter(|id| !collection.nodes.contains_key(id))
+            .copied()
+            .collect();
+        for id in &orphaned {
+            collection.bookkeeping.forget(id);
+        }
+        report.orphaned_entries = orphaned.into_iter().collect();
+
+        Ok((collection, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block::Block, GeometryNode, Point3, Rectangle};
+
+    /// Deletes the node from the serialized collection, leaving the references and
+    /// the bookkeeping of other nodes to it in place, like a careless hand edit.
+    fn delete_node(value: &mut Value, id: &Uuid) {
+        value["nodes"]
+            .as_object_mut()
+            .unwrap()
+            .remove(&id.to_string());
+    }
+
+    #[test]
+    fn a_dangling_anchor_is_restored_from_the_rectangle() {
+        let mut collection = NodeCollection::new();
+        let mut rectangle = Rectangle::new();
+        rectangle.anchor_mut().x = 3.0;
+        let (rectangle_id, anchor_id) = (rectangle.uuid(), rectangle.dependencies()[0]);
+        let anchor = rectangle.embedded_dependency(&anchor_id).unwrap();
+        collection.push(Box::new(rectangle)).unwrap();
+        collection.push(anchor).unwrap();
+        let mut value = collection.to_value().unwrap();
+        delete_node(&mut value, &anchor_id);
+
+        let (repaired, report) = NodeCollection::from_value_repairing(value).unwrap();
+
+        assert_eq!(
+            report.restored_references,
+            [DanglingReference {
+                node: rectangle_id,
+                missing: anchor_id,
+            }]
+        );
+        assert!(report.dropped_references.is_empty());
+        assert!(report.unrepaired_references.is_empty());
+        assert_eq!(repaired.try_get_typed::<Point3>(&anchor_id).unwrap().x, 3.0);
+    }
+
+    #[test]
+    fn dangling_block_members_and_bookkeeping_are_dropped() {
+        let mut collection = NodeCollection::new();
+        let (kept, deleted) = (Point3::new(), Point3::new());
+        let (kept_id, deleted_id) = (kept.uuid(), deleted.uuid());
+        let mut block = Block::new();
+        block.members_mut().extend([kept_id, deleted_id]);
+        let block_id = block.uuid();
+        collection.push(Box::new(kept)).unwrap();
+        collection.push(Box::new(deleted)).unwrap();
+        collection.push(Box::new(block)).unwrap();
+        collection.set_layer(&deleted_id, "hidden").unwrap();
+        let mut value = collection.to_value().unwrap();
+        delete_node(&mut value, &deleted_id);
+
+        let (repaired, report) = NodeCollection::from_value_repairing(value).unwrap();
+
+        assert_eq!(
+            report.dropped_references,
+            [DanglingReference {
+                node: block_id,
+                missing: deleted_id,
+            }]
+        );
+        assert_eq!(report.orphaned_entries, [deleted_id]);
+        let block = repaired.try_get_typed::<Block>(&block_id).unwrap();
+        assert_eq!(block.members(), [kept_id]);
+        assert_eq!(repaired.layer(&deleted_id), None);
+    }
+
+    #[test]
+    fn an_intact_document_needs_no_repairs() {
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(Point3::new())).unwrap();
+
+        let (_, report) =
+            NodeCollection::from_value_repairing(collection.to_value().unwrap()).unwrap();
+        assert!(report.is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::Schema,
+    JsonSchema,
+};
+use serde_json::{json, Value};
+
+use crate::{Bookkeeping, NodeCollection};
+
+/// Registry entry describing the json schema of one [`crate::GeometryNode`] type.
+pub struct NodeSchema {
+    /// The `geometry_node` tag of the type.
+    pub tag: &'static str,
+    pub schema: fn(&mut SchemaGenerator) -> Schema,
+}
+
+inventory::collect!(NodeSchema);
+
+pub(crate) fn schema_of<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// Adds a node type to the schema emitted by [`NodeCollection::json_schema`].
+/// Every type with a `#[typetag::serde]` impl of `GeometryNode` should be registered.
+macro_rules! register_schema {
+    ($node:ident) => {
+        inventory::submit! {
+            $crate::schema::NodeSchema {
+                tag: stringify!($node),
+                schema: $crate::schema::schema_of::<$node>,
+            }
+        }
+    };
+}
+pub(crate) use register_schema;
+
+impl NodeCollection {
+    /// A json schema of the serialized collection, describing every registered
+    /// node type as one variant of a union tagged by `geometry_node`.
+    pub fn json_schema() -> Value {
+        let mut gen = SchemaSettings::draft07().into_generator();
+
+        let mut nodes: Vec<&NodeSchema> = inventory::iter::<NodeSchema>.into_iter().collect();
+        nodes.sort_by_key(|n| n.tag);
+        let variants: Vec<Value> = nodes
+            .into_iter()
+            .map(|node| {
+                json!({
+                    "allOf": [
+                        (node.schema)(&mut gen),
+                        {
+                            "type": "object",
+                            "properties": { "geometry_node": { "const": node.tag } },
+                            "required": ["geometry_node"],
+                        },
+                    ]
+                })
+            })
+            .collect();
+        let bookkeeping = gen.subschema_for::<Bookkeeping>();
+
+        let mut definitions =
+            serde_json::to_value(gen.definitions()).expect("schemas serialize to json");
+        definitions["GeometryNode"] = json!({ "oneOf": variants });
+
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "NodeCollection",
+            "allOf": [
+                {
+                    "type": "object",
+                    "properties": {
+                        "nodes": {
+                            "type": "object",
+                            "additionalProperties": { "$ref": "#/definitions/GeometryNode" },
+                        },
+                    },
+                    "required": ["nodes"],
+                },
+                bookkeeping,
+            ],
+            "definitions": definitions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point3, Rectangle};
+
+    /// The `geometry_node` tags the schema's node union lists.
+    fn variant_tags(schema: &Value) -> Vec<&str> {
+        schema["definitions"]["GeometryNode"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v["allOf"][1]["properties"]["geometry_node"]["const"].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn the_schema_lists_every_builtin_node_type_once() {
+        let schema = NodeCollection::json_schema();
+        let tags = variant_tags(&schema);
+
+        for tag in ["Point3", "Rectangle", "Circle", "Polyline", "Block"] {
+            assert_eq!(tags.iter().filter(|t| **t == tag).count(), 1, "{}", tag);
+        }
+        let mut sorted = tags.clone();
+        sorted.sort();
+        assert_eq!(tags, sorted);
+        assert!(schema["definitions"]["Rectangle"]["properties"]["width"].is_object());
+    }
+
+    #[test]
+    fn serialized_nodes_carry_tags_the_schema_knows() {
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(Point3::new())).unwrap();
+        collection.push(Box::new(Rectangle::new())).unwrap();
+        let schema = NodeCollection::json_schema();
+        let tags = variant_tags(&schema);
+
+        let value = collection.to_value().unwrap();
+
+        for node in value["nodes"].as_object().unwrap().values() {
+            assert!(tags.contains(&node["geometry_node"].as_str().unwrap()));
+        }
+        assert_eq!(schema["allOf"][0]["required"], json!(["nodes"]));
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::NodeCollection;
+
+/// The nodes of two collections, sorted into whole node buckets.
+/// Each list is sorted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Only held by the newer collection.
+    pub added: Vec<Uuid>,
+    /// Only held by the older collection.
+    pub removed: Vec<Uuid>,
+    /// Held by both, with differing content.
+    pub modified: Vec<Uuid>,
+    /// Held by both, with equal content.
+    pub unchanged: Vec<Uuid>,
+}
+
+impl NodeCollection {
+    /// The nodes of `self` whose uuid `other` does not hold, sorted.
+    pub fn difference(&self, other: &NodeCollection) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self
+            .nodes
+            .keys()
+            .filter(|id| !other.nodes.contains_key(id))
+            .copied()
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// The uuids held by both `self` and `other`, sorted.
+    pub fn intersection(&self, other: &NodeCollection) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self
+            .nodes
+            .keys()
+            .filter(|id| other.nodes.contains_key(id))
+            .copied()
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Sorts the nodes of `self` and the newer `other` into buckets by comparing
+    /// them with [`crate::GeometryNode::semantic_eq`], so a replica only needs to fetch what changed.
+    ///
+    /// Nodes `self` holds a tombstone for are never added back,
+    /// see [`NodeCollection::set_keep_tombstones`].
+    pub fn reconcile(&self, other: &NodeCollection) -> Reconciliation {
+        let (unchanged, modified) = self
+            .intersection(other)
+            .into_iter()
+            .partition(|id| self.nodes[id].semantic_eq(other.nodes[id].as_ref()));
+
+        Reconciliation {
+            added: other
+                .difference(self)
+                .into_iter()
+                .filter(|id| !self.is_tombstoned(id))
+                .collect(),
+            removed: self.difference(other),
+            modified,
+            unchanged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3, Rectangle};
+
+    fn sorted(mut ids: Vec<Uuid>) -> Vec<Uuid> {
+        ids.sort();
+        ids
+    }
+
+    /// Pushes `n` points at the origin, returning their uuids in order.
+    fn with_points(collection: &mut NodeCollection, n: usize) -> Vec<Uuid> {
+        (0..n)
+            .map(|_| {
+                let point = Point3::new();
+                let id = point.uuid();
+                collection.push(Box::new(point)).unwrap();
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn difference_and_intersection_of_overlapping_collections() {
+        let mut a = NodeCollection::new();
+        let shared = with_points(&mut a, 2);
+        let mut b = a.snapshot();
+        let only_a = with_points(&mut a, 1);
+        let only_b = with_points(&mut b, 2);
+
+        assert_eq!(a.difference(&b), only_a);
+        assert_eq!(b.difference(&a), sorted(only_b));
+        assert_eq!(a.intersection(&b), sorted(shared.clone()));
+        assert_eq!(b.intersection(&a), sorted(shared));
+    }
+
+    #[test]
+    fn reconcile_sorts_nodes_into_the_four_buckets() {
+        let mut older = NodeCollection::new();
+        let kept = with_points(&mut older, 2);
+        let mut newer = older.snapshot();
+        let removed = with_points(&mut older, 1);
+        let added = with_points(&mut newer, 1);
+        newer.update(&kept[1], |p: &mut Point3| p.x = 4.0).unwrap();
+
+        assert_eq!(
+            older.reconcile(&newer),
+            Reconciliation {
+                added,
+                removed,
+                modified: vec![kept[1]],
+                unchanged: vec![kept[0]],
+            }
+        );
+    }
+
+    #[test]
+    fn reconcile_sees_a_rectangle_anchor_pointing_at_another_point() {
+        let mut older = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        older.push(Box::new(rectangle)).unwrap();
+        let mut newer = older.snapshot();
+        newer
+            .update(&id, |r: &mut Rectangle| {
+                r.anchor_mut().uuid = Uuid::new_v4()
+            })
+            .unwrap();
+
+        let reconciliation = older.reconcile(&newer);
+
+        assert_eq!(reconciliation.modified, vec![id]);
+        assert!(reconciliation.unchanged.is_empty());
+    }
+}
@@ -0,0 +1,657 @@
+use std::any::Any;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    bounds::Bounded,
+    concrete_node,
+    diff::{Change, ChangeCategory},
+    domain::DiffAware,
+    extra::ExtraFields,
+    factory::{NodeFactory, V4Factory},
+    fields::{set_field_by_json, FieldError},
+    measure::Measurable,
+    raycast::RayIntersect,
+    schema::register_schema,
+    sphere::BoundedSphere,
+    tessellate::Tessellate,
+    transform::Transformable,
+    GeometryNode, Point3,
+};
+
+/// Changes to a single coordinate of `points`, like `points/0/2`, move the geometry.
+fn point_coordinate_category(field: &[String]) -> Option<ChangeCategory> {
+    matches!(field, [points, _, _] if points == "points").then_some(ChangeCategory::Positional)
+}
+
+/// A circle in the XY plane through its center.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Circle {
+    pub(crate) center: Point3,
+    pub(crate) radius: f64,
+    pub(crate) uuid: Uuid,
+    #[serde(flatten)]
+    pub(crate) extra: ExtraFields,
+}
+
+impl Circle {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            center: Point3::from_factory(factory),
+            radius: 0.0,
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    pub fn radius_mut(&mut self) -> &mut f64 {
+        &mut self.radius
+    }
+}
+
+impl Default for Circle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Circle);
+
+#[typetag::serde]
+impl GeometryNode for Circle {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        Some(self)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        Some(self)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn as_measurable(&self) -> Option<&dyn Measurable> {
+        Some(self)
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        Some(self)
+    }
+    fn as_ray_intersect(&self) -> Option<&dyn RayIntersect> {
+        Some(self)
+    }
+    fn dependencies(&self) -> Vec<Uuid> {
+        vec![self.center.uuid]
+    }
+    fn embedded_dependency(&self, uuid: &Uuid) -> Option<Box<dyn GeometryNode>> {
+        (self.center.uuid == *uuid).then(|| self.center.clone_box())
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Circle>(other).is_some_and(|o| {
+            self.center.same_position(&o.center)
+                && self.radius == o.radius
+                && self.extra == o.extra
+                && self.dependencies() == o.dependencies()
+        })
+    }
+}
+
+/// A circular arc in the XY plane through its center, running counter clockwise
+/// from `start_angle` for `sweep_angle` radians. A negative sweep runs clockwise.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Arc {
+    pub(crate) center: Point3,
+    pub(crate) radius: f64,
+    pub(crate) start_angle: f64,
+    pub(crate) sweep_angle: f64,
+    pub(crate) uuid: Uuid,
+    #[serde(flatten)]
+    pub(crate) extra: ExtraFields,
+}
+
+impl Arc {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            center: Point3::from_factory(factory),
+            radius: 0.0,
+            start_angle: 0.0,
+            sweep_angle: 0.0,
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    pub fn radius_mut(&mut self) -> &mut f64 {
+        &mut self.radius
+    }
+    pub fn start_angle_mut(&mut self) -> &mut f64 {
+        &mut self.start_angle
+    }
+    pub fn sweep_angle_mut(&mut self) -> &mut f64 {
+        &mut self.sweep_angle
+    }
+
+    /// The point at `angle` on the arc's circle.
+    pub fn point_at(&self, angle: f64) -> [f64; 3] {
+        let c = self.center;
+        [
+            c.x + self.radius * angle.cos(),
+            c.y + self.radius * angle.sin(),
+            c.z,
+        ]
+    }
+}
+
+impl Default for Arc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Arc);
+
+#[typetag::serde]
+impl GeometryNode for Arc {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        Some(self)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        Some(self)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        Some(self)
+    }
+    fn dependencies(&self) -> Vec<Uuid> {
+        vec![self.center.uuid]
+    }
+    fn embedded_dependency(&self, uuid: &Uuid) -> Option<Box<dyn GeometryNode>> {
+        (self.center.uuid == *uuid).then(|| self.center.clone_box())
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Arc>(other).is_some_and(|o| {
+            self.center.same_position(&o.center)
+                && (self.radius, self.start_angle, self.sweep_angle)
+                    == (o.radius, o.start_angle, o.sweep_angle)
+                && self.extra == o.extra
+                && self.dependencies() == o.dependencies()
+        })
+    }
+}
+
+/// An axis aligned ellipse in the XY plane through its center.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Ellipse {
+    pub(crate) center: Point3,
+    pub(crate) radius_x: f64,
+    pub(crate) radius_y: f64,
+    pub(crate) uuid: Uuid,
+    #[serde(flatten)]
+    pub(crate) extra: ExtraFields,
+}
+
+impl Ellipse {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            center: Point3::from_factory(factory),
+            radius_x: 0.0,
+            radius_y: 0.0,
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    pub fn radius_x_mut(&mut self) -> &mut f64 {
+        &mut self.radius_x
+    }
+    pub fn radius_y_mut(&mut self) -> &mut f64 {
+        &mut self.radius_y
+    }
+}
+
+impl Default for Ellipse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Ellipse);
+
+#[typetag::serde]
+impl GeometryNode for Ellipse {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        Some(self)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        Some(self)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn as_measurable(&self) -> Option<&dyn Measurable> {
+        Some(self)
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        Some(self)
+    }
+    fn dependencies(&self) -> Vec<Uuid> {
+        vec![self.center.uuid]
+    }
+    fn embedded_dependency(&self, uuid: &Uuid) -> Option<Box<dyn GeometryNode>> {
+        (self.center.uuid == *uuid).then(|| self.center.clone_box())
+    }
+    fn change_category(&self, field: &[String]) -> Option<ChangeCategory> {
+        matches!(field, [radius] if radius == "radius_x" || radius == "radius_y")
+            .then_some(ChangeCategory::Dimensional)
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Ellipse>(other).is_some_and(|o| {
+            self.center.same_position(&o.center)
+                && (self.radius_x, self.radius_y) == (o.radius_x, o.radius_y)
+                && self.extra == o.extra
+                && self.dependencies() == o.dependencies()
+        })
+    }
+}
+
+/// A straight segment running from `start` to `end`.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Line {
+    pub(crate) start: Point3,
+    pub(crate) end: Point3,
+    pub(crate) uuid: Uuid,
+    #[serde(flatten)]
+    pub(crate) extra: ExtraFields,
+}
+
+impl Line {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            start: Point3::from_factory(factory),
+            end: Point3::from_factory(factory),
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    pub fn start_mut(&mut self) -> &mut Point3 {
+        &mut self.start
+    }
+
+    pub fn end_mut(&mut self) -> &mut Point3 {
+        &mut self.end
+    }
+
+    pub fn length(&self) -> f64 {
+        let (a, b) = (self.start, self.end);
+        ((b.x - a.x).powi(2) + (b.y - a.y).powi(2) + (b.z - a.z).powi(2)).sqrt()
+    }
+
+    /// Swaps the end points, so the line runs the other way.
+    pub fn reverse(&mut self) {
+        std::mem::swap(&mut self.start, &mut self.end);
+    }
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Line);
+
+#[typetag::serde]
+impl GeometryNode for Line {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        Some(self)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        Some(self)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        Some(self)
+    }
+    fn dependencies(&self) -> Vec<Uuid> {
+        vec![self.start.uuid, self.end.uuid]
+    }
+    fn embedded_dependency(&self, uuid: &Uuid) -> Option<Box<dyn GeometryNode>> {
+        [self.start, self.end]
+            .into_iter()
+            .find(|p| p.uuid == *uuid)
+            .map(|p| p.clone_box())
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Line>(other).is_some_and(|o| {
+            self.start.same_position(&o.start)
+                && self.end.same_position(&o.end)
+                && self.extra == o.extra
+                && self.dependencies() == o.dependencies()
+        })
+    }
+    fn diff_against_node(&self, other: &dyn GeometryNode) -> Option<Vec<Change>> {
+        concrete_node::<Line>(other).map(|o| self.diff_against(o))
+    }
+}
+
+/// A chain of line segments through its points, closing back onto the
+/// first point if `closed`. Like a [`PointCloud`] the points carry no uuids.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Polyline {
+    pub(crate) points: Vec<[f64; 3]>,
+    #[serde(default)]
+    pub(crate) closed: bool,
+    pub(crate) uuid: Uuid,
+    #[serde(flatten)]
+    pub(crate) extra: ExtraFields,
+}
+
+impl Polyline {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            points: Vec::new(),
+            closed: false,
+            uuid: factory.new_id(),
+            extra: ExtraFields::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn push(&mut self, x: f64, y: f64, z: f64) {
+        self.points.push([x, y, z]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PointView> + '_ {
+        self.points.iter().map(|&[x, y, z]| PointView { x, y, z })
+    }
+}
+
+impl Default for Polyline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(Polyline);
+
+#[typetag::serde]
+impl GeometryNode for Polyline {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn extra(&self) -> Option<&ExtraFields> {
+        Some(&self.extra)
+    }
+    fn extra_mut(&mut self) -> Option<&mut ExtraFields> {
+        Some(&mut self.extra)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        (!self.is_empty()).then_some(self as &dyn Bounded)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        (!self.is_empty()).then_some(self as &dyn BoundedSphere)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn as_tessellate(&self) -> Option<&dyn Tessellate> {
+        Some(self)
+    }
+    fn change_category(&self, field: &[String]) -> Option<ChangeCategory> {
+        point_coordinate_category(field)
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<Polyline>(other).is_some_and(|o| {
+            self.points == o.points && self.closed == o.closed && self.extra == o.extra
+        })
+    }
+}
+
+/// Many points stored compactly in one node, without a uuid per point.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct PointCloud {
+    pub(crate) points: Vec<[f64; 3]>,
+    pub(crate) uuid: Uuid,
+}
+
+/// A point of a [`PointCloud`] or [`Polyline`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointView {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl PointCloud {
+    pub fn new() -> Self {
+        Self::from_factory(&V4Factory)
+    }
+
+    pub fn from_factory(factory: &dyn NodeFactory) -> Self {
+        Self {
+            points: Vec::new(),
+            uuid: factory.new_id(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn push(&mut self, x: f64, y: f64, z: f64) {
+        self.points.push([x, y, z]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PointView> + '_ {
+        self.points.iter().map(|&[x, y, z]| PointView { x, y, z })
+    }
+}
+
+impl Default for PointCloud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+register_schema!(PointCloud);
+
+#[typetag::serde]
+impl GeometryNode for PointCloud {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn GeometryNode> {
+        Box::new(self.clone())
+    }
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), FieldError> {
+        set_field_by_json(self, name, value)
+    }
+    fn as_bounded(&self) -> Option<&dyn Bounded> {
+        (!self.is_empty()).then_some(self as &dyn Bounded)
+    }
+    fn as_bounded_sphere(&self) -> Option<&dyn BoundedSphere> {
+        (!self.is_empty()).then_some(self as &dyn BoundedSphere)
+    }
+    fn as_transformable_mut(&mut self) -> Option<&mut dyn Transformable> {
+        Some(self)
+    }
+    fn change_category(&self, field: &[String]) -> Option<ChangeCategory> {
+        point_coordinate_category(field)
+    }
+    fn semantic_eq(&self, other: &dyn GeometryNode) -> bool {
+        concrete_node::<PointCloud>(other).is_some_and(|o| self.points == o.points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diff::ChangePath, NodeCollection};
+
+    #[test]
+    fn adding_a_point_to_a_large_cloud_diffs_as_a_single_added_element() {
+        let mut cloud = PointCloud::new();
+        for i in 0..10_000 {
+            cloud.push(i as f64, 0.0, 2.0);
+        }
+        let id = cloud.uuid();
+        let mut before = NodeCollection::new();
+        before.push(Box::new(cloud)).unwrap();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after
+            .update(&id, |c: &mut PointCloud| c.push(-1.0, 1.0, 0.5))
+            .unwrap();
+
+        let diff = before.diff(&after).unwrap();
+
+        assert_eq!(
+            diff.changes(),
+            [Change::Added {
+                path: ChangePath::node_root(&id).join("points").join("10000"),
+                value: serde_json::json!([-1.0, 1.0, 0.5]),
+            }]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use serde::de::Error;
+use serde_json::{Map, Value};
+
+use crate::{error::GeodiffError, hash::value_hash, NodeCollection};
+
+/// The top level key of the pool of shared sub-nodes, see [`NodeCollection::to_shared_value`].
+pub const SHARED_POOL_KEY: &str = "shared";
+/// The key of an object standing in for a pooled sub-node.
+pub const SHARED_REF_KEY: &str = "$shared";
+
+/// True for the sub-nodes embedded in a node, like the anchor of a rectangle.
+fn is_sub_node(value: &Value) -> bool {
+    value.as_object().is_some_and(|o| o.contains_key("uuid"))
+}
+
+/// Calls `f` on every sub-node below the fields of a node, outermost first,
+/// descending into a sub-node only if `f` returns true for it.
+fn visit_sub_nodes(fields: &mut Value, f: &mut impl FnMut(&mut Value) -> bool) {
+    let children: Vec<&mut Value> = match fields {
+        Value::Object(map) => map.values_mut().collect(),
+        Value::Array(items) => items.iter_mut().collect(),
+        _ => return,
+    };
+    for child in children {
+        if !is_sub_node(child) || f(child) {
+            visit_sub_nodes(child, f);
+        }
+    }
+}
+
+impl NodeCollection {
+    /// The collection as json, with sub-nodes embedded more than once, like
+    /// an anchor many rectangles share, written once to a pool under
+    /// [`SHARED_POOL_KEY`] and referenced by `{"$shared": <key>}` objects.
+    ///
+    /// Sub-nodes only pool when they are equal in every field, uuid included.
+    pub fn to_shared_value(&self) -> Result<Value, GeodiffError> {
+        let mut value = self.to_value()?;
+
+        let mut counts: HashMap<u64, (Value, usize)> = HashMap::new();
+        for node in value["nodes"]
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|n| n.values_mut())
+        {
+            visit_sub_nodes(node, &mut |sub| {
+                let entry = counts
+                    .entry(value_hash(sub))
+                    .or_insert_with(|| (sub.clone(), 0));
+                // a colliding hash of another sub-node stays inline
+                if entry.0 == *sub {
+                    entry.1 += 1;
+                }
+                // the sub-nodes inside of a repeat are pooled with it
+                entry.1 <= 1
+            });
+        }
+        counts.retain(|_, (_, count)| *count > 1);
+        if counts.is_empty() {
+            return Ok(value);
+        }
+
+        for node in value["nodes"]
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|n| n.values_mut())
+        {
+            visit_sub_nodes(node, &mut |sub| {
+                let hash = value_hash(sub);
+                let pooled = counts.get(&hash).is_some_and(|(pooled, _)| pooled == sub);
+                if pooled {
+                    *sub = Value::Object(Map::from_iter([(
+                        SHARED_REF_KEY.to_string(),
+                        Value::String(format!("{:016x}", hash)),
+                    )]));
+                }
+                !pooled
+            });
+        }
+        let pool: Map<String, Value> = counts
+            .into_iter()
+            .map(|(hash, (sub, _))| (format!("{:016x}", hash), sub))
+            .collect();
+        value[SHARED_POOL_KEY] = Value::Object(pool);
+        Ok(value)
+    }
+
+    /// Reads a collection written by [`NodeCollection::to_shared_value`],
+    /// or by [`NodeCollection::to_value`], which has no pool.
+    pub fn from_shared_value(mut value: Value) -> Result<NodeCollection, GeodiffError> {
+        let pool = match value
+            .as_object_mut()
+            .and_then(|v| v.remove(SHARED_POOL_KEY))
+        {
+            Some(Value::Object(pool)) => pool,
+            Some(_) => return Err(serde_json::Error::custom("the shared pool is no object").into()),
+            None => Map::new(),
+        };
+
+        let mut missing = None;
+        for node in value["nodes"]
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|n| n.values_mut())
+        {
+            expand_refs(node, &pool, &mut missing);
+        }
+        if let Some(key) = missing {
+            return Err(
+                serde_json::Error::custom(format!("unknown shared sub-node {}", key)).into(),
+            );
+        }
+        Self::from_value(value)
+    }
+}
+
+/// Replaces the references below `value` by copies of the pooled sub-nodes,
+/// noting a key the pool does not hold in `missing`.
+fn expand_refs(value: &mut Value, pool: &Map<String, Value>, missing: &mut Option<String>) {
+    if let Some(key) = value
+        .as_object()
+        .filter(|o| o.len() == 1)
+        .and_then(|o| o.get(SHARED_REF_KEY))
+        .and_then(Value::as_str)
+    {
+        match pool.get(key) {
+            Some(sub) => *value = sub.clone(),
+            None => *missing = Some(key.to_string()),
+        }
+        return;
+    }
+
+    match value {
+        Value::Object(map) => map.values_mut().for_each(|v| expand_refs(v, pool, missing)),
+        Value::Array(items) => items.iter_mut().for_each(|v| expand_refs(v, pool, missing)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::Rectangle;
+
+    #[test]
+    fn an_anchor_two_rectangles_share_is_written_once() {
+        let mut first = Rectangle::new();
+        first.anchor_mut().x = 3.0;
+        let anchor_id = first.anchor.uuid;
+        let mut second = first.clone();
+        second.uuid = Uuid::new_v4();
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(first)).unwrap();
+        collection.push(Box::new(second)).unwrap();
+
+        let value = collection.to_shared_value().unwrap();
+
+        assert_eq!(value[SHARED_POOL_KEY].as_object().unwrap().len(), 1);
+        let text = value.to_string();
+        assert_eq!(text.matches(&anchor_id.to_string()).count(), 1);
+        assert_eq!(text.matches(SHARED_REF_KEY).count(), 2);
+
+        let reloaded = NodeCollection::from_shared_value(value).unwrap();
+        assert_eq!(collection.diff(&reloaded).unwrap().changes(), []);
+    }
+
+    #[test]
+    fn a_collection_without_repeats_has_no_pool() {
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(Rectangle::new())).unwrap();
+        collection.push(Box::new(Rectangle::new())).unwrap();
+
+        let value = collection.to_shared_value().unwrap();
+
+        assert!(value.get(SHARED_POOL_KEY).is_none());
+        assert_eq!(value, collection.to_value().unwrap());
+    }
+}
@@ -0,0 +1,143 @@
+use uuid::Uuid;
+
+use crate::{concrete_node, shapes::Polyline, NodeCollection};
+
+/// The distance of `p` to the segment from `a` to `b`.
+fn segment_distance(p: [f64; 3], a: [f64; 3], b: [f64; 3]) -> f64 {
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let ap = [p[0] - a[0], p[1] - a[1], p[2] - a[2]];
+    let len_sq = ab[0] * ab[0] + ab[1] * ab[1] + ab[2] * ab[2];
+    let t = if len_sq > 0.0 {
+        ((ap[0] * ab[0] + ap[1] * ab[1] + ap[2] * ab[2]) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let d = [ap[0] - t * ab[0], ap[1] - t * ab[1], ap[2] - t * ab[2]];
+    (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+}
+
+/// Marks the points between `first` and `last` that lie further than
+/// `tolerance` from the simplified line, by Ramer–Douglas–Peucker.
+fn mark_kept(points: &[[f64; 3]], first: usize, last: usize, tolerance: f64, keep: &mut [bool]) {
+    // an explicit stack, so long dense runs can not overflow the call stack
+    let mut pending = vec![(first, last)];
+    while let Some((first, last)) = pending.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, segment_distance(points[i], points[first], points[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                pending.push((first, i));
+                pending.push((i, last));
+            }
+        }
+    }
+}
+
+impl Polyline {
+    /// A copy of this polyline without the points that lie within
+    /// `tolerance` of the line through their neighbours that remain.
+    ///
+    /// The first and last point are always kept, as is the uuid,
+    /// so the result can replace this polyline in a collection.
+    pub fn simplify(&self, tolerance: f64) -> Polyline {
+        let mut simplified = self.clone();
+        if self.points.len() < 3 {
+            return simplified;
+        }
+
+        let last = self.points.len() - 1;
+        let mut keep = vec![false; self.points.len()];
+        keep[0] = true;
+        keep[last] = true;
+        mark_kept(&self.points, 0, last, tolerance, &mut keep);
+
+        simplified.points = self
+            .points
+            .iter()
+            .zip(keep)
+            .filter_map(|(p, kept)| kept.then_some(*p))
+            .collect();
+        simplified
+    }
+}
+
+impl NodeCollection {
+    /// Simplifies every polyline that is not frozen in place, see [`Polyline::simplify`].
+    /// Polylines that lose no points are left untouched and fire no events.
+    pub fn simplify_all(&mut self, tolerance: f64) {
+        let simplified: Vec<(Uuid, Polyline)> = self
+            .nodes
+            .iter()
+            .filter_map(|(id, n)| {
+                let polyline = concrete_node::<Polyline>(n.as_ref())?;
+                let simplified = polyline.simplify(tolerance);
+                (simplified.len() < polyline.len()).then_some((*id, simplified))
+            })
+            .collect();
+
+        for (id, polyline) in simplified {
+            // Locked polylines are skipped.
+            let _ = self.update(&id, |p: &mut Polyline| *p = polyline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock::LockInfo, GeometryNode};
+
+    fn polyline(points: &[[f64; 3]]) -> Polyline {
+        let mut polyline = Polyline::new();
+        for &[x, y, z] in points {
+            polyline.push(x, y, z);
+        }
+        polyline
+    }
+
+    #[test]
+    fn a_straight_run_collapses_to_its_end_points() {
+        let run: Vec<[f64; 3]> = (0..=10).map(|i| [i as f64, 0.0, 0.0]).collect();
+
+        let simplified = polyline(&run).simplify(0.01);
+
+        assert_eq!(simplified.points, [[0.0, 0.0, 0.0], [10.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn a_sharp_corner_is_kept() {
+        let corner = polyline(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 1.0, 0.0],
+            [2.0, 2.0, 0.0],
+        ]);
+
+        let simplified = corner.simplify(0.1);
+
+        assert_eq!(
+            simplified.points,
+            [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 2.0, 0.0]]
+        );
+        assert_eq!(simplified.uuid(), corner.uuid());
+    }
+
+    #[test]
+    fn simplify_all_skips_locked_polylines() {
+        let run: Vec<[f64; 3]> = (0..5).map(|i| [i as f64, 0.0, 0.0]).collect();
+        let (free, locked) = (polyline(&run), polyline(&run));
+        let (free_id, locked_id) = (free.uuid(), locked.uuid());
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(free)).unwrap();
+        collection.push(Box::new(locked)).unwrap();
+        collection.lock(&locked_id, LockInfo::new("traced"));
+
+        collection.simplify_all(0.01);
+
+        let len = |id| collection.try_get_typed::<Polyline>(id).unwrap().len();
+        assert_eq!((len(&free_id), len(&locked_id)), (2, 5));
+    }
+}
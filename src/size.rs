@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use crate::{GeometryNode, NodeCollection};
+
+/// The length of the compact json of a node, its `geometry_node` tag included.
+fn serialized_len(node: &dyn GeometryNode) -> usize {
+    serde_json::to_vec(node)
+        .expect("geometry nodes serialize to json")
+        .len()
+}
+
+impl NodeCollection {
+    /// The serialized size of every node in bytes, largest first,
+    /// to find what bloats a document. Nodes of equal size are ordered by uuid.
+    pub fn size_report(&self) -> Vec<(Uuid, usize)> {
+        let mut sizes: Vec<(Uuid, usize)> = self
+            .nodes
+            .iter()
+            .map(|(id, n)| (*id, serialized_len(n.as_ref())))
+            .collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sizes
+    }
+
+    /// The summed serialized size of the nodes of each type tag, in bytes.
+    pub fn size_by_type(&self) -> BTreeMap<&'static str, usize> {
+        let mut totals = BTreeMap::new();
+        for node in self.nodes.values() {
+            *totals.entry(node.type_tag()).or_default() += serialized_len(node.as_ref());
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shapes::PointCloud, Point3};
+
+    #[test]
+    fn a_large_point_cloud_reports_a_larger_size_than_a_point() {
+        let mut collection = NodeCollection::new();
+        let (mut cloud, point, other) = (PointCloud::new(), Point3::new(), Point3::new());
+        for i in 0..1000 {
+            cloud.push(i as f64, 0.5, 0.25);
+        }
+        let cloud_id = cloud.uuid();
+        collection.push(Box::new(point)).unwrap();
+        collection.push(Box::new(cloud)).unwrap();
+        collection.push(Box::new(other)).unwrap();
+
+        let report = collection.size_report();
+
+        assert_eq!(report.len(), 3);
+        assert_eq!(report[0].0, cloud_id);
+        assert!(report[0].1 > 10 * report[1].1);
+        // equally sized points are ordered by uuid
+        assert_eq!(report[1].1, report[2].1);
+        assert!(report[1].0 < report[2].0);
+
+        let by_type = collection.size_by_type();
+        assert_eq!(by_type["PointCloud"], report[0].1);
+        assert_eq!(by_type["Point3"], report[1].1 + report[2].1);
+    }
+}
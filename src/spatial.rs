@@ -0,0 +1,399 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    sync::{MutexGuard, PoisonError},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    bounds::{node_bounds, BoundingBox},
+    NodeCollection,
+};
+
+/// A cell of the grid the spatial hash buckets nodes by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GridCell {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl GridCell {
+    /// The cell holding `point` on a grid of cubes with edges `cell_size` long.
+    pub fn of(point: [f64; 3], cell_size: f64) -> Self {
+        let quantize = |v: f64| (v / cell_size).floor() as i64;
+        Self {
+            x: quantize(point[0]),
+            y: quantize(point[1]),
+            z: quantize(point[2]),
+        }
+    }
+
+    /// The 27 cells of the 3x3x3 block centered on this one, this cell included.
+    pub fn neighborhood(&self) -> impl Iterator<Item = GridCell> + '_ {
+        (-1..=1).flat_map(move |dx| {
+            (-1..=1).flat_map(move |dy| {
+                (-1..=1).map(move |dz| GridCell {
+                    x: self.x + dx,
+                    y: self.y + dy,
+                    z: self.z + dz,
+                })
+            })
+        })
+    }
+
+    /// True if the cells touch, sharing a face, edge or corner.
+    pub fn is_adjacent(&self, other: &GridCell) -> bool {
+        self != other
+            && (self.x - other.x).abs() <= 1
+            && (self.y - other.y).abs() <= 1
+            && (self.z - other.z).abs() <= 1
+    }
+}
+
+/// A square tile of the xy plane, see [`NodeCollection::tiles`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileCoord {
+    pub x: i64,
+    pub y: i64,
+}
+
+pub(crate) const DEFAULT_CELL_SIZE: f64 = 1.0;
+
+/// A cell, tile or grid spacing, or a tessellation tolerance, that is not
+/// a positive finite number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidSpacing(pub f64);
+
+impl InvalidSpacing {
+    /// Passes `spacing` on if it is positive and finite.
+    pub(crate) fn check(spacing: f64) -> Result<f64, InvalidSpacing> {
+        if spacing.is_finite() && spacing > 0.0 {
+            Ok(spacing)
+        } else {
+            Err(InvalidSpacing(spacing))
+        }
+    }
+}
+
+impl Display for InvalidSpacing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "spacing must be positive and finite, got {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSpacing {}
+
+/// Uuids of bounded nodes, bucketed by the cell of their bounding box center.
+#[derive(Debug)]
+pub(crate) struct SpatialHash {
+    cell_size: f64,
+    /// `None` until first queried, and after edits the hash could not follow.
+    cells: Option<Cells>,
+}
+
+#[derive(Debug, Default)]
+struct Cells {
+    by_cell: HashMap<GridCell, Vec<Uuid>>,
+    by_uuid: HashMap<Uuid, GridCell>,
+}
+
+impl Cells {
+    fn insert(&mut self, uuid: Uuid, cell: GridCell) {
+        self.remove(&uuid);
+        self.by_cell.entry(cell).or_default().push(uuid);
+        self.by_uuid.insert(uuid, cell);
+    }
+
+    fn remove(&mut self, uuid: &Uuid) {
+        let Some(cell) = self.by_uuid.remove(uuid) else {
+            return;
+        };
+        if let Some(bucket) = self.by_cell.get_mut(&cell) {
+            bucket.retain(|id| id != uuid);
+            if bucket.is_empty() {
+                self.by_cell.remove(&cell);
+            }
+        }
+    }
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self {
+            cell_size: DEFAULT_CELL_SIZE,
+            cells: None,
+        }
+    }
+}
+
+impl NodeCollection {
+    /// The edge length of the spatial hash's cells.
+    pub fn cell_size(&self) -> f64 {
+        self.spatial().cell_size
+    }
+
+    /// Sets the edge length of the spatial hash's cells, rehashing all nodes.
+    /// It should be about the distance below which nodes count as close.
+    pub fn set_cell_size(&mut self, cell_size: f64) -> Result<(), InvalidSpacing> {
+        let cell_size = InvalidSpacing::check(cell_size)?;
+        let spatial = self
+            .spatial
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        spatial.cell_size = cell_size;
+        spatial.cells = None;
+        Ok(())
+    }
+
+    /// The cell the center of the node's bounding box falls into,
+    /// `None` if the node is missing or not bounded.
+    pub fn cell_of(&self, uuid: &Uuid) -> Option<GridCell> {
+        self.with_cells(|cells| cells.by_uuid.get(uuid).copied())
+    }
+
+    /// The nodes whose center falls into `cell`.
+    pub fn nodes_in_cell(&self, cell: &GridCell) -> Vec<Uuid> {
+        self.with_cells(|cells| cells.by_cell.get(cell).cloned().unwrap_or_default())
+    }
+
+    /// The nodes in the node's own and all adjacent cells, the node itself excluded.
+    ///
+    /// Any node whose center lies within one cell size of the node's center is
+    /// among them, so close pairs can be found without comparing all nodes.
+    pub fn nearby_nodes(&self, uuid: &Uuid) -> Vec<Uuid> {
+        self.with_cells(|cells| {
+            let Some(cell) = cells.by_uuid.get(uuid) else {
+                return Vec::new();
+            };
+            cell.neighborhood()
+                .filter_map(|c| cells.by_cell.get(&c))
+                .flatten()
+                .filter(|id| *id != uuid)
+                .copied()
+                .collect()
+        })
+    }
+
+    /// Groups of bounded nodes that are [`crate::GeometryNode::semantic_eq`] to each other.
+    ///
+    /// Each node is only compared to the nodes of its own and the adjacent cells,
+    /// which also catches near duplicates that a custom equality accepts but that
+    /// fall on both sides of a cell border. A node joins the group of the first node,
+    /// in uuid order, it equals. Each group and the list of groups are sorted.
+    pub fn find_duplicates(&self) -> Vec<Vec<Uuid>> {
+        let mut ids: Vec<Uuid> = self.with_cells(|cells| cells.by_uuid.keys().copied().collect());
+        ids.sort();
+
+        let mut grouped = HashSet::new();
+        let mut groups = Vec::new();
+        for first in ids {
+            if !grouped.insert(first) {
+                continue;
+            }
+            let node = self.nodes[&first].as_ref();
+            let mut group = vec![first];
+            let mut nearby = self.nearby_nodes(&first);
+            nearby.sort();
+            for id in nearby {
+                if !grouped.contains(&id) && node.semantic_eq(self.nodes[&id].as_ref()) {
+                    grouped.insert(id);
+                    group.push(id);
+                }
+            }
+            if group.len() > 1 {
+                group.sort();
+                groups.push(group);
+            }
+        }
+        groups.sort();
+        groups
+    }
+
+    /// The bounded nodes grouped into square tiles with edges `tile_size` long
+    /// by the center of their bounding box, ignoring z, e.g. to render the
+    /// visible part of a large document first.
+    ///
+    /// Tiles are yielded in the order of their coordinates, each with its nodes sorted.
+    /// Tiles holding no nodes are skipped.
+    pub fn tiles(
+        &self,
+        tile_size: f64,
+    ) -> Result<impl Iterator<Item = (TileCoord, Vec<Uuid>)>, InvalidSpacing> {
+        let tile_size = InvalidSpacing::check(tile_size)?;
+        let mut tiles: BTreeMap<TileCoord, Vec<Uuid>> = BTreeMap::new();
+        let mut add = |uuid: Uuid, cell: GridCell| {
+            let coord = TileCoord {
+                x: cell.x,
+                y: cell.y,
+            };
+            tiles.entry(coord).or_default().push(uuid);
+        };
+
+        if tile_size == self.cell_size() {
+            self.with_cells(|cells| {
+                for (uuid, cell) in &cells.by_uuid {
+                    add(*uuid, *cell);
+                }
+            });
+        } else {
+            for (uuid, node) in &self.nodes {
+                if let Some(bounds) = node_bounds(node.as_ref()) {
+                    add(*uuid, GridCell::of(bounds.center(), tile_size));
+                }
+            }
+        }
+
+        Ok(tiles.into_iter().map(|(coord, mut uuids)| {
+            uuids.sort();
+            (coord, uuids)
+        }))
+    }
+
+    fn with_cells<R>(&self, f: impl FnOnce(&Cells) -> R) -> R {
+        let mut spatial = self.spatial();
+        let cell_size = spatial.cell_size;
+        let cells = spatial.cells.get_or_insert_with(|| {
+            let mut cells = Cells::default();
+            for (uuid, node) in &self.nodes {
+                if let Some(bounds) = node_bounds(node.as_ref()) {
+                    cells.insert(*uuid, GridCell::of(bounds.center(), cell_size));
+                }
+            }
+            cells
+        });
+        f(cells)
+    }
+
+    /// Moves a node to the cell of its new bounds, removing it if it is no longer bounded.
+    pub(crate) fn rehash(&self, uuid: Uuid, bounds: Option<BoundingBox>) {
+        let mut spatial = self.spatial();
+        let cell_size = spatial.cell_size;
+        let Some(cells) = &mut spatial.cells else {
+            return;
+        };
+        match bounds {
+            Some(bounds) => cells.insert(uuid, GridCell::of(bounds.center(), cell_size)),
+            None => cells.remove(&uuid),
+        }
+    }
+
+    pub(crate) fn invalidate_spatial(&self) {
+        self.spatial().cells = None;
+    }
+
+    fn spatial(&self) -> MutexGuard<'_, SpatialHash> {
+        self.spatial.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3};
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        let mut point = Point3::new();
+        (point.x, point.y, point.z) = (x, y, z);
+        point
+    }
+
+    /// Pushes points at the given positions, returning their uuids in order.
+    fn with_points(collection: &mut NodeCollection, positions: &[[f64; 3]]) -> Vec<Uuid> {
+        positions
+            .iter()
+            .map(|&[x, y, z]| {
+                let point = point(x, y, z);
+                let id = point.uuid();
+                collection.push(Box::new(point)).unwrap();
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nearby_points_land_in_neighboring_cells() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[0.9, 0.5, 0.0], [1.1, 0.5, 0.0]]);
+
+        let (a, b) = (collection.cell_of(&ids[0]), collection.cell_of(&ids[1]));
+        assert_eq!(a, Some(GridCell { x: 0, y: 0, z: 0 }));
+        assert_eq!(b, Some(GridCell { x: 1, y: 0, z: 0 }));
+        assert!(a.unwrap().is_adjacent(&b.unwrap()));
+        assert_eq!(collection.nodes_in_cell(&a.unwrap()), [ids[0]]);
+        assert_eq!(collection.nearby_nodes(&ids[0]), [ids[1]]);
+    }
+
+    #[test]
+    fn the_hash_follows_edits() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[0.5, 0.5, 0.5]]);
+        assert_eq!(
+            collection.cell_of(&ids[0]),
+            Some(GridCell { x: 0, y: 0, z: 0 })
+        );
+
+        collection
+            .update(&ids[0], |p: &mut Point3| p.x = -3.5)
+            .unwrap();
+        assert_eq!(
+            collection.cell_of(&ids[0]),
+            Some(GridCell { x: -4, y: 0, z: 0 })
+        );
+        assert!(collection
+            .nodes_in_cell(&GridCell { x: 0, y: 0, z: 0 })
+            .is_empty());
+
+        collection.remove(&ids[0]).unwrap();
+        assert_eq!(collection.cell_of(&ids[0]), None);
+    }
+
+    #[test]
+    fn find_duplicates_groups_equal_points() {
+        let mut collection = NodeCollection::new();
+        let mut twins = with_points(&mut collection, &[[2.0, 1.0, 0.0], [2.0, 1.0, 0.0]]);
+        with_points(&mut collection, &[[2.5, 1.0, 0.0], [9.0, 9.0, 9.0]]);
+        twins.sort();
+
+        assert_eq!(collection.find_duplicates(), [twins]);
+    }
+
+    #[test]
+    fn far_apart_nodes_land_in_different_tiles_and_a_cluster_shares_one() {
+        let mut collection = NodeCollection::new();
+        let mut cluster = with_points(
+            &mut collection,
+            &[[1.0, 1.0, 0.0], [2.0, 3.0, 5.0], [4.0, 4.0, 0.0]],
+        );
+        let far = with_points(&mut collection, &[[100.0, -50.0, 0.0]]);
+        cluster.sort();
+
+        let tiles: Vec<_> = collection.tiles(10.0).unwrap().collect();
+
+        assert_eq!(
+            tiles,
+            [
+                (TileCoord { x: 0, y: 0 }, cluster),
+                (TileCoord { x: 10, y: -5 }, far),
+            ]
+        );
+    }
+
+    #[test]
+    fn tiles_of_the_cell_size_match_the_cells() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[0.5, 0.5, 0.0], [3.5, 0.5, 7.0]]);
+
+        let tiles: Vec<_> = collection.tiles(DEFAULT_CELL_SIZE).unwrap().collect();
+
+        assert_eq!(
+            tiles,
+            [
+                (TileCoord { x: 0, y: 0 }, vec![ids[0]]),
+                (TileCoord { x: 3, y: 0 }, vec![ids[1]]),
+            ]
+        );
+        assert!(collection.tiles(0.0).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bounds::{Bounded, BoundingBox},
+    shapes::{Arc, Circle, Ellipse, Line, PointCloud, Polyline},
+    GeometryNode, NodeCollection, Point3, Rectangle,
+};
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2) + (b[2] - a[2]).powi(2)).sqrt()
+}
+
+/// The point `t` of the way from `a` to `b`.
+fn lerp(a: [f64; 3], b: [f64; 3], t: f64) -> [f64; 3] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ]
+}
+
+/// A sphere around `center`, a cheaper test than a [`BoundingBox`] for culling in 3d.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [f64; 3],
+    pub radius: f64,
+}
+
+impl BoundingSphere {
+    pub fn new(center: [f64; 3], radius: f64) -> Self {
+        Self { center, radius }
+    }
+
+    /// The sphere through the corners of `b`.
+    pub fn from_box(b: &BoundingBox) -> Self {
+        Self::new(b.center(), distance(b.min, b.max) / 2.0)
+    }
+
+    /// A sphere around all of `points` by Ritter's algorithm, usually a few
+    /// percent larger than the smallest one. `None` if there are no points.
+    pub fn from_points(points: &[[f64; 3]]) -> Option<Self> {
+        let first = *points.first()?;
+        let farthest_from = |p: [f64; 3]| {
+            points
+                .iter()
+                .copied()
+                .max_by(|a, b| distance(p, *a).total_cmp(&distance(p, *b)))
+                .unwrap_or(p)
+        };
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+
+        let mut sphere = Self::new(lerp(a, b, 0.5), distance(a, b) / 2.0);
+        for p in points {
+            sphere = sphere.enclose(&Self::new(*p, 0.0));
+        }
+        Some(sphere)
+    }
+
+    pub fn contains_point(&self, p: [f64; 3]) -> bool {
+        distance(self.center, p) <= self.radius
+    }
+
+    /// True if `other` lies inside of this sphere, touching included.
+    pub fn contains(&self, other: &BoundingSphere) -> bool {
+        distance(self.center, other.center) + other.radius <= self.radius
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool {
+        distance(self.center, other.center) <= self.radius + other.radius
+    }
+
+    /// The smallest sphere around this one and `other`.
+    pub fn enclose(&self, other: &BoundingSphere) -> Self {
+        if self.contains(other) {
+            return *self;
+        }
+        if other.contains(self) {
+            return *other;
+        }
+
+        let d = distance(self.center, other.center);
+        let radius = (d + self.radius + other.radius) / 2.0;
+        // move from this center towards the other, just enough to reach its far side
+        let center = lerp(self.center, other.center, (radius - self.radius) / d);
+        // guard against rounding leaving either sphere poking out
+        let radius = radius
+            .max(distance(center, self.center) + self.radius)
+            .max(distance(center, other.center) + other.radius);
+        Self::new(center, radius)
+    }
+}
+
+/// Geometry that fits inside of a sphere.
+pub trait BoundedSphere {
+    fn bounding_sphere(&self) -> BoundingSphere;
+}
+
+impl BoundedSphere for Point3 {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::new([self.x, self.y, self.z], 0.0)
+    }
+}
+
+impl BoundedSphere for Rectangle {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_box(&self.bounding_box())
+    }
+}
+
+impl BoundedSphere for Circle {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        let c = self.center;
+        BoundingSphere::new([c.x, c.y, c.z], self.radius.abs())
+    }
+}
+
+/// The sphere around the arc's bounding box, smaller than its circle's for short arcs.
+impl BoundedSphere for Arc {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_box(&self.bounding_box())
+    }
+}
+
+impl BoundedSphere for Ellipse {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        let c = self.center;
+        let radius = self.radius_x.abs().max(self.radius_y.abs());
+        BoundingSphere::new([c.x, c.y, c.z], radius)
+    }
+}
+
+impl BoundedSphere for Line {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        let (a, b) = (self.start, self.end);
+        BoundingSphere::from_points(&[[a.x, a.y, a.z], [b.x, b.y, b.z]])
+            .expect("a line has end points")
+    }
+}
+
+/// An empty cloud is bounded by the origin. The collection never asks
+/// for it, as [`GeometryNode::as_bounded_sphere`] skips empty clouds.
+impl BoundedSphere for PointCloud {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(&self.points).unwrap_or(BoundingSphere::new([0.0; 3], 0.0))
+    }
+}
+
+/// An empty polyline is bounded by the origin, like an empty [`PointCloud`].
+impl BoundedSphere for Polyline {
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(&self.points).unwrap_or(BoundingSphere::new([0.0; 3], 0.0))
+    }
+}
+
+pub(crate) fn node_sphere(node: &dyn GeometryNode) -> Option<BoundingSphere> {
+    node.as_bounded_sphere().map(|b| b.bounding_sphere())
+}
+
+impl NodeCollection {
+    /// A sphere around all nodes that have one, grown node by node in uuid
+    /// order, so it is not the smallest one but reproducible.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        let mut ids: Vec<_> = self.nodes.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|id| node_sphere(self.nodes[id].as_ref()))
+            .reduce(|acc, s| acc.enclose(&s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        let mut point = Point3::new();
+        (point.x, point.y, point.z) = (x, y, z);
+        point
+    }
+
+    #[test]
+    fn the_sphere_of_spread_out_points_contains_all_of_them() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [10.0, 0.0, 0.0],
+            [0.0, -7.0, 3.0],
+            [4.0, 4.0, -9.0],
+            [-6.0, 2.0, 5.0],
+            [3.0, 8.0, 8.0],
+        ];
+        let mut collection = NodeCollection::new();
+        for [x, y, z] in positions {
+            collection.push(Box::new(point(x, y, z))).unwrap();
+        }
+
+        let sphere = collection.bounding_sphere().unwrap();
+
+        for p in positions {
+            assert!(
+                sphere.contains_point(p),
+                "{:?} lies outside of {:?}",
+                p,
+                sphere
+            );
+        }
+        // no larger than the sphere around the bounding box
+        let bounds = BoundingSphere::from_box(&collection.bounds().unwrap());
+        assert!(sphere.radius <= bounds.radius);
+    }
+
+    #[test]
+    fn an_empty_collection_has_no_sphere() {
+        assert_eq!(NodeCollection::new().bounding_sphere(), None);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    bounds::{node_bounds, BoundingBox},
+    NodeCollection, Point3,
+};
+
+/// An overview of a collection, e.g. for a dashboard.
+#[derive(Clone, Debug)]
+pub struct CollectionStats {
+    /// Number of nodes per type tag.
+    pub counts: BTreeMap<&'static str, usize>,
+    pub total: usize,
+    pub bounds: Option<BoundingBox>,
+    pub centroid: Option<Point3>,
+    pub total_area: f64,
+    pub total_perimeter: f64,
+}
+
+impl Display for CollectionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} nodes", self.total)?;
+        for (tag, count) in &self.counts {
+            writeln!(f, "  {}: {}", tag, count)?;
+        }
+        match &self.bounds {
+            Some(b) => writeln!(f, "bounds: {:?} to {:?}", b.min, b.max)?,
+            None => writeln!(f, "bounds: none")?,
+        }
+        match &self.centroid {
+            Some(c) => writeln!(f, "centroid: [{}, {}, {}]", c.x, c.y, c.z)?,
+            None => writeln!(f, "centroid: none")?,
+        }
+        writeln!(f, "total area: {}", self.total_area)?;
+        write!(f, "total perimeter: {}", self.total_perimeter)
+    }
+}
+
+impl NodeCollection {
+    /// The average of the bounding box centers of all bounded nodes.
+    pub fn centroid(&self) -> Option<Point3> {
+        let centers: Vec<[f64; 3]> = self
+            .nodes
+            .values()
+            .filter_map(|n| node_bounds(n.as_ref()))
+            .map(|b| b.center())
+            .collect();
+        if centers.is_empty() {
+            return None;
+        }
+
+        let n = centers.len() as f64;
+        let mut centroid = Point3::from_factory(self.factory());
+        centroid.x = centers.iter().map(|c| c[0]).sum::<f64>() / n;
+        centroid.y = centers.iter().map(|c| c[1]).sum::<f64>() / n;
+        centroid.z = centers.iter().map(|c| c[2]).sum::<f64>() / n;
+        Some(centroid)
+    }
+
+    /// The centers of all measurable nodes, weighted by their area,
+    /// i.e. the center of mass of the filled shapes.
+    ///
+    /// Returns `None` if the measurable nodes have no area in total.
+    pub fn area_centroid(&self) -> Option<Point3> {
+        let weighted: Vec<([f64; 3], f64)> = self
+            .nodes
+            .values()
+            .filter_map(|n| Some((node_bounds(n.as_ref())?, n.as_measurable()?.area())))
+            .map(|(b, area)| (b.center(), area))
+            .collect();
+        let total: f64 = weighted.iter().map(|(_, area)| area).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let axis = |i: usize| weighted.iter().map(|(c, area)| c[i] * area).sum::<f64>() / total;
+        let mut centroid = Point3::from_factory(self.factory());
+        centroid.x = axis(0);
+        centroid.y = axis(1);
+        centroid.z = axis(2);
+        Some(centroid)
+    }
+
+    /// Node counts and aggregate measurements of the collection.
+    pub fn stats(&self) -> CollectionStats {
+        let mut counts = BTreeMap::new();
+        for node in self.nodes.values() {
+            *counts.entry(node.type_tag()).or_default() += 1;
+        }
+
+        CollectionStats {
+            counts,
+            total: self.nodes.len(),
+            bounds: self.bounds(),
+            centroid: self.centroid(),
+            total_area: self.total_area(),
+            total_perimeter: self.total_perimeter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{shapes::Circle, Rectangle};
+
+    #[test]
+    fn stats_count_nodes_per_type() {
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(Point3::new())).unwrap();
+        collection.push(Box::new(Point3::new())).unwrap();
+        collection.push(Box::new(Rectangle::new())).unwrap();
+        collection.push(Box::new(Circle::new())).unwrap();
+
+        let stats = collection.stats();
+
+        assert_eq!(
+            stats.counts,
+            BTreeMap::from([("Circle", 1), ("Point3", 2), ("Rectangle", 1)])
+        );
+        assert_eq!(stats.total, 4);
+    }
+
+    #[test]
+    fn a_large_rectangle_dominates_the_area_centroid() {
+        let rectangle = |x: f64, size: f64| {
+            let mut rectangle = Rectangle::new();
+            *rectangle.width_mut() = size;
+            *rectangle.height_mut() = size;
+            (rectangle.anchor_mut().x, rectangle.anchor_mut().y) = (x - size / 2.0, -size / 2.0);
+            rectangle
+        };
+        let mut collection = NodeCollection::new();
+        // a 9x9 rectangle centered on x = 0, and a 1x1 one centered on x = 82
+        collection.push(Box::new(rectangle(0.0, 9.0))).unwrap();
+        collection.push(Box::new(rectangle(82.0, 1.0))).unwrap();
+        // points are not measurable and do not pull the centroid
+        let mut point = Point3::new();
+        point.x = 1000.0;
+        collection.push(Box::new(point)).unwrap();
+
+        let centroid = collection.area_centroid().unwrap();
+
+        assert_eq!((centroid.x, centroid.y), (1.0, 0.0));
+        assert!(collection.centroid().unwrap().x > 300.0);
+    }
+}
@@ -0,0 +1,198 @@
+use uuid::Uuid;
+
+use crate::{
+    diff::{Change, ChangePath, Diff},
+    domain::prefer_domain_changes,
+    error::GeodiffError,
+    NodeCollection,
+};
+
+/// A way of computing the [`Diff`] between two collections.
+pub trait DiffStrategy {
+    /// Fails like [`NodeCollection::diff`] on NaN or infinite floats.
+    fn diff(&self, before: &NodeCollection, after: &NodeCollection) -> Result<Diff, GeodiffError>;
+}
+
+/// Diffs the collections as two json trees, except for the nodes of
+/// [`DiffAware`](crate::domain::DiffAware) types, which diff themselves.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonTreeDiff;
+
+impl DiffStrategy for JsonTreeDiff {
+    fn diff(&self, before: &NodeCollection, after: &NodeCollection) -> Result<Diff, GeodiffError> {
+        let before_value = before.to_value()?;
+        let after_value = after.to_value()?;
+        let changes = Change::record(&ChangePath::default(), &before_value, &after_value);
+        let mut diff = Diff::new(prefer_domain_changes(changes, before, after));
+        diff.record_node_types(before, after);
+        Ok(diff)
+    }
+}
+
+/// Diffs the collections node by node, see [`NodeCollection::diff_streaming`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeLevelDiff;
+
+impl DiffStrategy for NodeLevelDiff {
+    fn diff(&self, before: &NodeCollection, after: &NodeCollection) -> Result<Diff, GeodiffError> {
+        before.diff_streaming(after)
+    }
+}
+
+impl NodeCollection {
+    /// Diffs `self` and `other` with the default strategy, [`JsonTreeDiff`].
+    ///
+    /// Fails instead of diffing NaN or infinite floats as `null`,
+    /// see [`NodeCollection::check_finite`].
+    pub fn diff(&self, other: &NodeCollection) -> Result<Diff, GeodiffError> {
+        self.diff_with(other, &JsonTreeDiff)
+    }
+
+    /// Like [`NodeCollection::diff`], but records no more than `max_changes` changes,
+    /// a bounded cost check of whether two collections are wildly different.
+    ///
+    /// The nodes are diffed one by one in uuid order, like in
+    /// [`NodeCollection::diff_streaming`], followed by the bookkeeping.
+    /// Diffing stops at the first node whose changes do not fit under the cap,
+    /// so the nodes after it are neither diffed nor checked for NaN or infinite
+    /// floats. The flag is true if the cap was hit and further changes were dropped.
+    pub fn diff_capped(
+        &self,
+        other: &NodeCollection,
+        max_changes: usize,
+    ) -> Result<(Diff, bool), GeodiffError> {
+        let mut uuids: Vec<&Uuid> = (self.nodes.keys())
+            .chain(other.nodes.keys().filter(|id| !self.nodes.contains_key(id)))
+            .collect();
+        uuids.sort();
+
+        let mut changes = Vec::new();
+        let mut overflowed = false;
+        for uuid in uuids {
+            let node_changes = self.node_changes(other, uuid)?;
+            let room = max_changes - changes.len();
+            if node_changes.len() > room {
+                changes.extend(node_changes.into_iter().take(room));
+                overflowed = true;
+                break;
+            }
+            changes.extend(node_changes);
+        }
+        if !overflowed {
+            let (bookkeeping, bookkeeping_overflowed) = Change::record_capped(
+                &ChangePath::default(),
+                &serde_json::to_value(&self.bookkeeping).expect("bookkeeping serializes to json"),
+                &serde_json::to_value(&other.bookkeeping).expect("bookkeeping serializes to json"),
+                max_changes - changes.len(),
+            );
+            changes.extend(bookkeeping);
+            overflowed = bookkeeping_overflowed;
+        }
+
+        let mut diff = Diff::new(changes);
+        diff.record_node_types(self, other);
+        Ok((diff.sorted(), overflowed))
+    }
+
+    /// Diffs `self` and `other` with `strategy`, see [`Diff::sorted`] for the order of the changes.
+    pub fn diff_with(
+        &self,
+        other: &NodeCollection,
+        strategy: &dyn DiffStrategy,
+    ) -> Result<Diff, GeodiffError> {
+        Ok(strategy.diff(self, other)?.sorted())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        shapes::{Circle, Line},
+        GeometryNode, Point3, Rectangle,
+    };
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        let mut point = Point3::new();
+        (point.x, point.y, point.z) = (x, y, z);
+        point
+    }
+
+    /// A collection of a point, a rectangle and a circle, and an edited copy
+    /// with the rectangle modified and renamed, the point removed and a line added.
+    fn edited_pair() -> (NodeCollection, NodeCollection) {
+        let mut before = NodeCollection::new();
+        let point = point(1.0, 2.0, 3.0);
+        let rectangle = Rectangle::new();
+        let (point_id, rectangle_id) = (point.uuid(), rectangle.uuid());
+        before.push(Box::new(point)).unwrap();
+        before.push(Box::new(rectangle)).unwrap();
+        before.push(Box::new(Circle::new())).unwrap();
+        before.set_name(&rectangle_id, "door").unwrap();
+
+        let mut after = before.snapshot();
+        after
+            .update(&rectangle_id, |r: &mut Rectangle| {
+                *r.width_mut() = 2.5;
+                r.anchor_mut().y = -1.0;
+            })
+            .unwrap();
+        after.set_name(&rectangle_id, "window").unwrap();
+        after.remove(&point_id).unwrap();
+        after.push(Box::new(Line::new())).unwrap();
+        (before, after)
+    }
+
+    #[test]
+    fn json_tree_and_node_level_diffs_are_equivalent() {
+        let (before, after) = edited_pair();
+
+        let tree = before.diff_with(&after, &JsonTreeDiff).unwrap();
+        let nodes = before.diff_with(&after, &NodeLevelDiff).unwrap();
+
+        assert!(!tree.changes().is_empty());
+        assert_eq!(tree, nodes);
+    }
+
+    #[test]
+    fn diffing_the_same_pair_twice_gives_byte_identical_diffs() {
+        let (before, after) = edited_pair();
+
+        let first = serde_json::to_vec(&before.diff(&after).unwrap()).unwrap();
+        let second = serde_json::to_vec(&before.diff(&after).unwrap()).unwrap();
+        let streamed =
+            serde_json::to_vec(&before.diff_with(&after, &NodeLevelDiff).unwrap()).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first, streamed);
+    }
+
+    #[test]
+    fn disjoint_collections_stop_at_the_cap() {
+        let disjoint = || {
+            let mut collection = NodeCollection::new();
+            for i in 0..1000 {
+                collection
+                    .push(Box::new(point(i as f64, 0.0, 0.0)))
+                    .unwrap();
+            }
+            collection
+        };
+        let (before, after) = (disjoint(), disjoint());
+
+        let (diff, overflowed) = before.diff_capped(&after, 10).unwrap();
+
+        assert_eq!(diff.changes().len(), 10);
+        assert!(overflowed);
+    }
+
+    #[test]
+    fn a_diff_under_the_cap_is_complete() {
+        let (before, after) = edited_pair();
+
+        let (diff, overflowed) = before.diff_capped(&after, 1000).unwrap();
+
+        assert!(!overflowed);
+        assert_eq!(diff, before.diff(&after).unwrap());
+    }
+}
@@ -0,0 +1,78 @@
+use uuid::Uuid;
+
+use crate::{
+    diff::{node_value, Change, ChangePath, Diff},
+    domain::diff_nodes,
+    error::GeodiffError,
+    finite::check_node_finite,
+    hash::content_hash,
+    NodeCollection,
+};
+
+impl NodeCollection {
+    /// Diffs `self` and `other` node by node instead of as one json tree.
+    ///
+    /// Nodes with equal content hashes on both sides are skipped without
+    /// being diffed, which makes this much cheaper than [`NodeCollection::diff`]
+    /// when only a few nodes changed. So is equal bookkeeping, like names and
+    /// layers. The changes found are the same.
+    ///
+    /// Fails like [`NodeCollection::diff`] on NaN or infinite floats.
+    pub fn diff_streaming(&self, other: &NodeCollection) -> Result<Diff, GeodiffError> {
+        let mut changes = Vec::new();
+        for uuid in self.nodes.keys() {
+            changes.extend(self.node_changes(other, uuid)?);
+        }
+        for uuid in other.nodes.keys() {
+            if !self.nodes.contains_key(uuid) {
+                changes.extend(self.node_changes(other, uuid)?);
+            }
+        }
+
+        if self.bookkeeping != other.bookkeeping {
+            changes.extend(Change::record(
+                &ChangePath::default(),
+                &serde_json::to_value(&self.bookkeeping).expect("bookkeeping serializes to json"),
+                &serde_json::to_value(&other.bookkeeping).expect("bookkeeping serializes to json"),
+            ));
+        }
+
+        let mut diff = Diff::new(changes);
+        diff.record_node_types(self, other);
+        Ok(diff)
+    }
+
+    /// The changes of a single node from `self` to `other`, empty if the content hashes
+    /// of both sides match. Fails if the node holds NaN or infinite floats on either side.
+    pub(crate) fn node_changes(
+        &self,
+        other: &NodeCollection,
+        uuid: &Uuid,
+    ) -> Result<Vec<Change>, GeodiffError> {
+        let root = ChangePath::node_root(uuid);
+        Ok(match (self.nodes.get(uuid), other.nodes.get(uuid)) {
+            (None, None) => Vec::new(),
+            (Some(before), None) => {
+                check_node_finite(before.as_ref())?;
+                vec![Change::Removed {
+                    path: root,
+                    value: node_value(before.as_ref()),
+                }]
+            }
+            (None, Some(after)) => {
+                check_node_finite(after.as_ref())?;
+                vec![Change::Added {
+                    path: root,
+                    value: node_value(after.as_ref()),
+                }]
+            }
+            (Some(before), Some(after)) => {
+                if content_hash(before.as_ref())? == content_hash(after.as_ref())? {
+                    Vec::new()
+                } else {
+                    diff_nodes(before.as_ref(), after.as_ref())
+                }
+            }
+        })
+    }
+}
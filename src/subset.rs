@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    bounds::BoundingBox, diff::node_value, error::GeodiffError, finite::check_node_finite,
+    GeometryNode, NodeCollection,
+};
+
+/// Selects the nodes to take into a subset of a collection.
+#[derive(Clone, Debug)]
+pub enum SubsetFilter {
+    /// All nodes on the named layer.
+    Layer(String),
+    /// All nodes whose bounding box intersects the region.
+    Region(BoundingBox),
+    /// The listed nodes, ignoring uuids the collection does not hold.
+    Ids(Vec<Uuid>),
+}
+
+impl NodeCollection {
+    /// Copies the nodes matching `filter` into a new collection.
+    ///
+    /// Nodes the matches depend on are copied as well, transitively,
+    /// so the subset holds no dangling references.
+    pub fn export_subset(&self, filter: SubsetFilter) -> NodeCollection {
+        let matches = match filter {
+            SubsetFilter::Layer(layer) => self.nodes_on_layer(&layer),
+            SubsetFilter::Region(region) => self.query_region(&region),
+            SubsetFilter::Ids(ids) => ids,
+        };
+
+        let mut subset = NodeCollection::new();
+        let mut visited = HashSet::new();
+        let mut pending = matches;
+        while let Some(id) = pending.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&id) else {
+                continue;
+            };
+
+            pending.extend(node.dependencies());
+            subset.insert_node(node.clone_box());
+            subset.copy_bookkeeping(self, &id, id);
+        }
+
+        subset
+    }
+
+    /// Copies the listed nodes into a new collection under fresh uuids,
+    /// e.g. to paste them without overwriting the originals.
+    ///
+    /// Every uuid of a listed node is rewritten where the copies hold or refer to it,
+    /// including the uuids of embedded points like a rectangle's anchor, so
+    /// references among the copies stay intact. References to nodes not listed
+    /// keep their old uuid and dangle until repaired. Names, layers and z indices
+    /// move to the new uuids. Returns the copies and the map from old to new uuids.
+    /// Uuids the collection does not hold are ignored.
+    ///
+    /// Only the uuids of the nodes themselves and their references, see
+    /// [`GeometryNode::dependencies`], are rewritten, extra fields are copied as they are.
+    /// Fails on nodes holding NaN or infinite floats, see [`NodeCollection::check_finite`].
+    pub fn clone_with_new_ids(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<(NodeCollection, HashMap<Uuid, Uuid>), GeodiffError> {
+        let mut remap = HashMap::new();
+        let mut order = Vec::new();
+        for id in ids {
+            if self.nodes.contains_key(id) && !remap.contains_key(id) {
+                remap.insert(*id, self.new_id());
+                order.push(*id);
+            }
+        }
+
+        let mut copies = NodeCollection::new();
+        for old in order {
+            let new = remap[&old];
+            let node = self.nodes[&old].as_ref();
+            check_node_finite(node)?;
+            let mut value = node_value(node);
+            remap_node_uuids(node, &mut value, &remap);
+            copies.insert_node(serde_json::from_value(value)?);
+            copies.copy_bookkeeping(self, &old, new);
+        }
+
+        Ok((copies, remap))
+    }
+}
+
+/// Replaces the uuids `remap` maps in `value`, the serialized `node`, where they are
+/// the node's own uuid or one of its references, leaving its extra fields alone.
+fn remap_node_uuids(node: &dyn GeometryNode, value: &mut Value, remap: &HashMap<Uuid, Uuid>) {
+    let mut own: HashSet<Uuid> = node.dependencies().into_iter().collect();
+    own.insert(node.uuid());
+    let extra: HashSet<&String> = node.extra().map(|e| e.keys().collect()).unwrap_or_default();
+
+    let Value::Object(fields) = value else {
+        return;
+    };
+    for (name, field) in fields.iter_mut() {
+        if !extra.contains(name) {
+            remap_strings(field, &own, remap);
+        }
+    }
+}
+
+/// Replaces every string below `value` that is one of the `own` uuids in `remap` by its new uuid.
+fn remap_strings(value: &mut Value, own: &HashSet<Uuid>, remap: &HashMap<Uuid, Uuid>) {
+    match value {
+        Value::String(s) => {
+            let new = Uuid::parse_str(s)
+                .ok()
+                .filter(|id| own.contains(id))
+                .and_then(|id| remap.get(&id));
+            if let Some(new) = new {
+                *s = new.to_string();
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| remap_strings(v, own, remap)),
+        Value::Object(map) => map.values_mut().for_each(|v| remap_strings(v, own, remap)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block::Block, Point3, Rectangle};
+
+    struct Fixture {
+        collection: NodeCollection,
+        rectangle: Uuid,
+        anchor: Uuid,
+        point: Uuid,
+        block: Uuid,
+    }
+
+    /// A rectangle with its anchor on layer `walls`, and far from it a point
+    /// held by a block.
+    fn fixture() -> Fixture {
+        let mut collection = NodeCollection::new();
+
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 2.0;
+        *rectangle.height_mut() = 2.0;
+        let (rectangle_id, anchor_id) = (rectangle.uuid(), rectangle.dependencies()[0]);
+        collection
+            .push(rectangle.embedded_dependency(&anchor_id).unwrap())
+            .unwrap();
+        collection.push(Box::new(rectangle)).unwrap();
+        collection.set_layer(&rectangle_id, "walls").unwrap();
+
+        let mut point = Point3::new();
+        (point.x, point.y) = (100.0, 100.0);
+        let point_id = point.uuid();
+        collection.push(Box::new(point)).unwrap();
+        let mut block = Block::new();
+        block.members_mut().push(point_id);
+        let block_id = block.uuid();
+        collection.push(Box::new(block)).unwrap();
+
+        Fixture {
+            collection,
+            rectangle: rectangle_id,
+            anchor: anchor_id,
+            point: point_id,
+            block: block_id,
+        }
+    }
+
+    fn ids(collection: &NodeCollection) -> HashSet<Uuid> {
+        collection.iter().map(|n| n.uuid()).collect()
+    }
+
+    #[test]
+    fn layer_subset_pulls_in_the_anchor() {
+        let f = fixture();
+        let subset = f
+            .collection
+            .export_subset(SubsetFilter::Layer("walls".to_string()));
+        assert_eq!(ids(&subset), HashSet::from([f.rectangle, f.anchor]));
+        assert_eq!(subset.layer(&f.rectangle), Some("walls"));
+    }
+
+    #[test]
+    fn region_subset_takes_the_nodes_inside() {
+        let f = fixture();
+        let region = BoundingBox::new([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]);
+        let subset = f.collection.export_subset(SubsetFilter::Region(region));
+        assert_eq!(ids(&subset), HashSet::from([f.rectangle, f.anchor]));
+    }
+
+    #[test]
+    fn id_subset_pulls_in_block_members() {
+        let f = fixture();
+        let subset = f
+            .collection
+            .export_subset(SubsetFilter::Ids(vec![f.block, Uuid::new_v4()]));
+        assert_eq!(ids(&subset), HashSet::from([f.block, f.point]));
+    }
+
+    #[test]
+    fn a_rectangle_and_its_anchor_are_remapped_together() {
+        let f = fixture();
+
+        let (copies, remap) = f
+            .collection
+            .clone_with_new_ids(&[f.rectangle, f.anchor])
+            .unwrap();
+
+        let (rectangle, anchor) = (remap[&f.rectangle], remap[&f.anchor]);
+        assert_eq!(remap.len(), 2);
+        assert!(rectangle != f.rectangle && anchor != f.anchor);
+        assert_eq!(ids(&copies), HashSet::from([rectangle, anchor]));
+        let copy = copies.try_get_typed::<Rectangle>(&rectangle).unwrap();
+        assert_eq!(copy.dependencies(), [anchor]);
+        assert_eq!(copy.anchor.uuid, anchor);
+        assert_eq!(copies.layer(&rectangle), Some("walls"));
+    }
+
+    #[test]
+    fn references_to_nodes_left_out_keep_their_uuids() {
+        let f = fixture();
+
+        let (copies, remap) = f.collection.clone_with_new_ids(&[f.block]).unwrap();
+
+        let block = copies.try_get_typed::<Block>(&remap[&f.block]).unwrap();
+        assert_eq!(block.members(), [f.point]);
+    }
+}
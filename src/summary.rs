@@ -0,0 +1,62 @@
+use uuid::Uuid;
+
+use crate::NodeCollection;
+
+/// What a list of nodes needs to show for one node, without touching the node's geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeSummary {
+    pub uuid: Uuid,
+    pub type_tag: &'static str,
+    pub name: Option<String>,
+    pub layer: Option<String>,
+}
+
+impl NodeCollection {
+    /// A summary line for every node, e.g. to fill a tree view.
+    pub fn summaries(&self) -> impl Iterator<Item = NodeSummary> + '_ {
+        self.nodes.iter().map(|(uuid, node)| NodeSummary {
+            uuid: *uuid,
+            type_tag: node.type_tag(),
+            name: self.name(uuid).map(str::to_string),
+            layer: self.layer(uuid).map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3, Rectangle};
+
+    #[test]
+    fn summaries_carry_the_type_name_and_layer_of_each_node() {
+        let mut collection = NodeCollection::new();
+        let (point, rectangle) = (Point3::new(), Rectangle::new());
+        let (p, r) = (point.uuid(), rectangle.uuid());
+        collection.push(Box::new(point)).unwrap();
+        collection.push(Box::new(rectangle)).unwrap();
+        collection.set_name(&r, "door").unwrap();
+        collection.set_layer(&r, "walls").unwrap();
+
+        let mut summaries: Vec<NodeSummary> = collection.summaries().collect();
+        summaries.sort_by_key(|s| s.type_tag);
+
+        assert_eq!(
+            summaries,
+            [
+                NodeSummary {
+                    uuid: p,
+                    type_tag: "Point3",
+                    name: None,
+                    layer: None,
+                },
+                NodeSummary {
+                    uuid: r,
+                    type_tag: "Rectangle",
+                    name: Some("door".to_string()),
+                    layer: Some("walls".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::f64::consts::{PI, TAU};
+
+use uuid::Uuid;
+
+use crate::{
+    extra::ExtraFields,
+    shapes::{Arc, Circle, Ellipse, Line, Polyline},
+    spatial::InvalidSpacing,
+    NodeCollection, Rectangle,
+};
+
+/// Geometry that can be approximated by straight line segments.
+pub trait Tessellate {
+    /// A polyline within `tolerance` of the geometry, keeping its uuid.
+    /// Smaller tolerances give more points.
+    ///
+    /// Fails with [`InvalidSpacing`] if `tolerance` is not positive and finite.
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing>;
+}
+
+/// The number of segments needed to keep the chords of a circular arc
+/// within `tolerance` of it, at least `min`.
+fn segment_count(
+    radius: f64,
+    sweep: f64,
+    tolerance: f64,
+    min: usize,
+) -> Result<usize, InvalidSpacing> {
+    let tolerance = InvalidSpacing::check(tolerance)?;
+    let radius = radius.abs();
+    // the sagitta of a chord spanning `step` is `radius * (1 - cos(step / 2))`
+    let step = if tolerance >= radius {
+        PI
+    } else {
+        2.0 * (1.0 - tolerance / radius).acos()
+    };
+    Ok(((sweep.abs() / step).ceil() as usize).max(min))
+}
+
+/// A polyline through `point_at(0.0)` up to `point_at(n - 1)`.
+fn sample(uuid: Uuid, n: usize, closed: bool, point_at: impl Fn(f64) -> [f64; 3]) -> Polyline {
+    Polyline {
+        points: (0..n).map(|i| point_at(i as f64)).collect(),
+        closed,
+        uuid,
+        extra: ExtraFields::new(),
+    }
+}
+
+impl Tessellate for Circle {
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing> {
+        let n = segment_count(self.radius, TAU, tolerance, 3)?;
+        let (c, r) = (self.center, self.radius);
+        Ok(sample(self.uuid, n, true, |i| {
+            let angle = TAU * i / n as f64;
+            [c.x + r * angle.cos(), c.y + r * angle.sin(), c.z]
+        }))
+    }
+}
+
+impl Tessellate for Arc {
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing> {
+        let sweep = self.sweep_angle.clamp(-TAU, TAU);
+        let n = segment_count(self.radius, sweep, tolerance, 1)?;
+        Ok(sample(self.uuid, n + 1, false, |i| {
+            self.point_at(self.start_angle + sweep * i / n as f64)
+        }))
+    }
+}
+
+/// The segment count is that of a circle with the larger radius,
+/// which bounds the chord error of the ellipse's parametric points.
+impl Tessellate for Ellipse {
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing> {
+        let radius = self.radius_x.abs().max(self.radius_y.abs());
+        let n = segment_count(radius, TAU, tolerance, 3)?;
+        let c = self.center;
+        Ok(sample(self.uuid, n, true, |i| {
+            let t = TAU * i / n as f64;
+            [
+                c.x + self.radius_x * t.cos(),
+                c.y + self.radius_y * t.sin(),
+                c.z,
+            ]
+        }))
+    }
+}
+
+/// Rectangles are exact polylines, so the tolerance is only checked.
+impl Tessellate for Rectangle {
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing> {
+        InvalidSpacing::check(tolerance)?;
+        let c = self.corner();
+        let (w, h) = (self.width, self.height);
+        Ok(Polyline {
+            points: vec![
+                [c.x, c.y, c.z],
+                [c.x + w, c.y, c.z],
+                [c.x + w, c.y + h, c.z],
+                [c.x, c.y + h, c.z],
+            ],
+            closed: true,
+            uuid: self.uuid,
+            extra: ExtraFields::new(),
+        })
+    }
+}
+
+impl Tessellate for Line {
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing> {
+        InvalidSpacing::check(tolerance)?;
+        let (a, b) = (self.start, self.end);
+        Ok(Polyline {
+            points: vec![[a.x, a.y, a.z], [b.x, b.y, b.z]],
+            closed: false,
+            uuid: self.uuid,
+            extra: ExtraFields::new(),
+        })
+    }
+}
+
+impl Tessellate for Polyline {
+    fn tessellate(&self, tolerance: f64) -> Result<Polyline, InvalidSpacing> {
+        InvalidSpacing::check(tolerance)?;
+        Ok(self.clone())
+    }
+}
+
+impl NodeCollection {
+    /// A new collection holding a polyline for every node that can be tessellated,
+    /// under the node's uuid and with its name, layer and extra fields.
+    ///
+    /// Fails with [`InvalidSpacing`] if `tolerance` is not positive and finite,
+    /// even if no node can be tessellated.
+    pub fn tessellate_all(&self, tolerance: f64) -> Result<NodeCollection, InvalidSpacing> {
+        let tolerance = InvalidSpacing::check(tolerance)?;
+        let mut tessellated = NodeCollection::with_capacity(self.nodes.len());
+        for (id, source) in &self.nodes {
+            let Some(node) = source.as_tessellate() else {
+                continue;
+            };
+
+            let mut polyline = node.tessellate(tolerance)?;
+            polyline.extra = source.extra().cloned().unwrap_or_default();
+            tessellated.insert_node(Box::new(polyline));
+            if let Some(name) = self.name(id) {
+                tessellated.bookkeeping.names.insert(*id, name.to_string());
+            }
+            if let Some(layer) = self.layer(id) {
+                tessellated
+                    .bookkeeping
+                    .layers
+                    .insert(*id, layer.to_string());
+            }
+        }
+        Ok(tessellated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Point3};
+
+    fn circle(radius: f64) -> Circle {
+        let mut circle = Circle::new();
+        *circle.radius_mut() = radius;
+        circle
+    }
+
+    /// The largest distance between the circle and the midpoints of the polyline's chords.
+    fn chord_error(circle: &Circle, polyline: &Polyline) -> f64 {
+        let points = &polyline.points;
+        (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                let mid = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+                circle.radius - mid[0].hypot(mid[1])
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn finer_tolerances_give_more_points() {
+        let circle = circle(10.0);
+
+        let coarse = circle.tessellate(1.0).unwrap();
+        let fine = circle.tessellate(0.01).unwrap();
+
+        assert!(coarse.points.len() < fine.points.len());
+        assert!(coarse.is_closed() && fine.is_closed());
+        assert!(chord_error(&circle, &coarse) <= 1.0);
+        assert!(chord_error(&circle, &fine) <= 0.01);
+    }
+
+    #[test]
+    fn tessellate_all_keeps_uuids_and_names_and_skips_points() {
+        let mut collection = NodeCollection::new();
+        let circle = circle(2.0);
+        let id = circle.uuid();
+        collection.push(Box::new(circle)).unwrap();
+        collection.push(Box::new(Point3::new())).unwrap();
+        collection.set_name(&id, "wheel").unwrap();
+
+        let tessellated = collection.tessellate_all(0.1).unwrap();
+
+        assert_eq!(tessellated.len(), 1);
+        assert!(tessellated.try_get_typed::<Polyline>(&id).is_some());
+        assert_eq!(tessellated.name(&id), Some("wheel"));
+    }
+
+    #[test]
+    fn non_positive_or_non_finite_tolerances_fail() {
+        let circle = circle(2.0);
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(circle.clone())).unwrap();
+
+        for tolerance in [0.0, -1.0, f64::INFINITY] {
+            assert_eq!(
+                circle.tessellate(tolerance).err(),
+                Some(InvalidSpacing(tolerance))
+            );
+            assert!(matches!(
+                collection.tessellate_all(tolerance),
+                Err(InvalidSpacing(t)) if t == tolerance
+            ));
+        }
+        assert!(matches!(
+            Rectangle::new().tessellate(f64::NAN),
+            Err(InvalidSpacing(t)) if t.is_nan()
+        ));
+    }
+}
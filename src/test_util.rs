@@ -0,0 +1,104 @@
+//! Assertions for testing code built on geodiff, which explain a failure
+//! with the diff of the collections instead of their json.
+
+use crate::{
+    diff::{Change, Diff},
+    NodeCollection,
+};
+
+/// Panics with the [`Diff::to_unified_text`] of the collections unless they are equal,
+/// that is unless their diff is empty. Also panics if they do not diff at all.
+#[track_caller]
+pub fn assert_collections_eq(a: &NodeCollection, b: &NodeCollection) {
+    let diff = match a.diff(b) {
+        Ok(diff) => diff,
+        Err(e) => panic!("collections do not diff: {}", e),
+    };
+    if !diff.is_empty() {
+        panic!(
+            "collections differ in {} changes:\n{}",
+            diff.len(),
+            diff.to_unified_text()
+        );
+    }
+}
+
+/// Panics with the [`Diff::to_unified_text`] of `diff` unless it holds `expected`.
+#[track_caller]
+pub fn assert_diff_contains(diff: &Diff, expected: &Change) {
+    if !diff.changes().contains(expected) {
+        let expected = Diff::new(vec![expected.clone()]);
+        panic!(
+            "diff is missing the change\n{}\nit holds\n{}",
+            expected.to_unified_text(),
+            diff.to_unified_text()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{diff::ChangePath, GeometryNode, Rectangle};
+
+    /// A collection of one rectangle, a copy with the rectangle widened to 2,
+    /// and the rectangle's uuid.
+    fn widened() -> (NodeCollection, NodeCollection, Uuid) {
+        let mut before = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        before.push(Box::new(rectangle)).unwrap();
+        let mut after = NodeCollection::from_value(before.to_value().unwrap()).unwrap();
+        after
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 2.0)
+            .unwrap();
+        (before, after, id)
+    }
+
+    #[test]
+    fn equal_collections_pass() {
+        let (before, _, _) = widened();
+
+        assert_collections_eq(&before, &before);
+    }
+
+    #[test]
+    #[should_panic(expected = "collections differ in 1 changes")]
+    fn differing_collections_panic_with_their_diff() {
+        let (before, after, _) = widened();
+
+        assert_collections_eq(&before, &after);
+    }
+
+    #[test]
+    fn the_widening_is_in_the_diff() {
+        let (before, after, id) = widened();
+
+        assert_diff_contains(
+            &before.diff(&after).unwrap(),
+            &Change::Modified {
+                path: ChangePath::node_root(&id).join("width"),
+                before: Value::from(0.0),
+                after: Value::from(2.0),
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "diff is missing the change")]
+    fn a_missing_change_panics() {
+        let (before, after, id) = widened();
+
+        assert_diff_contains(
+            &before.diff(&after).unwrap(),
+            &Change::Modified {
+                path: ChangePath::node_root(&id).join("height"),
+                before: Value::from(0.0),
+                after: Value::from(2.0),
+            },
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+
+use uuid::Uuid;
+
+use crate::{lock::EditError, NodeCollection};
+
+impl NodeCollection {
+    /// Makes [`NodeCollection::remove`] leave a tombstone with the uuid of the
+    /// removed node, so replicas syncing with this collection learn about the
+    /// removal instead of adding the node back.
+    ///
+    /// Tombstones serialize with the collection and travel with its diffs.
+    /// Importing a collection drops the nodes either side holds a tombstone for.
+    /// Applying a diff drops the nodes tombstoned once it is applied, but a node
+    /// the diff adds back clears its tombstone, like pushing a node does.
+    pub fn set_keep_tombstones(&mut self, keep: bool) {
+        self.bookkeeping.keep_tombstones = keep;
+    }
+
+    pub fn keeps_tombstones(&self) -> bool {
+        self.bookkeeping.keep_tombstones
+    }
+
+    pub fn is_tombstoned(&self, key: &Uuid) -> bool {
+        self.bookkeeping.tombstones.contains(key)
+    }
+
+    /// The uuids of the removed nodes, sorted.
+    pub fn tombstones(&self) -> impl Iterator<Item = &Uuid> + '_ {
+        self.bookkeeping.tombstones.iter()
+    }
+
+    /// Forgets all tombstones, e.g. once every replica has seen the removals.
+    pub fn purge_tombstones(&mut self) {
+        self.bookkeeping.tombstones.clear();
+    }
+
+    /// Adds the nodes of `other` that `self` does not hold, with their names, layers
+    /// and draw order, and removes the nodes `other` holds a tombstone for.
+    ///
+    /// Nodes either side tombstoned are not added, and nodes both hold keep the
+    /// content of `self`. The tombstones of `other` are kept, so the removals
+    /// propagate on to the next replica. Nodes `self` holds locked are kept
+    /// and get no tombstone.
+    pub fn import(&mut self, other: &NodeCollection) {
+        for key in other.tombstones() {
+            if let Err(EditError::Locked { .. }) = self.remove(key) {
+                continue;
+            }
+            self.bookkeeping.tombstones.insert(*key);
+        }
+
+        let mut added: Vec<&Uuid> = other
+            .nodes
+            .keys()
+            .filter(|id| !self.nodes.contains_key(id) && !self.is_tombstoned(id))
+            .collect();
+        added.sort_by_key(|id| (other.index_of(id), **id));
+        for id in added {
+            // Locks go with their node, so nodes `self` does not hold are never locked.
+            self.insert_node(other.nodes[id].clone_box());
+            self.copy_bookkeeping(other, id, *id);
+        }
+    }
+
+    /// Drops the nodes `self` holds a tombstone for, except for the `readded` ones,
+    /// whose tombstones are stale and dropped instead.
+    pub(crate) fn bury_tombstoned(&mut self, readded: &BTreeSet<Uuid>) {
+        for id in readded {
+            self.bookkeeping.tombstones.remove(id);
+        }
+        let buried: Vec<Uuid> = self
+            .nodes
+            .keys()
+            .filter(|id| self.bookkeeping.tombstones.contains(id))
+            .copied()
+            .collect();
+        for id in buried {
+            self.nodes.remove(&id);
+            self.bookkeeping.forget(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeometryNode, Rectangle};
+
+    /// A replica holding one rectangle, a copy of it that removed the rectangle
+    /// leaving a tombstone, and the rectangle's uuid.
+    fn replicas() -> (NodeCollection, NodeCollection, Uuid) {
+        let mut stale = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        stale.push(Box::new(rectangle)).unwrap();
+        let mut removed = stale.snapshot();
+        removed.set_keep_tombstones(true);
+        removed.remove(&id).unwrap();
+        (stale, removed, id)
+    }
+
+    #[test]
+    fn importing_a_stale_replica_does_not_resurrect_a_tombstoned_node() {
+        let (stale, mut removed, id) = replicas();
+
+        removed.import(&stale);
+
+        assert!(removed.get(&id).is_none());
+        assert!(removed.is_tombstoned(&id));
+    }
+
+    #[test]
+    fn importing_a_tombstone_removes_the_node() {
+        let (mut stale, removed, id) = replicas();
+
+        stale.import(&removed);
+
+        assert!(stale.get(&id).is_none());
+        assert_eq!(stale.tombstones().collect::<Vec<_>>(), [&id]);
+    }
+
+    #[test]
+    fn a_diff_adding_the_node_back_clears_its_tombstone() {
+        let (stale, mut removed, id) = replicas();
+        let readd = removed.diff(&stale).unwrap();
+
+        removed.apply(&readd).unwrap();
+
+        assert!(removed.get(&id).is_some());
+        assert!(!removed.is_tombstoned(&id));
+    }
+
+    #[test]
+    fn purging_forgets_the_tombstones() {
+        let (stale, mut removed, id) = replicas();
+        removed.purge_tombstones();
+
+        removed.import(&stale);
+
+        assert!(removed.get(&id).is_some());
+    }
+}
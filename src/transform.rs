@@ -0,0 +1,622 @@
+use std::f64::consts::PI;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    bounds::{node_bounds, BoundingBox},
+    events::NodeEvent,
+    lock::EditError,
+    shapes::{Arc, Circle, Ellipse, Line, PointCloud, Polyline},
+    spatial::InvalidSpacing,
+    NodeCollection, Point3, Rectangle,
+};
+
+/// A coordinate plane through the origin to mirror geometry across.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirrorPlane {
+    /// Negates z.
+    XY,
+    /// Negates x.
+    YZ,
+    /// Negates y.
+    XZ,
+}
+
+impl MirrorPlane {
+    /// The index of the coordinate the plane negates.
+    pub fn axis(&self) -> usize {
+        match self {
+            MirrorPlane::YZ => 0,
+            MirrorPlane::XZ => 1,
+            MirrorPlane::XY => 2,
+        }
+    }
+}
+
+/// Geometry that can be moved around in space.
+pub trait Transformable {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64);
+    fn mirror(&mut self, plane: MirrorPlane);
+    /// Scales uniformly about the origin.
+    fn scale(&mut self, factor: f64);
+    /// Rotates counterclockwise about the z axis through the origin, by `angle` radians.
+    fn rotate_z(&mut self, angle: f64);
+    /// Snaps every coordinate to the nearest multiple of `grid`, see [`snap`].
+    fn quantize(&mut self, grid: f64);
+}
+
+/// Rounds `v` to the nearest multiple of `grid`. Values exactly halfway between
+/// two multiples round away from zero, like [`f64::round`], and `-0.0` becomes `0.0`.
+pub fn snap(v: f64, grid: f64) -> f64 {
+    (v / grid).round() * grid + 0.0
+}
+
+/// Rotates `[x, y]` counterclockwise about the origin.
+fn rotate_xy(x: f64, y: f64, angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+/// A placement of geometry: scaled uniformly, then rotated about the z axis,
+/// then translated, all relative to the origin.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: [f64; 3],
+    /// Counterclockwise about the z axis, in radians.
+    pub rotation: f64,
+    pub scale: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: [0.0; 3],
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    pub fn translation(dx: f64, dy: f64, dz: f64) -> Self {
+        Self {
+            translation: [dx, dy, dz],
+            ..Self::identity()
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    pub fn apply_point(&self, p: [f64; 3]) -> [f64; 3] {
+        let (x, y) = rotate_xy(p[0] * self.scale, p[1] * self.scale, self.rotation);
+        [
+            x + self.translation[0],
+            y + self.translation[1],
+            p[2] * self.scale + self.translation[2],
+        ]
+    }
+
+    pub fn apply(&self, target: &mut dyn Transformable) {
+        if self.scale != 1.0 {
+            target.scale(self.scale);
+        }
+        if self.rotation != 0.0 {
+            target.rotate_z(self.rotation);
+        }
+        let [dx, dy, dz] = self.translation;
+        target.translate(dx, dy, dz);
+    }
+
+    /// The transform applying `self` first and `outer` second.
+    pub fn then(&self, outer: &Transform) -> Transform {
+        Transform {
+            translation: outer.apply_point(self.translation),
+            rotation: self.rotation + outer.rotation,
+            scale: self.scale * outer.scale,
+        }
+    }
+}
+
+impl Transformable for Point3 {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.x += dx;
+        self.y += dy;
+        self.z += dz;
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        match plane {
+            MirrorPlane::XY => self.z = -self.z,
+            MirrorPlane::YZ => self.x = -self.x,
+            MirrorPlane::XZ => self.y = -self.y,
+        }
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.x *= factor;
+        self.y *= factor;
+        self.z *= factor;
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        (self.x, self.y) = rotate_xy(self.x, self.y, angle);
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.x = snap(self.x, grid);
+        self.y = snap(self.y, grid);
+        self.z = snap(self.z, grid);
+    }
+}
+
+impl Transformable for Rectangle {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.anchor.translate(dx, dy, dz);
+    }
+
+    /// The dimensions stay positive and the anchor keeps its [`AnchorKind`],
+    /// so flipping x or y moves the anchor to the mirror image of the opposite corner.
+    fn mirror(&mut self, plane: MirrorPlane) {
+        let mut corner = self.corner();
+        corner.mirror(plane);
+        match plane {
+            MirrorPlane::XY => (),
+            MirrorPlane::YZ => corner.x -= self.width,
+            MirrorPlane::XZ => corner.y -= self.height,
+        }
+        self.set_corner(corner);
+    }
+
+    /// As for mirroring, a negative factor keeps the dimensions positive.
+    fn scale(&mut self, factor: f64) {
+        let mut corner = self.corner();
+        corner.scale(factor);
+        self.width *= factor.abs();
+        self.height *= factor.abs();
+        if factor < 0.0 {
+            corner.x -= self.width;
+            corner.y -= self.height;
+        }
+        self.set_corner(corner);
+    }
+
+    /// Rectangles are axis aligned, so this rotates the rectangle's center
+    /// and keeps its orientation.
+    fn rotate_z(&mut self, angle: f64) {
+        let mut corner = self.corner();
+        let (cx, cy) = (self.width / 2.0, self.height / 2.0);
+        corner.translate(cx, cy, 0.0);
+        corner.rotate_z(angle);
+        corner.translate(-cx, -cy, 0.0);
+        self.set_corner(corner);
+    }
+
+    /// Snaps the corners, so the dimensions become multiples of `grid` as well.
+    fn quantize(&mut self, grid: f64) {
+        let mut corner = self.corner();
+        let far = [
+            snap(corner.x + self.width, grid),
+            snap(corner.y + self.height, grid),
+        ];
+        corner.quantize(grid);
+        self.width = far[0] - corner.x;
+        self.height = far[1] - corner.y;
+        self.set_corner(corner);
+    }
+}
+
+impl Transformable for Circle {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.center.translate(dx, dy, dz);
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        self.center.mirror(plane);
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.center.scale(factor);
+        self.radius *= factor.abs();
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        self.center.rotate_z(angle);
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.center.quantize(grid);
+    }
+}
+
+impl Transformable for Line {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.start.translate(dx, dy, dz);
+        self.end.translate(dx, dy, dz);
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        self.start.mirror(plane);
+        self.end.mirror(plane);
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.start.scale(factor);
+        self.end.scale(factor);
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        self.start.rotate_z(angle);
+        self.end.rotate_z(angle);
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.start.quantize(grid);
+        self.end.quantize(grid);
+    }
+}
+
+impl Transformable for Ellipse {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.center.translate(dx, dy, dz);
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        self.center.mirror(plane);
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.center.scale(factor);
+        self.radius_x *= factor.abs();
+        self.radius_y *= factor.abs();
+    }
+
+    /// Ellipses are axis aligned, so this rotates the center and keeps the orientation.
+    fn rotate_z(&mut self, angle: f64) {
+        self.center.rotate_z(angle);
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.center.quantize(grid);
+    }
+}
+
+impl Transformable for Arc {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.center.translate(dx, dy, dz);
+    }
+
+    /// Mirroring reverses the direction the arc runs in, so the arc keeps
+    /// running counter clockwise from the mirrored end point.
+    fn mirror(&mut self, plane: MirrorPlane) {
+        self.center.mirror(plane);
+        let end = self.start_angle + self.sweep_angle;
+        match plane {
+            MirrorPlane::XY => (),
+            MirrorPlane::YZ => self.start_angle = PI - end,
+            MirrorPlane::XZ => self.start_angle = -end,
+        }
+    }
+
+    /// A negative factor turns the arc half way around its center.
+    fn scale(&mut self, factor: f64) {
+        self.center.scale(factor);
+        self.radius *= factor.abs();
+        if factor < 0.0 {
+            self.start_angle += PI;
+        }
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        self.center.rotate_z(angle);
+        self.start_angle += angle;
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.center.quantize(grid);
+    }
+}
+
+/// The points of point clouds and polylines.
+impl Transformable for Vec<[f64; 3]> {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        for p in self.iter_mut() {
+            p[0] += dx;
+            p[1] += dy;
+            p[2] += dz;
+        }
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        for p in self.iter_mut() {
+            p[plane.axis()] = -p[plane.axis()];
+        }
+    }
+
+    fn scale(&mut self, factor: f64) {
+        for p in self.iter_mut().flatten() {
+            *p *= factor;
+        }
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        for p in self.iter_mut() {
+            (p[0], p[1]) = rotate_xy(p[0], p[1], angle);
+        }
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        for v in self.iter_mut().flatten() {
+            *v = snap(*v, grid);
+        }
+    }
+}
+
+impl Transformable for PointCloud {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.points.translate(dx, dy, dz);
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        self.points.mirror(plane);
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.points.scale(factor);
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        self.points.rotate_z(angle);
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.points.quantize(grid);
+    }
+}
+
+impl Transformable for Polyline {
+    fn translate(&mut self, dx: f64, dy: f64, dz: f64) {
+        self.points.translate(dx, dy, dz);
+    }
+
+    fn mirror(&mut self, plane: MirrorPlane) {
+        self.points.mirror(plane);
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.points.scale(factor);
+    }
+
+    fn rotate_z(&mut self, angle: f64) {
+        self.points.rotate_z(angle);
+    }
+
+    fn quantize(&mut self, grid: f64) {
+        self.points.quantize(grid);
+    }
+}
+
+impl NodeCollection {
+    /// Snaps the coordinates of every transformable node that is not frozen
+    /// to a grid with a spacing of `grid`, see [`snap`] for the rounding.
+    /// Blocks keep their transforms, their members snap on their own.
+    pub fn quantize(&mut self, grid: f64) -> Result<(), InvalidSpacing> {
+        let grid = InvalidSpacing::check(grid)?;
+        let ids: Vec<Uuid> = self.nodes.keys().copied().collect();
+        for id in &ids {
+            // Locked and non transformable nodes are skipped.
+            let _ = self.transform_node(id, |t| t.quantize(grid));
+        }
+        Ok(())
+    }
+
+    /// Translates every transformable node that is not frozen.
+    pub fn translate_all(&mut self, dx: f64, dy: f64, dz: f64) {
+        let ids: Vec<Uuid> = self.nodes.keys().copied().collect();
+        for id in &ids {
+            // Locked and non transformable nodes are skipped.
+            let _ = self.transform_node(id, |t| t.translate(dx, dy, dz));
+        }
+    }
+
+    /// Translates the listed nodes, skipping missing and non transformable ones.
+    ///
+    /// Locked nodes are skipped as well, and returned in the order they are listed.
+    pub fn translate_nodes(&mut self, ids: &[Uuid], dx: f64, dy: f64, dz: f64) -> Vec<Uuid> {
+        self.transform_nodes(ids, |t| t.translate(dx, dy, dz))
+    }
+
+    /// Mirrors the listed nodes across `plane`, skipping missing and non transformable ones.
+    ///
+    /// Locked nodes are skipped as well, and returned in the order they are listed.
+    pub fn mirror_nodes(&mut self, ids: &[Uuid], plane: MirrorPlane) -> Vec<Uuid> {
+        self.transform_nodes(ids, |t| t.mirror(plane))
+    }
+
+    fn transform_nodes(&mut self, ids: &[Uuid], f: impl Fn(&mut dyn Transformable)) -> Vec<Uuid> {
+        let mut locked = Vec::new();
+        for id in ids {
+            // Missing and non transformable nodes are skipped silently.
+            if let Err(EditError::Locked { .. }) = self.transform_node(id, &f) {
+                locked.push(*id);
+            }
+        }
+        locked
+    }
+
+    /// Moves every node whose center lies outside of `region` onto
+    /// the closest point inside of it. Frozen nodes are left in place.
+    pub fn clamp_to(&mut self, region: &BoundingBox) {
+        let strays: Vec<(Uuid, [f64; 3])> = self
+            .nodes
+            .iter()
+            .filter_map(|(id, n)| {
+                let center = node_bounds(n.as_ref())?.center();
+                let clamped = region.clamp_point(center);
+                (clamped != center).then(|| {
+                    let delta = [0, 1, 2].map(|i| clamped[i] - center[i]);
+                    (*id, delta)
+                })
+            })
+            .collect();
+
+        for (id, [dx, dy, dz]) in strays {
+            // Locked and non transformable nodes are skipped.
+            let _ = self.transform_node(&id, |t| t.translate(dx, dy, dz));
+        }
+    }
+
+    /// Runs `f` on a node that is transformable and not locked,
+    /// keeping bounds and listeners up to date.
+    ///
+    /// Fails if the node is missing, locked or not transformable.
+    pub(crate) fn transform_node(
+        &mut self,
+        key: &Uuid,
+        f: impl FnOnce(&mut dyn Transformable),
+    ) -> Result<(), EditError> {
+        let before = node_bounds(self.check_editable(key)?);
+        let node = self
+            .nodes
+            .get_mut(key)
+            .ok_or(EditError::MissingNode(*key))?;
+        let found = node.type_tag();
+        let transformable = node
+            .as_transformable_mut()
+            .ok_or(EditError::WrongType { uuid: *key, found })?;
+
+        f(transformable);
+
+        let after = node_bounds(node.as_ref());
+        self.shrink_bounds(before);
+        self.expand_bounds(after);
+        self.rehash(*key, after);
+        self.emit(NodeEvent::Modified(*key));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock::LockInfo, GeometryNode};
+
+    fn point(x: f64, y: f64, z: f64) -> Point3 {
+        let mut point = Point3::new();
+        (point.x, point.y, point.z) = (x, y, z);
+        point
+    }
+
+    /// Pushes points at the given positions, returning their uuids in order.
+    fn with_points(collection: &mut NodeCollection, positions: &[[f64; 3]]) -> Vec<Uuid> {
+        positions
+            .iter()
+            .map(|&[x, y, z]| {
+                let point = point(x, y, z);
+                let id = point.uuid();
+                collection.push(Box::new(point)).unwrap();
+                id
+            })
+            .collect()
+    }
+
+    fn position(collection: &NodeCollection, id: &Uuid) -> [f64; 3] {
+        let point = collection.try_get_typed::<Point3>(id).unwrap();
+        [point.x, point.y, point.z]
+    }
+
+    #[test]
+    fn translate_nodes_leaves_unlisted_nodes_unmoved() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[0.0, 0.0, 0.0], [1.0, 2.0, 3.0]]);
+
+        collection.translate_nodes(&ids[..1], 5.0, 0.0, -1.0);
+
+        assert_eq!(position(&collection, &ids[0]), [5.0, 0.0, -1.0]);
+        assert_eq!(position(&collection, &ids[1]), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn translate_nodes_skips_locked_nodes_of_the_selection() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[0.0, 0.0, 0.0], [1.0, 2.0, 3.0]]);
+        collection.lock(&ids[1], LockInfo::new("surveyed"));
+
+        let skipped = collection.translate_nodes(&ids, 5.0, 0.0, 0.0);
+
+        assert_eq!(skipped, [ids[1]]);
+        assert_eq!(position(&collection, &ids[0]), [5.0, 0.0, 0.0]);
+        assert_eq!(position(&collection, &ids[1]), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn clamp_pulls_a_stray_point_to_the_nearest_boundary() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[15.0, 5.0, -3.0], [2.0, 3.0, 0.0]]);
+        let region = BoundingBox::new([0.0, 0.0, 0.0], [10.0, 10.0, 1.0]);
+
+        collection.clamp_to(&region);
+
+        assert_eq!(position(&collection, &ids[0]), [10.0, 5.0, 0.0]);
+        assert_eq!(position(&collection, &ids[1]), [2.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn mirroring_a_point_across_xy_negates_its_z() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(&mut collection, &[[1.0, 2.0, 3.0]]);
+
+        collection.mirror_nodes(&ids, MirrorPlane::XY);
+
+        assert_eq!(position(&collection, &ids[0]), [1.0, 2.0, -3.0]);
+    }
+
+    #[test]
+    fn mirroring_a_rectangle_keeps_its_dimensions_positive() {
+        let mut rectangle = Rectangle::new();
+        *rectangle.width_mut() = 4.0;
+        *rectangle.height_mut() = 2.0;
+        rectangle.anchor_mut().x = 1.0;
+
+        rectangle.mirror(MirrorPlane::YZ);
+
+        assert_eq!((rectangle.width, rectangle.height), (4.0, 2.0));
+        let corner = rectangle.corner();
+        assert_eq!((corner.x, corner.y), (-5.0, 0.0));
+    }
+
+    #[test]
+    fn quantize_snaps_points_near_grid_intersections() {
+        let mut collection = NodeCollection::new();
+        let ids = with_points(
+            &mut collection,
+            &[
+                [0.49, 1.51, -0.01],
+                [0.25, -0.75, 1.0],
+                [2.0000001, -1.9999999, 0.0],
+            ],
+        );
+
+        collection.quantize(0.5).unwrap();
+
+        assert_eq!(position(&collection, &ids[0]), [0.5, 1.5, 0.0]);
+        // halfway values round away from zero
+        assert_eq!(position(&collection, &ids[1]), [0.5, -1.0, 1.0]);
+        assert_eq!(position(&collection, &ids[2]), [2.0, -2.0, 0.0]);
+    }
+
+    #[test]
+    fn quantize_rejects_invalid_grids() {
+        let mut collection = NodeCollection::new();
+
+        assert_eq!(collection.quantize(0.0), Err(InvalidSpacing(0.0)));
+        assert!(collection.quantize(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    bounds::BoundingBox, concrete_node, diff::Diff, error::GeodiffError, raycast::Ray,
+    stats::CollectionStats, GeometryNode, NodeCollection,
+};
+
+/// A read only handle to a collection, for handing it to code that must not edit it.
+///
+/// Unlike a plain `&NodeCollection` the view offers no way to reach the
+/// mutators, so it is safe to pass on to plugins. Caches the collection
+/// keeps, like its bounds, are still filled on demand.
+#[derive(Clone, Copy)]
+pub struct NodeCollectionView<'a> {
+    collection: &'a NodeCollection,
+}
+
+impl<'a> NodeCollectionView<'a> {
+    pub fn len(&self) -> usize {
+        self.collection.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collection.is_empty()
+    }
+
+    pub fn contains(&self, key: &Uuid) -> bool {
+        self.collection.get(key).is_some()
+    }
+
+    pub fn get(&self, key: &Uuid) -> Option<&'a dyn GeometryNode> {
+        self.collection.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a dyn GeometryNode> + 'a {
+        self.collection.iter()
+    }
+
+    pub fn try_get_typed<T>(&self, key: &Uuid) -> Option<&'a T>
+    where
+        T: GeometryNode + 'static,
+    {
+        self.collection.get(key).and_then(concrete_node::<T>)
+    }
+
+    pub fn name(&self, key: &Uuid) -> Option<&'a str> {
+        self.collection.name(key)
+    }
+
+    pub fn layer(&self, key: &Uuid) -> Option<&'a str> {
+        self.collection.layer(key)
+    }
+
+    pub fn nodes_on_layer(&self, layer: &str) -> Vec<Uuid> {
+        self.collection.nodes_on_layer(layer)
+    }
+
+    pub fn is_frozen(&self, key: &Uuid) -> bool {
+        self.collection.is_frozen(key)
+    }
+
+    pub fn z_index(&self, key: &Uuid) -> i64 {
+        self.collection.z_index(key)
+    }
+
+    pub fn ordered_for_draw(&self) -> Vec<Uuid> {
+        self.collection.ordered_for_draw()
+    }
+
+    pub fn extra_field(&self, key: &Uuid, name: &str) -> Option<&'a Value> {
+        self.collection.extra_field(key, name)
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.collection.bounds()
+    }
+
+    pub fn query_region(&self, region: &BoundingBox) -> Vec<Uuid> {
+        self.collection.query_region(region)
+    }
+
+    pub fn nearby_nodes(&self, key: &Uuid) -> Vec<Uuid> {
+        self.collection.nearby_nodes(key)
+    }
+
+    pub fn raycast(&self, ray: &Ray) -> Option<(Uuid, f64)> {
+        self.collection.raycast(ray)
+    }
+
+    pub fn stats(&self) -> CollectionStats {
+        self.collection.stats()
+    }
+
+    /// Diffs the viewed collection against `other`, see [`NodeCollection::diff`].
+    pub fn diff(&self, other: &NodeCollection) -> Result<Diff, GeodiffError> {
+        self.collection.diff(other)
+    }
+
+    /// Copies the viewed collection to json, see [`NodeCollection::to_value`].
+    pub fn to_value(&self) -> Result<Value, GeodiffError> {
+        self.collection.to_value()
+    }
+}
+
+impl NodeCollection {
+    /// A read only view of this collection.
+    pub fn view(&self) -> NodeCollectionView<'_> {
+        NodeCollectionView { collection: self }
+    }
+}
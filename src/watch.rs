@@ -0,0 +1,159 @@
+use uuid::Uuid;
+
+use crate::{
+    diff::{node_value, NodeChange, NodeChangeKind},
+    domain::diff_nodes,
+    events::NodeEvent,
+    GeometryNode, NodeCollection,
+};
+
+pub type NodeListener = Box<dyn FnMut(&NodeChange) + Send + Sync>;
+
+/// Identifies a listener registered by [`NodeCollection::watch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchHandle(u64);
+
+struct Watch {
+    handle: WatchHandle,
+    uuid: Uuid,
+    /// The node as of the last time the listener was called, `None` while it is absent.
+    last: Option<Box<dyn GeometryNode>>,
+    listener: NodeListener,
+}
+
+/// The listeners watching single nodes of a collection.
+#[derive(Default)]
+pub(crate) struct Watchers {
+    next_handle: u64,
+    watches: Vec<Watch>,
+}
+
+/// How a watched node changed since `before`, `None` if it did not.
+fn node_change(
+    before: Option<&dyn GeometryNode>,
+    after: Option<&dyn GeometryNode>,
+) -> Option<NodeChangeKind> {
+    match (before, after) {
+        (None, None) => None,
+        (None, Some(after)) => Some(NodeChangeKind::Added(node_value(after))),
+        (Some(before), None) => Some(NodeChangeKind::Removed(node_value(before))),
+        (Some(before), Some(after)) if before.type_tag() != after.type_tag() => {
+            Some(NodeChangeKind::TypeChanged {
+                from: before.type_tag().to_string(),
+                to: after.type_tag().to_string(),
+            })
+        }
+        (Some(before), Some(after)) => {
+            let changes = diff_nodes(before, after);
+            (!changes.is_empty()).then_some(NodeChangeKind::Modified(changes))
+        }
+    }
+}
+
+impl NodeCollection {
+    /// Registers a listener that is called with the changes of a single node
+    /// after every edit that touched it, like [`NodeCollection::on_change`]
+    /// but quiet about all other nodes. The node does not have to exist yet.
+    ///
+    /// Edits during a [`NodeCollection::batch`] are reported once, when it ends.
+    /// The changes carry the author current at the time of the report,
+    /// see [`NodeCollection::set_current_author`].
+    pub fn watch(&mut self, uuid: Uuid, cb: NodeListener) -> WatchHandle {
+        let handle = WatchHandle(self.watchers.next_handle);
+        self.watchers.next_handle += 1;
+        self.watchers.watches.push(Watch {
+            handle,
+            uuid,
+            last: self.nodes.get(&uuid).map(|n| n.clone_box()),
+            listener: cb,
+        });
+        handle
+    }
+
+    /// Removes a listener registered by [`NodeCollection::watch`].
+    /// Returns false if it was removed already.
+    pub fn unwatch(&mut self, handle: WatchHandle) -> bool {
+        let len = self.watchers.watches.len();
+        self.watchers.watches.retain(|w| w.handle != handle);
+        self.watchers.watches.len() != len
+    }
+
+    /// Calls the listeners watching the nodes `event` is about.
+    pub(crate) fn notify_watchers(&mut self, event: &NodeEvent) {
+        let uuids = event.uuids();
+        let author = self.current_author().map(str::to_string);
+        for watch in &mut self.watchers.watches {
+            if !uuids.contains(&watch.uuid) {
+                continue;
+            }
+            let current = self.nodes.get(&watch.uuid);
+            if let Some(kind) = node_change(watch.last.as_deref(), current.map(|n| n.as_ref())) {
+                (watch.listener)(&NodeChange {
+                    kind,
+                    author: author.clone(),
+                });
+            }
+            watch.last = current.map(|n| n.clone_box());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::Rectangle;
+
+    /// A listener collecting the changes it is called with.
+    fn recorder() -> (NodeListener, Arc<Mutex<Vec<NodeChange>>>) {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&changes);
+        let listener = Box::new(move |change: &NodeChange| {
+            recorded.lock().unwrap().push(change.clone());
+        });
+        (listener, changes)
+    }
+
+    #[test]
+    fn editing_a_fires_only_the_watcher_of_a() {
+        let mut collection = NodeCollection::new();
+        let (a, b) = (Rectangle::new(), Rectangle::new());
+        let (a_id, b_id) = (a.uuid(), b.uuid());
+        collection.push(Box::new(a)).unwrap();
+        collection.push(Box::new(b)).unwrap();
+        let (a_listener, a_changes) = recorder();
+        let (b_listener, b_changes) = recorder();
+        collection.watch(a_id, a_listener);
+        collection.watch(b_id, b_listener);
+
+        collection
+            .update(&a_id, |r: &mut Rectangle| *r.width_mut() = 2.0)
+            .unwrap();
+
+        let a_changes = a_changes.lock().unwrap();
+        assert_eq!(a_changes.len(), 1);
+        assert!(
+            matches!(&a_changes[0].kind, NodeChangeKind::Modified(changes) if changes.len() == 1)
+        );
+        assert!(b_changes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_unwatched_listener_is_not_called() {
+        let mut collection = NodeCollection::new();
+        let rectangle = Rectangle::new();
+        let id = rectangle.uuid();
+        collection.push(Box::new(rectangle)).unwrap();
+        let (listener, changes) = recorder();
+        let handle = collection.watch(id, listener);
+
+        assert!(collection.unwatch(handle));
+        collection
+            .update(&id, |r: &mut Rectangle| *r.width_mut() = 2.0)
+            .unwrap();
+
+        assert!(changes.lock().unwrap().is_empty());
+        assert!(!collection.unwatch(handle));
+    }
+}
@@ -0,0 +1,100 @@
+use uuid::Uuid;
+
+use crate::{concrete_node, shapes::Polyline, NodeCollection};
+
+impl Polyline {
+    /// The area enclosed by the points in the xy plane, joined back to the first,
+    /// positive if they run counterclockwise and negative if clockwise.
+    pub fn signed_area(&self) -> f64 {
+        let n = self.points.len();
+        let twice: f64 = (0..n)
+            .map(|i| {
+                let (a, b) = (self.points[i], self.points[(i + 1) % n]);
+                a[0] * b[1] - b[0] * a[1]
+            })
+            .sum();
+        twice / 2.0
+    }
+
+    /// True if the points run counterclockwise. Degenerate polylines
+    /// enclosing no area run neither way.
+    pub fn is_ccw(&self) -> bool {
+        self.signed_area() > 0.0
+    }
+
+    /// Reverses the points if they run clockwise. Returns whether they were reversed.
+    pub fn ensure_ccw(&mut self) -> bool {
+        let clockwise = self.signed_area() < 0.0;
+        if clockwise {
+            self.points.reverse();
+        }
+        clockwise
+    }
+}
+
+impl NodeCollection {
+    /// Makes all closed polylines that are not frozen run counterclockwise,
+    /// see [`Polyline::ensure_ccw`]. Open polylines are left as they are.
+    pub fn normalize_winding(&mut self) {
+        let clockwise: Vec<Uuid> = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| {
+                concrete_node::<Polyline>(n.as_ref())
+                    .is_some_and(|p| p.closed && p.signed_area() < 0.0)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in clockwise {
+            // Locked polylines are skipped.
+            let _ = self.update(&id, |p: &mut Polyline| {
+                p.ensure_ccw();
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeometryNode;
+
+    /// The unit square, clockwise.
+    fn clockwise_square(closed: bool) -> Polyline {
+        let mut square = Polyline::new();
+        for [x, y] in [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]] {
+            square.push(x, y, 0.0);
+        }
+        square.closed = closed;
+        square
+    }
+
+    #[test]
+    fn a_clockwise_square_is_reversed_to_counterclockwise() {
+        let mut square = clockwise_square(true);
+        assert_eq!(square.signed_area(), -1.0);
+        assert!(!square.is_ccw());
+
+        assert!(square.ensure_ccw());
+
+        assert!(square.is_ccw());
+        assert_eq!(square.points[0], [1.0, 0.0, 0.0]);
+        assert!(!square.ensure_ccw());
+    }
+
+    #[test]
+    fn only_closed_polylines_are_normalized() {
+        let (closed, open) = (clockwise_square(true), clockwise_square(false));
+        let (closed_id, open_id) = (closed.uuid(), open.uuid());
+        let mut collection = NodeCollection::new();
+        collection.push(Box::new(closed)).unwrap();
+        collection.push(Box::new(open)).unwrap();
+
+        collection.normalize_winding();
+
+        let is_ccw = |id| collection.try_get_typed::<Polyline>(id).unwrap().is_ccw();
+        assert!(is_ccw(&closed_id));
+        assert!(!is_ccw(&open_id));
+    }
+}